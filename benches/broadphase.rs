@@ -0,0 +1,47 @@
+use bevy::prelude::*;
+use criterion::{criterion_group, criterion_main, Criterion};
+use seb_mul_game::game::{
+    step, Mass, Owner, PhysicsConfig, PlayerId, Position, Radius, Velocity, FIXED_TIMESTEP,
+};
+
+const PIECES: usize = 5000;
+
+/// Pieces on a square grid, each just overlapping its neighbours, so every
+/// step has real contacts to resolve.  No randomness: runs are comparable.
+fn scene(parallel_threshold: usize) -> World {
+    let mut world = World::new();
+    world.insert_resource(PhysicsConfig {
+        parallel_threshold,
+        ..PhysicsConfig::default()
+    });
+
+    let side = (PIECES as f32).sqrt().ceil() as usize;
+    for i in 0..PIECES {
+        let (col, row) = (i % side, i / side);
+        world.spawn((
+            Position(Vec2::new(4.0 + col as f32 * 4.0, 4.0 + row as f32 * 4.0)),
+            Velocity(Vec2::new(if row % 2 == 0 { 10.0 } else { -10.0 }, 0.0)),
+            Mass(1.0),
+            Radius(2.1),
+            Owner(PlayerId((i % 2) as u32)),
+        ));
+    }
+    world
+}
+
+fn broadphase(c: &mut Criterion) {
+    let mut group = c.benchmark_group("broadphase_5000");
+    group.sample_size(10);
+
+    for (name, threshold) in [("serial", usize::MAX), ("parallel", 0)] {
+        group.bench_function(name, |b| {
+            let mut world = scene(threshold);
+            b.iter(|| step(&mut world, FIXED_TIMESTEP));
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, broadphase);
+criterion_main!(benches);
@@ -0,0 +1,80 @@
+use bevy::prelude::*;
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use seb_mul_game::game::{
+    step_board, step_collisions, Mass, Owner, PlayerId, Position, Radius, Velocity,
+};
+
+const SIZES: [usize; 3] = [100, 1_000, 10_000];
+
+/// `n` pieces on a square grid, each just overlapping its neighbours, with
+/// alternate rows moving in opposite directions.  No randomness: runs are
+/// comparable across machines and commits.
+fn scene(n: usize) -> World {
+    let mut world = World::new();
+    let side = (n as f32).sqrt().ceil() as usize;
+    for i in 0..n {
+        let (col, row) = (i % side, i / side);
+        world.spawn((
+            Position(Vec2::new(4.0 + col as f32 * 4.0, 4.0 + row as f32 * 4.0)),
+            Velocity(Vec2::new(if row % 2 == 0 { 10.0 } else { -10.0 }, 0.0)),
+            Mass(1.0),
+            Radius(2.1),
+            Owner(PlayerId((i % 2) as u32)),
+        ));
+    }
+    world
+}
+
+/// Overlapping pairs in the scene — the work the narrowphase actually does.
+fn overlapping_pairs(world: &mut World) -> u64 {
+    let bodies: Vec<(Vec2, f32)> = world
+        .query::<(&Position, &Radius)>()
+        .iter(world)
+        .map(|(p, r)| (p.0, r.0))
+        .collect();
+    let mut pairs = 0;
+    for (i, &(p1, r1)) in bodies.iter().enumerate() {
+        for &(p2, r2) in &bodies[i + 1..] {
+            if p1.distance_squared(p2) < (r1 + r2) * (r1 + r2) {
+                pairs += 1;
+            }
+        }
+    }
+    pairs
+}
+
+fn resolve_collisions(c: &mut Criterion) {
+    let mut group = c.benchmark_group("resolve_collisions");
+    group.sample_size(10);
+
+    for n in SIZES {
+        // Throughput is in overlapping pairs, so the report shows pairs/s
+        // next to the time per step.
+        let pairs = overlapping_pairs(&mut scene(n));
+        println!("resolve_collisions/{n}: {pairs} overlapping pairs");
+        group.throughput(Throughput::Elements(pairs));
+        group.bench_with_input(BenchmarkId::from_parameter(n), &n, |b, &n| {
+            // Each step moves pieces apart, so every iteration starts fresh.
+            b.iter_batched(|| scene(n), |mut world| step_collisions(&mut world), BatchSize::LargeInput);
+        });
+    }
+
+    group.finish();
+}
+
+fn rebuild_board(c: &mut Criterion) {
+    let mut group = c.benchmark_group("rebuild_board");
+
+    for n in SIZES {
+        group.throughput(Throughput::Elements(n as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(n), &n, |b, &n| {
+            let mut world = scene(n);
+            b.iter(|| step_board(&mut world));
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, resolve_collisions, rebuild_board);
+criterion_main!(benches);
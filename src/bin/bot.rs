@@ -0,0 +1,7 @@
+use clap::Parser;
+use seb_mul_game::bot::{self, BotArgs};
+
+#[tokio::main]
+async fn main() {
+    bot::run(BotArgs::parse()).await;
+}
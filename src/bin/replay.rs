@@ -0,0 +1,194 @@
+use clap::{ArgAction, Parser};
+use seb_mul_game::board::BoardState;
+use seb_mul_game::logger::Logger;
+use seb_mul_game::replay::{logged_state, ReplayRecord, REPLAY_VERSION};
+use seb_mul_game::state::GameState;
+use std::io::{self, BufRead, Write as _};
+use std::path::PathBuf;
+use std::time::Duration;
+
+// ── CLI ───────────────────────────────────────────────────────────────────────
+
+#[derive(Parser, Debug)]
+#[command(
+    name    = "replay",
+    version,
+    about   = "Seb n Vic Multiplayer Game — replay viewer",
+    long_about = "Re-runs a .replay file written by `server --replay-dir` through the\n\
+                  authoritative rules and prints the board after every move.\n\
+                  Any move the rules reject is reported as a determinism error.\n\n\
+                  With --from-log, reads a server log instead and prints every\n\
+                  board traced in it (run the server with -vvv or\n\
+                  --log-level board=trace for those)."
+)]
+struct Args {
+    /// Replay file to play back
+    file: PathBuf,
+
+    /// Treat FILE as a server log and print the boards traced in it
+    #[arg(long)]
+    from_log: bool,
+
+    /// With --from-log, only show this game's boards
+    #[arg(long, value_name = "ID", requires = "from_log")]
+    game: Option<u64>,
+
+    /// Wait for Enter before each move
+    #[arg(short, long)]
+    step: bool,
+
+    /// Pause between moves, in milliseconds
+    #[arg(short, long, default_value_t = 0, value_name = "MS")]
+    delay: u64,
+
+    /// Increase output verbosity (-v verbose, -vv debug, -vvv trace)
+    #[arg(short, long, action = ArgAction::Count)]
+    verbose: u8,
+}
+
+// ── MAIN ──────────────────────────────────────────────────────────────────────
+
+fn main() {
+    let args = Args::parse();
+    let log  = Logger::new(args.verbose);
+
+    let file = match std::fs::File::open(&args.file) {
+        Ok(f) => f,
+        Err(e) => {
+            eprintln!("Failed to open {}: {e}", args.file.display());
+            std::process::exit(1);
+        }
+    };
+
+    if args.from_log {
+        from_log(&args, file);
+        return;
+    }
+
+    let mut state    = GameState::new();
+    let mut moves    = 0usize;
+    let mut rejected = 0usize;
+    let mut stdin    = io::stdin().lock();
+
+    for (n, line) in io::BufReader::new(file).lines().enumerate() {
+        let lineno = n + 1;
+        let line = match line {
+            Ok(l) => l,
+            Err(e) => {
+                eprintln!("Read error at line {lineno}: {e}");
+                std::process::exit(1);
+            }
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        log.trace(format_args!("line {lineno}: {line}"));
+
+        let record: ReplayRecord = match serde_json::from_str(&line) {
+            Ok(r) => r,
+            Err(e) => {
+                log.warn(format_args!("line {lineno}: malformed record ({e}) — skipped"));
+                continue;
+            }
+        };
+
+        match record {
+            ReplayRecord::Header { version, game_id, seed, rules, started_ms } => {
+                if version != REPLAY_VERSION {
+                    log.warn(format_args!(
+                        "replay version {version} differs from supported version {REPLAY_VERSION}"
+                    ));
+                }
+                log.verbose(format_args!("rules: {rules:?}"));
+                state = GameState::with_rules(seed, rules);
+                println!("Game {game_id}, seed {seed} (recorded at {started_ms} ms since epoch)");
+                println!("P{} moves first.", state.turn());
+                println!("Board:\n{}", BoardState::from(&state));
+            }
+            ReplayRecord::Move { player, at_ms, cmd } => {
+                moves += 1;
+                if args.step {
+                    print!("\n[Enter] for move {moves}…");
+                    io::stdout().flush().ok();
+                    let mut buf = String::new();
+                    if stdin.read_line(&mut buf).unwrap_or(0) == 0 {
+                        break;
+                    }
+                } else if args.delay > 0 {
+                    std::thread::sleep(Duration::from_millis(args.delay));
+                }
+
+                log.verbose(format_args!("move {moves} at {at_ms} ms"));
+                println!("\nMove {moves}: P{player} {cmd}");
+                let out_before = state.eliminated().len();
+                match cmd.apply(&mut state, player) {
+                    Ok(()) => {
+                        println!("Board:\n{}", BoardState::from(&state));
+                        for out in &state.eliminated()[out_before..] {
+                            println!("P{out} has no legal move and is out.");
+                        }
+                        if let Err(reason) = state.check_invariants() {
+                            log.warn(format_args!("move {moves} broke an invariant: {reason}"));
+                        }
+                        if let Some((a, b)) = state.overlapping_pair() {
+                            log.verbose(format_args!("pieces #{a} and #{b} overlap after move {moves}"));
+                        }
+                    }
+                    Err(reason) => {
+                        rejected += 1;
+                        log.warn(format_args!(
+                            "line {lineno}: move {moves} rejected by the rules: {reason}"
+                        ));
+                    }
+                }
+            }
+            ReplayRecord::Undo { at_ms } => {
+                log.verbose(format_args!("undo at {at_ms} ms"));
+                println!("\nUndo: the last move is taken back");
+                match state.undo() {
+                    Ok(()) => println!("Board:\n{}", BoardState::from(&state)),
+                    Err(reason) => {
+                        rejected += 1;
+                        log.warn(format_args!("line {lineno}: undo rejected by the rules: {reason}"));
+                    }
+                }
+            }
+            ReplayRecord::End { outcome, .. } => {
+                println!("\nGame over: {outcome}");
+            }
+        }
+    }
+
+    println!("\n{moves} move(s) replayed, {rejected} rejected.");
+    if rejected > 0 {
+        std::process::exit(2);
+    }
+}
+
+/// Print each board in the server log `file`, in the order they were
+/// logged.  Lines of other games, and anything but `STATE` traces, are
+/// skipped.
+fn from_log(args: &Args, file: std::fs::File) {
+    let mut boards = 0usize;
+    for (n, line) in io::BufReader::new(file).lines().enumerate() {
+        let line = match line {
+            Ok(l) => l,
+            Err(e) => {
+                eprintln!("Read error at line {}: {e}", n + 1);
+                std::process::exit(1);
+            }
+        };
+        let Some((game, board)) = logged_state(&line) else { continue };
+        if args.game.is_some_and(|want| want != game) {
+            continue;
+        }
+        boards += 1;
+        println!("\nGame {game}, state {}:", board.seq);
+        println!("Board:\n{board}");
+    }
+
+    println!("\n{boards} board(s) found.");
+    if boards == 0 {
+        std::process::exit(2);
+    }
+}
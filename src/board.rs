@@ -0,0 +1,159 @@
+use crate::protocol::WireF32;
+use crate::state::GameState;
+use std::fmt;
+
+/// A piece as seen by a client, numbered by its position in `STATE`.
+///
+/// Under fog of war the server may withhold an enemy piece's position or
+/// radius; withheld values are `NaN` here and `?` on the wire.
+#[derive(Debug, Clone)]
+pub struct Piece {
+    pub index:  usize,
+    pub owner:  u8,
+    pub x:      f32,
+    pub y:      f32,
+    pub radius: f32,
+}
+
+/// Client-side view of the board, rebuilt from each `STATE` line.
+#[derive(Debug, Clone)]
+pub struct BoardState {
+    pub seq:    u64,
+    pub tick:   u64,
+    pub pieces: Vec<Piece>,
+}
+
+impl BoardState {
+    /// Parse the payload after `STATE `:
+    /// `<seq> <tick> <n> [<owner> <x> <y> <r>]×n`.
+    /// Any of `<x> <y> <r>` may be `?` for a value hidden by fog of war.
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut t = line.split_whitespace();
+        let seq: u64 = t.next()?.parse().ok()?;
+        let tick: u64 = t.next()?.parse().ok()?;
+        let n: usize = t.next()?.parse().ok()?;
+        // `n` is untrusted: never reserve more pieces than the line could hold.
+        let mut pieces = Vec::with_capacity(n.min(line.len() / 8));
+        for index in 0..n {
+            pieces.push(Piece {
+                index,
+                owner:  t.next()?.parse().ok()?,
+                x:      parse_value(t.next()?)?,
+                y:      parse_value(t.next()?)?,
+                radius: parse_value(t.next()?)?,
+            });
+        }
+        Some(Self { seq, tick, pieces })
+    }
+
+    /// Whether a circle at `(x, y)` would overlap any piece, using the same
+    /// test as the server.  Hidden pieces never count as overlapping.
+    pub fn overlaps(&self, x: f32, y: f32, radius: f32) -> bool {
+        self.pieces.iter().any(|p| {
+            let dist = ((p.x - x).powi(2) + (p.y - y).powi(2)).sqrt();
+            dist < p.radius + radius
+        })
+    }
+
+    /// Inverse of [`BoardState::parse`]. Each piece serialises as
+    /// `<owner> <x> <y> <radius>`, each value as a [`WireF32`].
+    pub fn wire_payload(&self) -> String {
+        let body: Vec<String> = self
+            .pieces
+            .iter()
+            .map(|p| {
+                format!("{} {} {} {}", p.owner, wire_value(p.x), wire_value(p.y), wire_value(p.radius))
+            })
+            .collect();
+        format!("{} {} {} {}", self.seq, self.tick, self.pieces.len(), body.join(" "))
+    }
+}
+
+/// Side of the square canvas [`BoardState::to_svg`] draws on, in pixels.
+pub const SVG_SIZE: f32 = 512.0;
+
+/// Fill colour for each owner, cycling for larger tables.
+const SVG_COLORS: [&str; 8] = [
+    "#e6194b", "#3cb44b", "#4363d8", "#f58231", "#911eb4", "#42d4f4", "#f032e6", "#9a6324",
+];
+
+impl BoardState {
+    /// Draw the board as a standalone SVG image: each piece is a circle
+    /// coloured by owner and labelled with its index, on a square canvas
+    /// covering `0..board_size` on both axes.  Pieces whose position is
+    /// hidden are left out; a hidden radius is drawn as a small dashed ring.
+    pub fn to_svg(&self, board_size: f32) -> String {
+        let scale = SVG_SIZE / board_size;
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{SVG_SIZE}\" height=\"{SVG_SIZE}\" \
+             viewBox=\"0 0 {SVG_SIZE} {SVG_SIZE}\">\n\
+             <rect width=\"{SVG_SIZE}\" height=\"{SVG_SIZE}\" fill=\"#f4f1e8\" stroke=\"#333\"/>\n"
+        );
+        for p in self.pieces.iter().filter(|p| !p.x.is_nan() && !p.y.is_nan()) {
+            let (cx, cy) = (p.x * scale, p.y * scale);
+            let color = SVG_COLORS[p.owner as usize % SVG_COLORS.len()];
+            let ring = if p.radius.is_nan() {
+                format!("r=\"4\" fill=\"none\" stroke=\"{color}\" stroke-dasharray=\"2 2\"")
+            } else {
+                format!("r=\"{:.2}\" fill=\"{color}\" fill-opacity=\"0.8\" stroke=\"#222\"", p.radius * scale)
+            };
+            svg.push_str(&format!(
+                "<circle cx=\"{cx:.2}\" cy=\"{cy:.2}\" {ring}/>\n\
+                 <text x=\"{cx:.2}\" y=\"{cy:.2}\" font-family=\"sans-serif\" font-size=\"12\" \
+                 text-anchor=\"middle\" dominant-baseline=\"central\">{}</text>\n",
+                p.index
+            ));
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+fn parse_value(token: &str) -> Option<f32> {
+    if token == "?" { Some(f32::NAN) } else { token.parse().ok() }
+}
+
+fn wire_value(v: f32) -> String {
+    if v.is_nan() { "?".to_string() } else { WireF32(v).to_string() }
+}
+
+/// Offline tools render the authoritative state through the same view.
+impl From<&GameState> for BoardState {
+    fn from(state: &GameState) -> Self {
+        let pieces = state
+            .pieces()
+            .iter()
+            .enumerate()
+            .map(|(index, p)| Piece { index, owner: p.owner, x: p.x, y: p.y, radius: p.radius })
+            .collect();
+        Self { seq: state.seq(), tick: state.tick(), pieces }
+    }
+}
+
+/// Piece renders as a compact single-line summary.
+impl fmt::Display for Piece {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.x.is_nan() || self.y.is_nan() {
+            return write!(f, "  #{:<2}  P{}  (out of sight)", self.index, self.owner);
+        }
+        write!(
+            f,
+            "  #{:<2}  P{}  pos=({:>8.2}, {:>8.2})  radius=",
+            self.index, self.owner, self.x, self.y
+        )?;
+        if self.radius.is_nan() { write!(f, "?") } else { write!(f, "{:.2}", self.radius) }
+    }
+}
+
+/// Board renders as a labelled list of all pieces.
+impl fmt::Display for BoardState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.pieces.is_empty() {
+            return write!(f, "  (board is empty)");
+        }
+        for piece in &self.pieces {
+            writeln!(f, "{piece}")?;
+        }
+        Ok(())
+    }
+}
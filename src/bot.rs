@@ -0,0 +1,322 @@
+use crate::board::{BoardState, Piece};
+use crate::client::{parse_name, GameClient};
+use crate::logger::Logger;
+use crate::net::{compose_candidates, parse_candidates, Candidates};
+use crate::protocol::{ClientCmd, Phase, RulesInfo, ServerMsg};
+use crate::replay::now_ms;
+use crate::rng::Rng;
+use crate::state::Rules;
+use clap::{ArgAction, Parser, ValueEnum};
+use std::f32::consts::TAU;
+use tokio::time::{sleep, Duration};
+
+// ── CLI ───────────────────────────────────────────────────────────────────────
+
+/// Command-line options for the computer player.
+#[derive(Parser, Debug)]
+#[command(
+    name    = "bot",
+    version,
+    about   = "Seb n Vic Multiplayer Game — computer player",
+    long_about = "Connects to a running game server like any other client and plays\n\
+                  one game on its own, for testing or to fill an empty seat.\n\
+                  It agrees to every draw offer and undo request."
+)]
+pub struct BotArgs {
+    /// Full server address; overrides --host and --port
+    #[arg(value_parser = parse_candidates)]
+    addr: Option<Candidates>,
+
+    /// Server IP address or hostname; every address it resolves to is tried
+    #[arg(long, default_value = "127.0.0.1")]
+    host: String,
+
+    /// Server port
+    #[arg(short, long, default_value_t = 7878)]
+    port: u16,
+
+    /// How the bot chooses its moves
+    #[arg(long, value_enum, default_value_t = Strategy::Random)]
+    strategy: Strategy,
+
+    /// Seed for the bot's choices (default: taken from the clock)
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Pause before each move, in milliseconds
+    #[arg(long, default_value_t = 0, value_name = "MS")]
+    think: u64,
+
+    /// Name to play under, sent to the server on connecting
+    #[arg(long, value_parser = parse_name)]
+    name: Option<String>,
+
+    /// Seconds to keep trying the server's addresses before giving up
+    #[arg(long, default_value_t = 10, value_name = "SECS")]
+    connect_timeout: u64,
+
+    /// Increase output verbosity (-v verbose, -vv debug, -vvv trace)
+    #[arg(short, long, action = ArgAction::Count)]
+    verbose: u8,
+}
+
+// ── STRATEGY ──────────────────────────────────────────────────────────────────
+
+/// How a bot picks its moves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Strategy {
+    /// Place or shoot at random.
+    Random,
+    /// Crowd the enemy: place large pieces against theirs, then shoot
+    /// pieces up to them.
+    Aggressive,
+}
+
+/// Everything a strategy is told when it is the bot's turn.
+pub struct View<'a> {
+    pub me:    u8,
+    pub board: Option<&'a BoardState>,
+    pub rules: RulesInfo,
+    pub phase: Phase,
+}
+
+/// Space left between pieces the bot moves next to each other, so rounding
+/// on the wire cannot make them overlap.
+const GAP: f32 = 0.5;
+
+/// Random spots tried before settling on one the board shows is taken.
+const PLACE_TRIES: usize = 50;
+
+impl View<'_> {
+    fn pieces(&self) -> impl Iterator<Item = &Piece> {
+        self.board.into_iter().flat_map(|b| &b.pieces)
+    }
+
+    fn mine(&self) -> Vec<&Piece> {
+        self.pieces().filter(|p| p.owner == self.me).collect()
+    }
+
+    /// Enemy pieces whose position and size are in sight.
+    fn enemies(&self) -> Vec<&Piece> {
+        self.pieces()
+            .filter(|p| p.owner != self.me && p.x.is_finite() && p.y.is_finite() && p.radius.is_finite())
+            .collect()
+    }
+
+    fn can_place(&self) -> bool {
+        let placed = self.mine().len() as u32;
+        self.phase != Phase::Shooting && self.rules.piece_limit.is_none_or(|limit| placed < limit)
+    }
+
+    fn can_shoot(&self) -> bool {
+        self.phase != Phase::Placement && !self.mine().is_empty()
+    }
+
+    /// Whether a piece at `(x, y)` would lie on the board clear of every
+    /// known piece other than `moving`.
+    fn is_free(&self, x: f32, y: f32, radius: f32, moving: Option<usize>) -> bool {
+        let size = self.rules.board_size;
+        x - radius >= 0.0
+            && y - radius >= 0.0
+            && x + radius <= size
+            && y + radius <= size
+            && !self.pieces().any(|p| {
+                Some(p.index) != moving && ((p.x - x).powi(2) + (p.y - y).powi(2)).sqrt() < p.radius + radius
+            })
+    }
+}
+
+impl Strategy {
+    /// A move for the position in `view`.  Moves the board already shows
+    /// to be illegal are avoided, but the server may still refuse one; ask
+    /// again for another.
+    pub fn choose(self, view: &View, rng: &mut Rng) -> ClientCmd {
+        match self {
+            Strategy::Random     => random_move(view, rng),
+            Strategy::Aggressive => aggressive_move(view, rng).unwrap_or_else(|| random_move(view, rng)),
+        }
+    }
+}
+
+fn random_move(view: &View, rng: &mut Rng) -> ClientCmd {
+    let mine = view.mine();
+    if view.can_shoot() && (!view.can_place() || rng.below(2) == 0) {
+        let piece = mine[rng.below(mine.len() as u64) as usize];
+        let angle = rng.next_f32() * TAU;
+        let reach = view.rules.max_force.min(view.rules.board_size / 2.0);
+        let force = (0.1 + 0.9 * rng.next_f32()) * reach;
+        return ClientCmd::Shoot { index: piece.index, dx: angle.cos(), dy: angle.sin(), force };
+    }
+    let rules = &view.rules;
+    let largest = rules.max_radius.min(rules.board_size / 10.0).max(rules.min_radius);
+    let mut place = None;
+    for _ in 0..PLACE_TRIES {
+        let radius = rules.min_radius + rng.next_f32() * (largest - rules.min_radius);
+        let span = (rules.board_size - 2.0 * radius).max(0.0);
+        let (x, y) = (radius + rng.next_f32() * span, radius + rng.next_f32() * span);
+        place = Some(ClientCmd::Place { x, y, radius });
+        if view.is_free(x, y, radius, None) {
+            break;
+        }
+    }
+    place.expect("PLACE_TRIES is not zero")
+}
+
+/// Place the largest piece that fits against an enemy piece, or failing
+/// that shoot the piece closest to an enemy up against it.  With no enemy
+/// in sight, take the middle of the board.
+fn aggressive_move(view: &View, rng: &mut Rng) -> Option<ClientCmd> {
+    let enemies = view.enemies();
+    if view.can_place() {
+        let radius = view.rules.max_radius.min(view.rules.board_size / 4.0);
+        let middle = view.rules.board_size / 2.0;
+        if enemies.is_empty() && view.is_free(middle, middle, radius, None) {
+            return Some(ClientCmd::Place { x: middle, y: middle, radius });
+        }
+        let turn = rng.next_f32() * TAU;
+        let mut radius = radius;
+        while radius >= view.rules.min_radius {
+            for enemy in &enemies {
+                let reach = enemy.radius + radius + GAP;
+                for k in 0..16 {
+                    let angle = turn + k as f32 * TAU / 16.0;
+                    let (x, y) = (enemy.x + reach * angle.cos(), enemy.y + reach * angle.sin());
+                    if view.is_free(x, y, radius, None) {
+                        return Some(ClientCmd::Place { x, y, radius });
+                    }
+                }
+            }
+            radius /= 2.0;
+        }
+    }
+    if !view.can_shoot() {
+        return None;
+    }
+    let mut pairs: Vec<(f32, &Piece, &Piece)> = Vec::new();
+    for piece in view.mine() {
+        for enemy in &enemies {
+            let gap = ((enemy.x - piece.x).powi(2) + (enemy.y - piece.y).powi(2)).sqrt() - piece.radius - enemy.radius;
+            pairs.push((gap, piece, enemy));
+        }
+    }
+    pairs.sort_by(|a, b| a.0.total_cmp(&b.0));
+    pairs.into_iter().find_map(|(gap, piece, enemy)| {
+        let force = (gap - GAP).min(view.rules.max_force);
+        let (dx, dy) = (enemy.x - piece.x, enemy.y - piece.y);
+        let len = dx.hypot(dy);
+        let (x, y) = (piece.x + dx / len * force, piece.y + dy / len * force);
+        (force > GAP && view.is_free(x, y, piece.radius, Some(piece.index)))
+            .then_some(ClientCmd::Shoot { index: piece.index, dx, dy, force })
+    })
+}
+
+// ── RUN ───────────────────────────────────────────────────────────────────────
+
+/// Refusals in a row after which the bot stops trying to move.
+const MAX_REFUSALS: u32 = 100;
+
+/// Connect to a server and play one game.
+pub async fn run(args: BotArgs) {
+    let log = Logger::new(args.verbose);
+
+    let addrs = compose_candidates(args.addr, &args.host, args.port).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(1);
+    });
+    let (mut client, addr) = match GameClient::connect_any(&addrs, Duration::from_secs(args.connect_timeout)).await {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("Failed to connect: {e}");
+            std::process::exit(1);
+        }
+    };
+    log.info(format_args!("Connected to {addr}"));
+
+    let seed = args.seed.unwrap_or_else(now_ms);
+    let mut rng = Rng::new(seed);
+    log.verbose(format_args!("Playing {:?} with seed {seed}", args.strategy));
+
+    if let Some(name) = args.name
+        && let Err(e) = client.send_tagged(&ClientCmd::Name(name), "name").await
+    {
+        eprintln!("Failed to send name: {e}");
+        std::process::exit(1);
+    }
+
+    let mut me = 0;
+    let mut rules = RulesInfo::from(&Rules::default());
+    let mut phase = Phase::Open;
+    let mut board: Option<BoardState> = None;
+    let mut refusals = 0;
+
+    loop {
+        let msg = match client.recv().await {
+            Ok(Some(msg)) => msg,
+            Ok(None) => {
+                println!("The server closed the connection.");
+                break;
+            }
+            Err(e) => {
+                eprintln!("Connection lost: {e}");
+                std::process::exit(1);
+            }
+        };
+        let reply = match &msg {
+            ServerMsg::Ready { player_id, .. } => {
+                me = *player_id;
+                println!("{msg}");
+                Some(ClientCmd::ReadyAck)
+            }
+            ServerMsg::Rules(announced) => {
+                rules = *announced;
+                None
+            }
+            ServerMsg::PieceLimit(n) => {
+                rules.piece_limit = Some(*n);
+                None
+            }
+            ServerMsg::Phase(p) => {
+                phase = *p;
+                None
+            }
+            ServerMsg::State(state) => {
+                board = Some(state.clone());
+                None
+            }
+            ServerMsg::Ok { tag: Some(tag) } if tag == "move" => {
+                refusals = 0;
+                None
+            }
+            ServerMsg::Error { tag: Some(tag), reason, .. } if tag == "move" => {
+                log.verbose(format_args!("Move refused: {reason}"));
+                refusals += 1;
+                if refusals < MAX_REFUSALS {
+                    let view = View { me, board: board.as_ref(), rules, phase };
+                    Some(args.strategy.choose(&view, &mut rng))
+                } else {
+                    log.warn(format_args!("{MAX_REFUSALS} moves refused in a row; waiting for the game to end"));
+                    None
+                }
+            }
+            ServerMsg::YourTurn => {
+                sleep(Duration::from_millis(args.think)).await;
+                let view = View { me, board: board.as_ref(), rules, phase };
+                Some(args.strategy.choose(&view, &mut rng))
+            }
+            ServerMsg::DrawOffered   => Some(ClientCmd::DrawAccept),
+            ServerMsg::UndoRequested => Some(ClientCmd::UndoAccept),
+            ServerMsg::GameOver(_) | ServerMsg::Disconnected => {
+                println!("{msg}");
+                break;
+            }
+            _ => None,
+        };
+        let Some(cmd) = reply else { continue };
+        log.verbose(format_args!("→ {}", cmd.to_wire().trim_end()));
+        let sent = if cmd.is_move() { client.send_tagged(&cmd, "move").await } else { client.send(&cmd).await };
+        if let Err(e) = sent {
+            eprintln!("Connection lost: {e}");
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,988 @@
+use crate::logger::{Category, Logger};
+use crate::net::{compose_candidates, parse_candidates, Candidates};
+use crate::board::{BoardState, Piece};
+use crate::compress::InflateReader;
+use crate::protocol::{
+    parse_f32, parse_index, valid_chat, valid_name, valid_skin, ClientCmd, ErrorCode, RulesInfo, ServerMsg, CHAT_RULE,
+    NAME_RULE, SKIN_RULE,
+};
+use crate::replay::now_ms;
+use clap::{ArgAction, Parser};
+use std::fmt;
+use std::io::{self, Write as _};
+use std::collections::{HashMap, VecDeque};
+use std::fs::{File, OpenOptions};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWriteExt, BufReader, Lines, Stdin};
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::net::TcpStream;
+use tokio::task::JoinSet;
+use tokio::time::{sleep, sleep_until, Duration, Instant};
+
+// ── CLI ───────────────────────────────────────────────────────────────────────
+
+/// Command-line options for the terminal client.
+#[derive(Parser, Debug)]
+#[command(
+    name    = "client",
+    version,
+    about   = "Seb n Vic Multiplayer Game — terminal client",
+    long_about = "Connects to a running game server and plays interactively.\n\
+                  Commands (type when it is your turn):\n  \
+                    place <x> <y> <radius>\n  \
+                    shoot <piece#> <dx> <dy> <force>\n  \
+                    aim <piece#> <x> <y> <force>\n  \
+                    draw | accept | decline\n\
+                  `board` reprints the current board at any time.\n\
+                  With --script, commands are read from a file (# starts a comment)\n\
+                  and issued one per turn; the client exits when the script ends."
+)]
+pub struct ClientArgs {
+    /// Full server address; overrides --host and --port
+    #[arg(value_parser = parse_candidates)]
+    addr: Option<Candidates>,
+
+    /// Server IP address or hostname; every address it resolves to is tried
+    #[arg(long, default_value = "127.0.0.1")]
+    host: String,
+
+    /// Server port
+    #[arg(short, long, default_value_t = 7878)]
+    port: u16,
+
+    /// Read commands from this file instead of stdin, one per turn
+    #[arg(long, value_name = "PATH")]
+    script: Option<PathBuf>,
+
+    /// Append a timestamped log of every line sent and received to this file
+    #[arg(long, value_name = "PATH")]
+    transcript: Option<PathBuf>,
+
+    /// Ring the terminal bell and print a banner when it becomes your turn
+    #[arg(long)]
+    notify: bool,
+
+    /// Print a one-line status summary whenever the turn changes
+    #[arg(long)]
+    status: bool,
+
+    /// Seconds to keep trying the server's addresses before giving up
+    #[arg(long, default_value_t = 10, value_name = "SECS")]
+    connect_timeout: u64,
+
+    /// Seconds to wait for the server to answer a command before giving the
+    /// prompt back
+    #[arg(long, default_value_t = 10, value_name = "SECS", value_parser = clap::value_parser!(u64).range(1..))]
+    reply_timeout: u64,
+
+    /// Ask the server to deflate what it sends; worth it on large boards
+    #[arg(long)]
+    compress: bool,
+
+    /// Name to play under, sent to the server on connecting
+    #[arg(long, value_parser = parse_name)]
+    name: Option<String>,
+
+    /// Colour to draw your pieces in, sent to the server on connecting
+    #[arg(long, value_parser = parse_skin)]
+    skin: Option<String>,
+
+    /// Increase output verbosity (-v verbose, -vv debug, -vvv trace)
+    #[arg(short, long, action = ArgAction::Count)]
+    verbose: u8,
+}
+
+pub(crate) fn parse_name(s: &str) -> Result<String, String> {
+    valid_name(s).map(str::to_string).ok_or_else(|| NAME_RULE.to_string())
+}
+
+fn parse_skin(s: &str) -> Result<String, String> {
+    valid_skin(&s.to_ascii_lowercase()).map(str::to_string).ok_or_else(|| SKIN_RULE.to_string())
+}
+
+// ── CLIENT EVENTS (operational logging to stderr) ─────────────────────────────
+
+enum ClientEvent<'a> {
+    Connecting { addrs: &'a [SocketAddr] },
+    Connected  { addr: SocketAddr },
+    Sending    { cmd: &'a str },
+    Received   { raw: &'a str },
+    Disconnected,
+}
+
+impl fmt::Display for ClientEvent<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientEvent::Connecting { addrs } => write!(f, "Connecting to {}…", join_addrs(addrs)),
+            ClientEvent::Connected  { addr }  => write!(f, "Connected to {addr}"),
+            ClientEvent::Sending    { cmd }   => write!(f, "→ {cmd}"),
+            ClientEvent::Received   { raw }   => write!(f, "← {raw}"),
+            ClientEvent::Disconnected         => write!(f, "Connection closed by server"),
+        }
+    }
+}
+
+fn join_addrs(addrs: &[SocketAddr]) -> String {
+    addrs.iter().map(SocketAddr::to_string).collect::<Vec<_>>().join(" or ")
+}
+
+// ── PROMPT ────────────────────────────────────────────────────────────────────
+
+fn print_prompt(player_id: u8) {
+    print!("\nP{player_id}> ");
+    io::stdout().flush().ok();
+}
+
+/// Get the player's attention when the turn passes to them.
+fn notify_turn() {
+    print!("\x07\n  ***  YOUR TURN  ***");
+    io::stdout().flush().ok();
+}
+
+/// Who you are, how many pieces each player has (yours first) and whether
+/// it is your turn, e.g. `You: P0 | pieces: 3 vs 2 | your turn`.
+fn status_line(player_id: u8, players: u8, board: Option<&BoardState>, my_turn: bool) -> String {
+    let count = |p: u8| board.map_or(0, |b| b.pieces.iter().filter(|piece| piece.owner == p).count());
+    let counts: Vec<String> = std::iter::once(player_id)
+        .chain((0..players).filter(|&p| p != player_id))
+        .map(|p| count(p).to_string())
+        .collect();
+    let turn = if my_turn { "your turn" } else { "waiting" };
+    format!("You: P{player_id} | pieces: {} | {turn}", counts.join(" vs "))
+}
+
+/// What to try after a rejection, chosen by its code so a reworded reason
+/// still gets the same advice.  `None` when the reason says it all.
+fn rejection_hint(code: ErrorCode, board: Option<&BoardState>, rules: Option<&RulesInfo>) -> Option<String> {
+    match code {
+        ErrorCode::NotYourTurn => Some("Wait for your turn.".into()),
+        ErrorCode::OutOfBounds => rules.map(|r| format!("The whole piece must lie on the {0}×{0} board.", r.board_size)),
+        ErrorCode::Overlap => Some("Pick a spot clear of the other pieces; 'board' shows where they are.".into()),
+        ErrorCode::BadIndex => match board.map_or(0, |b| b.pieces.len()) {
+            0 => Some("There are no pieces on the board yet.".into()),
+            n => Some(format!("Pieces are numbered 0 to {}; 'board' lists them.", n - 1)),
+        },
+        ErrorCode::NotYourPiece => Some("You can only shoot your own pieces; 'board' shows whose is whose.".into()),
+        ErrorCode::BadRadius => rules.map(|r| format!("Radii run from {} to {}.", r.min_radius, r.max_radius)),
+        _ => None,
+    }
+}
+
+fn print_board(board: Option<&BoardState>) {
+    match board {
+        Some(board) => println!("Board:\n{board}"),
+        None => println!("Board:\n  (board is empty)"),
+    }
+}
+
+fn print_help() {
+    println!("  Commands:");
+    println!("    place <x> <y> <radius>          — place a new piece");
+    println!("    shoot <piece#> <dx> <dy> <force> — shoot an existing piece");
+    println!("    shoot_vel <piece#> <vx> <vy>     — shoot a piece exactly (vx, vy)");
+    println!("    aim <piece#> <x> <y> <force>     — shoot a piece toward a point");
+    println!("    draw                             — offer your opponent a draw");
+    println!("    accept | decline                 — answer a draw offer (any time)");
+    println!("    undo                             — ask to take back the last move (any time)");
+    println!("    undo accept | undo decline       — answer an undo request (any time)");
+    println!("    name <name>                      — set your name for ratings");
+    println!("    rating [name]                    — show a rating (default: yours)");
+    println!("    /name <name>                     — change your name (any time)");
+    println!("    /skin <colour>                   — colour your pieces (any time)");
+    println!("    /chat <message>                  — talk to the other players (any time)");
+    println!("    board | show                     — reprint the current board (any time)");
+    println!("    p | s | a                        — short for place, shoot and aim");
+}
+
+// ── INPUT ─────────────────────────────────────────────────────────────────────
+
+/// Parse a `/command`, which is not a move and may be typed whatever the
+/// turn; `None` if `raw` is not one.
+fn parse_slash(raw: &str) -> Option<Result<ClientCmd, String>> {
+    let rest = raw.strip_prefix('/')?;
+    let (kw, arg) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    let arg = arg.trim();
+    Some(match kw.to_ascii_lowercase().as_str() {
+        "name" if arg.is_empty() => Err("missing name".into()),
+        "name" => valid_name(arg).map(|n| ClientCmd::Name(n.to_string())).ok_or_else(|| NAME_RULE.into()),
+        "skin" if arg.is_empty() => Err("missing colour".into()),
+        "skin" => valid_skin(&arg.to_ascii_lowercase())
+            .map(|s| ClientCmd::Skin(s.to_string()))
+            .ok_or_else(|| SKIN_RULE.into()),
+        "chat" if arg.is_empty() => Err("missing message".into()),
+        "chat" => valid_chat(arg).map(|t| ClientCmd::Chat(t.to_string())).ok_or_else(|| CHAT_RULE.into()),
+        _ => Err(format!("unknown command '/{kw}'")),
+    })
+}
+
+/// Single-letter shortcuts for the commands typed every turn.
+const ALIASES: [(&str, &str); 3] = [("p", "place"), ("s", "shoot"), ("a", "aim")];
+
+/// Every command word a player can type, for pointing a stray letter at the
+/// commands it might have meant.
+const COMMANDS: [&str; 13] = [
+    "place", "shoot", "shoot_vel", "aim", "draw", "accept", "decline", "undo", "name", "rating", "board", "show",
+    "help",
+];
+
+/// Expand a leading shortcut such as `p` into the command word it stands
+/// for, leaving the rest of the line as typed.  Any other single-letter
+/// command is refused with the words it could have been short for.
+pub fn expand_alias(raw: &str) -> Result<String, String> {
+    let raw = raw.trim();
+    let (kw, rest) = raw.split_once(char::is_whitespace).unwrap_or((raw, ""));
+    if kw.chars().count() != 1 {
+        return Ok(raw.to_string());
+    }
+    let kw = kw.to_lowercase();
+    if let Some((_, word)) = ALIASES.iter().find(|(short, _)| *short == kw) {
+        return Ok(format!("{word} {rest}"));
+    }
+    let candidates: Vec<&str> = COMMANDS.into_iter().filter(|c| c.starts_with(&kw)).collect();
+    Err(match candidates[..] {
+        []    => format!("unknown command '{kw}'; the shortcuts are p (place), s (shoot) and a (aim)"),
+        [one] => format!("'{kw}' is not a shortcut; type '{one}' in full"),
+        _     => format!("'{kw}' could be {}; type the command in full", candidates.join(" or ")),
+    })
+}
+
+/// Parse a typed line, expanding shortcuts and client-side helpers such as
+/// `aim` into the wire command they stand for.
+fn parse_line(raw: &str, board: Option<&BoardState>) -> Result<ClientCmd, String> {
+    let line = expand_alias(raw)?;
+    let mut t = line.split_whitespace();
+    if !t.next().is_some_and(|kw| kw.eq_ignore_ascii_case("aim")) {
+        return ClientCmd::parse_input(&line);
+    }
+
+    let index = parse_index(&mut t)?;
+    let x     = parse_f32(&mut t, "target x")?;
+    let y     = parse_f32(&mut t, "target y")?;
+    let force = parse_f32(&mut t, "force")?;
+    if force <= 0.0 {
+        return Err("force must be > 0".into());
+    }
+
+    let piece = board_piece(board, index)?;
+    let (dx, dy) = aim_direction(piece.x, piece.y, x, y)
+        .ok_or("the target is the piece's own position")?;
+    Ok(ClientCmd::Shoot { index, dx, dy, force })
+}
+
+/// The piece `index` names on the retained board.  Before the first
+/// `STATE` there are no pieces at all.
+fn board_piece(board: Option<&BoardState>, index: usize) -> Result<&Piece, String> {
+    let pieces = board.map_or(&[][..], |b| &b.pieces[..]);
+    match pieces.len() {
+        0 => Err("there are no pieces on the board yet".into()),
+        n => pieces
+            .get(index)
+            .ok_or_else(|| format!("there is no piece #{index}; pieces are numbered 0 to {}", n - 1)),
+    }
+}
+
+/// Reject moves the retained board and the announced `rules` already show
+/// to be illegal, saving a round trip. The server stays authoritative: before
+/// `RULES` arrives only the board-independent rules are checked.
+fn validate(cmd: &ClientCmd, board: Option<&BoardState>, rules: Option<&RulesInfo>, player_id: u8) -> Result<(), String> {
+    match *cmd {
+        ClientCmd::Place { x, y, radius } => {
+            if let Some(rules) = rules {
+                if radius < rules.min_radius {
+                    return Err(format!("radius must be at least {}", rules.min_radius));
+                }
+                if radius > rules.max_radius {
+                    return Err(format!("radius may be at most {}", rules.max_radius));
+                }
+                let size = rules.board_size;
+                if x + radius > size || y + radius > size {
+                    return Err(format!("piece must lie within the {size}×{size} board"));
+                }
+            }
+            if x - radius < 0.0 || y - radius < 0.0 {
+                return Err("piece must lie within the board".into());
+            }
+            if board.is_some_and(|b| b.overlaps(x, y, radius)) {
+                return Err("overlaps an existing piece".into());
+            }
+        }
+        ClientCmd::Shoot { index, .. } | ClientCmd::ShootVel { index, .. } => {
+            if let Some(rules) = rules
+                && cmd.shot_force().is_some_and(|force| force > rules.max_force)
+            {
+                let what = if matches!(cmd, ClientCmd::ShootVel { .. }) { "speed" } else { "force" };
+                return Err(format!("{what} may be at most {}", rules.max_force));
+            }
+            let piece = board_piece(board, index)?;
+            if piece.owner != player_id {
+                return Err(format!("piece #{index} is not yours"));
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Load a command script, dropping blank lines and `#` comments.
+fn load_script(path: &Path) -> io::Result<VecDeque<String>> {
+    Ok(std::fs::read_to_string(path)?
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(String::from)
+        .collect())
+}
+
+/// Next line of input. A script only yields once the client is `ready` for
+/// another command; `None` means the input (or script) is exhausted.
+async fn next_input(
+    stdin:  &mut Lines<BufReader<Stdin>>,
+    script: Option<&mut VecDeque<String>>,
+    ready:  bool,
+) -> io::Result<Option<String>> {
+    match script {
+        None => stdin.next_line().await,
+        Some(_) if !ready => std::future::pending().await,
+        Some(script) => Ok(script.pop_front()),
+    }
+}
+
+/// Direction from `(x, y)` toward the target, or `None` if they coincide.
+fn aim_direction(x: f32, y: f32, target_x: f32, target_y: f32) -> Option<(f32, f32)> {
+    let (dx, dy) = (target_x - x, target_y - y);
+    (dx != 0.0 || dy != 0.0).then_some((dx, dy))
+}
+
+// ── TRANSCRIPT ────────────────────────────────────────────────────────────────
+
+/// Timestamped record of exactly what this client sent (`>`) and received
+/// (`<`). Does nothing when no `--transcript` path was given.
+struct Transcript {
+    out: Option<io::BufWriter<File>>,
+}
+
+impl Transcript {
+    fn open(path: Option<&Path>) -> io::Result<Self> {
+        let out = match path {
+            Some(path) => Some(io::BufWriter::new(
+                OpenOptions::new().create(true).append(true).open(path)?,
+            )),
+            None => None,
+        };
+        Ok(Self { out })
+    }
+
+    fn sent    (&mut self, line: &str) { self.write('>', line); }
+    fn received(&mut self, line: &str) { self.write('<', line); }
+
+    fn write(&mut self, dir: char, line: &str) {
+        let Some(out) = &mut self.out else { return };
+        if let Err(e) = writeln!(out, "{} {dir} {line}", now_ms()) {
+            eprintln!("Transcript write failed; no longer recording: {e}");
+            self.out = None;
+        }
+    }
+
+    fn finish(&mut self) {
+        if let Some(out) = &mut self.out
+            && let Err(e) = out.flush()
+        {
+            eprintln!("Failed to flush transcript: {e}");
+        }
+    }
+}
+
+// ── GAME CLIENT ───────────────────────────────────────────────────────────────
+
+/// A connection to a game server, speaking the wire protocol in terms of
+/// [`ClientCmd`] and [`ServerMsg`].  The terminal front end below is one
+/// user; bots and tests drive it directly.
+pub struct GameClient {
+    lines:  Lines<BufReader<Box<dyn AsyncRead + Unpin + Send>>>,
+    writer: OwnedWriteHalf,
+}
+
+/// How long an attempt on one address runs alone before the next address is
+/// tried alongside it (RFC 8305's connection attempt delay).
+const ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+impl GameClient {
+    pub async fn connect(addr: SocketAddr) -> io::Result<Self> {
+        Ok(Self::from_stream(TcpStream::connect(addr).await?))
+    }
+
+    /// Connect to whichever of `addrs` answers first, happy-eyeballs style:
+    /// they are tried in order, each starting once the one before has failed
+    /// or had [`ATTEMPT_DELAY`] to itself.  Gives up after `within` in all.
+    /// Returns the address that connected.
+    pub async fn connect_any(addrs: &[SocketAddr], within: Duration) -> io::Result<(Self, SocketAddr)> {
+        let give_up = sleep(within);
+        tokio::pin!(give_up);
+        let mut untried = addrs.iter().copied().peekable();
+        let mut attempts = JoinSet::new();
+        let mut failures = Vec::new();
+        let mut kind = io::ErrorKind::NotFound;
+        loop {
+            if let Some(addr) = untried.next() {
+                attempts.spawn(async move { (addr, TcpStream::connect(addr).await) });
+            } else if attempts.is_empty() {
+                break;
+            }
+            tokio::select! {
+                Some(joined) = attempts.join_next() => match joined.map_err(io::Error::other)? {
+                    (addr, Ok(stream)) => return Ok((Self::from_stream(stream), addr)),
+                    (addr, Err(e)) => {
+                        kind = e.kind();
+                        failures.push(format!("{addr}: {e}"));
+                    }
+                },
+                _ = sleep(ATTEMPT_DELAY), if untried.peek().is_some() => {}
+                _ = &mut give_up => return Err(io::Error::new(io::ErrorKind::TimedOut, "connection timed out")),
+            }
+        }
+        if failures.is_empty() {
+            failures.push("no addresses to try".to_string());
+        }
+        Err(io::Error::new(kind, failures.join("; ")))
+    }
+
+    fn from_stream(stream: TcpStream) -> Self {
+        let (reader, writer) = stream.into_split();
+        let reader: Box<dyn AsyncRead + Unpin + Send> = Box::new(reader);
+        Self { lines: BufReader::new(reader).lines(), writer }
+    }
+
+    pub async fn send(&mut self, cmd: &ClientCmd) -> io::Result<()> {
+        self.writer.write_all(cmd.to_wire().as_bytes()).await
+    }
+
+    /// Send `cmd` with a tag for the server to echo on its OK or ERROR.
+    pub async fn send_tagged(&mut self, cmd: &ClientCmd, tag: &str) -> io::Result<()> {
+        self.writer.write_all(cmd.to_wire_tagged(tag).as_bytes()).await
+    }
+
+    /// The next line from the server exactly as received, or `None` once
+    /// the server hangs up.  Cancellation-safe, so it can sit in a
+    /// `select!`.  After a `COMPRESS deflate` line the rest of the stream
+    /// is inflated here, so callers see the same lines either way.
+    pub async fn recv_line(&mut self) -> io::Result<Option<String>> {
+        let line = self.lines.next_line().await?;
+        if line.as_deref().is_some_and(|l| matches!(ServerMsg::parse(l.trim()), ServerMsg::Compress)) {
+            let empty: Box<dyn AsyncRead + Unpin + Send> = Box::new(tokio::io::empty());
+            let plain = std::mem::replace(&mut self.lines, BufReader::new(empty).lines()).into_inner();
+            let buffered = plain.buffer().to_vec();
+            let inflated: Box<dyn AsyncRead + Unpin + Send> = Box::new(InflateReader::new(plain.into_inner(), buffered));
+            self.lines = BufReader::new(inflated).lines();
+        }
+        Ok(line)
+    }
+
+    /// The next message from the server, or `None` once it hangs up.
+    /// Cancellation-safe like [`GameClient::recv_line`].
+    pub async fn recv(&mut self) -> io::Result<Option<ServerMsg>> {
+        Ok(self.recv_line().await?.map(|line| ServerMsg::parse(line.trim())))
+    }
+}
+
+// ── RUN ──────────────────────────────────────────────────────────────────────
+
+/// How long after an `OK` the matching `STATE` may take before the board is
+/// assumed stale.
+const STATE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Connect to a server and play one game from the terminal.
+pub async fn run(args: ClientArgs) {
+    let log  = Logger::new(args.verbose);
+
+    let addrs = compose_candidates(args.addr, &args.host, args.port).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(1);
+    });
+
+    let mut script = args.script.as_deref().map(|path| {
+        load_script(path).unwrap_or_else(|e| {
+            eprintln!("Failed to read script {}: {e}", path.display());
+            std::process::exit(1);
+        })
+    });
+
+    let mut transcript = Transcript::open(args.transcript.as_deref()).unwrap_or_else(|e| {
+        eprintln!("Failed to open transcript: {e}");
+        std::process::exit(1);
+    });
+
+    log.info(ClientEvent::Connecting { addrs: &addrs });
+
+    let (mut client, addr) = match GameClient::connect_any(&addrs, Duration::from_secs(args.connect_timeout)).await {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("Failed to connect to {}: {e}", join_addrs(&addrs));
+            std::process::exit(1);
+        }
+    };
+
+    log.info(ClientEvent::Connected { addr });
+
+    let mut stdin_lines  = BufReader::new(tokio::io::stdin()).lines();
+
+    // Game state tracked client-side.
+    let mut player_id: u8 = 0;
+    let mut players:   u8 = 2;
+    let mut my_turn       = false;
+    let mut last_seq: u64 = 0;
+    let mut last_tick: u64 = 0;  // last simulation tick the server confirmed
+    let mut draw_pending  = false;  // opponent's offer awaiting our answer
+    let mut undo_pending  = false;  // opponent's undo request awaiting our answer
+    let mut awaiting      = false;  // sent a command the server must answer
+    let mut piece_limit: Option<u32> = None;
+    let mut rules: Option<RulesInfo> = None;
+    let mut board: Option<BoardState> = None;   // latest applied STATE
+    let mut next_tag: u32 = 0;
+    let mut pending: HashMap<String, String> = HashMap::new();  // tag → command sent
+    // An accepted move whose STATE has not arrived yet: when it is due, and
+    // our own command if the move was ours.
+    let mut unconfirmed: Option<(Instant, Option<String>)> = None;
+    // While another player is on a timed turn: when their time runs out,
+    // and when the countdown line is next redrawn.
+    let mut countdown: Option<(Instant, Instant)> = None;
+    // While `awaiting`: when the reply is overdue, and whether the command
+    // gave up our turn.
+    let mut reply_due: Option<(Instant, bool)> = None;
+    let reply_timeout = Duration::from_secs(args.reply_timeout);
+
+    // The handshake and our name are accepted before the game starts, so
+    // send them straight away.
+    let compress = args.compress.then_some(ClientCmd::Hello { deflate: true });
+    let name = args.name.clone().map(ClientCmd::Name);
+    let skin = args.skin.clone().map(ClientCmd::Skin);
+    for cmd in compress.into_iter().chain(name).chain(skin) {
+        next_tag += 1;
+        let tag = next_tag.to_string();
+        let wire = cmd.to_wire_tagged(&tag);
+        pending.insert(tag.clone(), cmd.to_wire().trim_end().to_string());
+        log.of(Category::Protocol).verbose(ClientEvent::Sending { cmd: wire.trim_end() });
+        transcript.sent(wire.trim_end());
+        if let Err(e) = client.send_tagged(&cmd, &tag).await {
+            eprintln!("Failed to send '{}': {e}", cmd.to_wire().trim_end());
+            std::process::exit(1);
+        }
+    }
+
+    loop {
+        tokio::select! {
+            // ── Server → Client ───────────────────────────────────────────────
+            result = client.recv_line() => {
+                let raw = match result {
+                    Ok(Some(l)) => l,
+                    _ => {
+                        log.info(ClientEvent::Disconnected);
+                        println!("\nDisconnected from server.");
+                        break;
+                    }
+                };
+
+                log.of(Category::Protocol).trace(ClientEvent::Received { raw: &raw });
+                transcript.received(&raw);
+
+                let msg = ServerMsg::parse(raw.trim());
+
+                match &msg {
+                    ServerMsg::Ready { player_id: id, players: n, .. } => {
+                        player_id = *id;
+                        players   = *n;
+                        println!("\n{msg}");
+                        print_help();
+                        // Tell the server we are ready to be timed.
+                        let wire = ClientCmd::ReadyAck.to_wire();
+                        log.of(Category::Protocol).verbose(ClientEvent::Sending { cmd: wire.trim_end() });
+                        transcript.sent(wire.trim_end());
+                        if client.send(&ClientCmd::ReadyAck).await.is_err() {
+                            eprintln!("Failed to send command.");
+                            break;
+                        }
+                    }
+                    ServerMsg::YourTurn => {
+                        if args.notify && !my_turn {
+                            notify_turn();
+                        }
+                        my_turn = true;
+                        countdown = None;
+                        // The opponent has moved, so any offer of theirs lapsed.
+                        draw_pending = false;
+                        undo_pending = false;
+                        if args.status {
+                            println!("\n{}", status_line(player_id, players, board.as_ref(), my_turn));
+                        }
+                        print_prompt(player_id);
+                    }
+                    ServerMsg::DrawOffered => {
+                        draw_pending = true;
+                        println!("\n{msg}");
+                        print_prompt(player_id);
+                    }
+                    ServerMsg::UndoRequested => {
+                        undo_pending = true;
+                        println!("\n{msg}");
+                        print_prompt(player_id);
+                    }
+                    ServerMsg::DrawDeclined | ServerMsg::UndoDeclined => {
+                        println!("\n{msg}");
+                        if my_turn {
+                            print_prompt(player_id);
+                        }
+                    }
+                    ServerMsg::GameOver(_) => {
+                        println!("\n{msg}");
+                        break;
+                    }
+                    ServerMsg::Error { tag, code, reason } => {
+                        match tag.as_ref().and_then(|t| pending.remove(t)) {
+                            Some(sent) => println!("\nRejected '{sent}': {reason}"),
+                            None => println!("\n{msg}"),
+                        }
+                        if let Some(hint) = rejection_hint(*code, board.as_ref(), rules.as_ref()) {
+                            println!("  {hint}");
+                        }
+                        // Turn stays with us and we re-prompt, unless it
+                        // was never ours.
+                        if awaiting {
+                            awaiting  = false;
+                            my_turn   = *code != ErrorCode::NotYourTurn;
+                            reply_due = None;
+                        }
+                        if my_turn {
+                            print_prompt(player_id);
+                        }
+                    }
+                    ServerMsg::Disconnected => {
+                        println!("\n{msg}");
+                        break;
+                    }
+                    ServerMsg::OpponentTurn => {
+                        my_turn = false;
+                        countdown = None;
+                        println!("\n{msg}");
+                        if args.status {
+                            println!("{}", status_line(player_id, players, board.as_ref(), my_turn));
+                        }
+                    }
+                    ServerMsg::Ok { tag } => {
+                        awaiting  = false;
+                        reply_due = None;
+                        // A move's OK is followed by its STATE; report the
+                        // move once the board arrives.  Our other tagged
+                        // commands are complete as they stand; an untagged
+                        // OK is another player's move.
+                        match tag.as_ref().and_then(|t| pending.remove(t)) {
+                            Some(sent) if ClientCmd::parse(&sent).is_some_and(|c| c.is_move()) => {
+                                log.verbose(format_args!("server accepted '{sent}'"));
+                                unconfirmed = Some((Instant::now() + STATE_TIMEOUT, Some(sent)));
+                            }
+                            Some(sent) => log.verbose(format_args!("server accepted '{sent}'")),
+                            None => {
+                                log.verbose("server acknowledged move");
+                                unconfirmed = Some((Instant::now() + STATE_TIMEOUT, None));
+                            }
+                        }
+                    }
+                    ServerMsg::State(state) => {
+                        // A stale frame means we are out of step with the
+                        // server; drop it and ask for the authoritative board.
+                        if state.seq < last_seq || state.tick < last_tick {
+                            log.warn(format_args!(
+                                "stale STATE seq {} tick {} (last applied {last_seq} at tick {last_tick}) — requesting resync",
+                                state.seq, state.tick
+                            ));
+                            let wire = ClientCmd::Resync.to_wire();
+                            log.of(Category::Protocol).verbose(ClientEvent::Sending { cmd: wire.trim_end() });
+                            transcript.sent(wire.trim_end());
+                            if client.send(&ClientCmd::Resync).await.is_err() {
+                                eprintln!("Failed to send command.");
+                                break;
+                            }
+                            continue;
+                        }
+                        if state.seq > last_seq + 1 {
+                            log.verbose(format_args!(
+                                "missed {} STATE frame(s); applying full state",
+                                state.seq - last_seq - 1
+                            ));
+                        }
+                        last_seq  = state.seq;
+                        last_tick = state.tick;
+                        board = Some(state.clone());
+                        if let Some((_, Some(sent))) = unconfirmed.take() {
+                            println!("\nAccepted '{sent}'.");
+                        }
+                        println!("\n{msg}");
+                        if let Some(limit) = piece_limit {
+                            let mine = state.pieces.iter().filter(|p| p.owner == player_id).count();
+                            let left = (limit as usize).saturating_sub(mine);
+                            println!("  Placements left: {left} of {limit}");
+                        }
+                    }
+                    ServerMsg::Rating { .. } => {
+                        awaiting  = false;
+                        reply_due = None;
+                        println!("\n{msg}");
+                        if my_turn {
+                            print_prompt(player_id);
+                        }
+                    }
+                    ServerMsg::Chat { .. } => {
+                        println!("\n{msg}");
+                        if my_turn {
+                            print_prompt(player_id);
+                        }
+                    }
+                    ServerMsg::Rules(announced) => {
+                        rules = Some(*announced);
+                        piece_limit = announced.piece_limit;
+                        println!("\n{msg}");
+                    }
+                    ServerMsg::PieceLimit(n) => {
+                        piece_limit = Some(*n);
+                        println!("\n{msg}");
+                    }
+                    ServerMsg::TurnDeadline(_) if my_turn => {
+                        println!("\n{msg}");
+                        print_prompt(player_id);
+                    }
+                    ServerMsg::TurnDeadline(secs) => {
+                        let now = Instant::now();
+                        countdown = Some((now + Duration::from_secs((*secs).into()), now));
+                    }
+                    ServerMsg::Eliminated(id) => {
+                        println!("\n{msg}");
+                        if *id == player_id {
+                            println!("  You can keep watching until the game ends.");
+                        }
+                    }
+                    ServerMsg::Compress => log.verbose(format_args!("{msg}")),
+                    ServerMsg::Waiting
+                    | ServerMsg::Phase(_)
+                    | ServerMsg::Skin { .. }
+                    | ServerMsg::OpponentError(_)
+                    | ServerMsg::Unknown(_) => {
+                        println!("\n{msg}");
+                    }
+                }
+            }
+
+            // ── Opponent's clock ──────────────────────────────────────────────
+            _ = sleep_until(countdown.map_or_else(Instant::now, |(_, redraw)| redraw)), if countdown.is_some() => {
+                let (deadline, redraw) = countdown.unwrap();
+                let left = deadline.saturating_duration_since(Instant::now());
+                if left.is_zero() {
+                    print!("\r  Time is up — waiting for timeout…      ");
+                    countdown = None;
+                } else {
+                    print!("\r  {}s left on their clock      ", left.as_millis().div_ceil(1000));
+                    countdown = Some((deadline, redraw + Duration::from_secs(1)));
+                }
+                io::stdout().flush().ok();
+            }
+
+            // ── Missing STATE ─────────────────────────────────────────────────
+            _ = sleep_until(unconfirmed.as_ref().map_or_else(Instant::now, |(due, _)| *due)),
+                if unconfirmed.is_some() =>
+            {
+                let (_, sent) = unconfirmed.take().unwrap();
+                let what = sent.map_or_else(|| "a move".to_string(), |sent| format!("'{sent}'"));
+                log.warn(format_args!("no STATE after the OK for {what} — requesting resync"));
+                println!("\n  ! The server accepted {what} but sent no board; asking for it again.");
+                let wire = ClientCmd::Resync.to_wire();
+                log.of(Category::Protocol).verbose(ClientEvent::Sending { cmd: wire.trim_end() });
+                transcript.sent(wire.trim_end());
+                if client.send(&ClientCmd::Resync).await.is_err() {
+                    eprintln!("Failed to send command.");
+                    break;
+                }
+            }
+
+            // ── Missing reply ─────────────────────────────────────────────────
+            // A server that never answers must not leave input disabled.
+            // The tag stays pending, so a late reply is still recognised.
+            _ = sleep_until(reply_due.map_or_else(Instant::now, |(due, _)| due)), if reply_due.is_some() => {
+                let (_, gave_turn) = reply_due.take().unwrap();
+                log.warn(format_args!("no reply from the server within {}s", args.reply_timeout));
+                println!("\n  ! The server has not answered; you can type again.");
+                awaiting = false;
+                if gave_turn {
+                    my_turn = true;
+                }
+                if my_turn {
+                    print_prompt(player_id);
+                }
+            }
+
+            // ── Stdin / Script → Server ───────────────────────────────────────
+            result = next_input(
+                &mut stdin_lines,
+                script.as_mut(),
+                (my_turn || draw_pending || undo_pending) && !awaiting,
+            ) => {
+                let raw = match result {
+                    Ok(Some(l)) => l,
+                    _ => {
+                        println!("\nInput closed.");
+                        break;
+                    }
+                };
+                if script.is_some() {
+                    println!("{raw}");
+                }
+
+                let trimmed = raw.trim();
+
+                // Local commands work at any time and never reach the server.
+                let local = match trimmed.to_ascii_uppercase().as_str() {
+                    "" => true,
+                    "HELP" | "?" => {
+                        print_help();
+                        true
+                    }
+                    "BOARD" | "SHOW" => {
+                        print_board(board.as_ref());
+                        true
+                    }
+                    _ => false,
+                };
+                if local {
+                    if my_turn || draw_pending || undo_pending {
+                        print_prompt(player_id);
+                    }
+                    continue;
+                }
+                // Slash commands are routed before the gameplay parser.
+                let slash = parse_slash(trimmed);
+                if slash.is_none()
+                    && !my_turn && !draw_pending && !undo_pending && !trimmed.eq_ignore_ascii_case("undo")
+                {
+                    println!("  ? not your turn — type 'board' to see the board");
+                    continue;
+                }
+
+                match slash.unwrap_or_else(|| parse_line(trimmed, board.as_ref())) {
+                    Ok(cmd) => {
+                        let allowed_any_time = matches!(
+                            cmd,
+                            ClientCmd::DrawAccept
+                                | ClientCmd::DrawDecline
+                                | ClientCmd::Undo
+                                | ClientCmd::UndoAccept
+                                | ClientCmd::UndoDecline
+                                | ClientCmd::Name(_)
+                                | ClientCmd::Skin(_)
+                                | ClientCmd::Chat(_)
+                        );
+                        if !my_turn && !allowed_any_time {
+                            println!("  ? not your turn — you can only answer the pending offer");
+                            print_prompt(player_id);
+                            continue;
+                        }
+                        if let Err(reason) = validate(&cmd, board.as_ref(), rules.as_ref(), player_id) {
+                            println!("  ? {reason}");
+                            print_prompt(player_id);
+                            continue;
+                        }
+                        // Tag commands answered by OK or ERROR so the reply
+                        // can be matched to what was sent.
+                        let tagged = cmd.is_move()
+                            || matches!(
+                                cmd,
+                                ClientCmd::Name(_)
+                                    | ClientCmd::Skin(_)
+                                    | ClientCmd::Chat(_)
+                                    | ClientCmd::DrawAccept
+                                    | ClientCmd::UndoAccept
+                            );
+                        let tag = tagged.then(|| {
+                            next_tag += 1;
+                            pending.insert(next_tag.to_string(), cmd.to_wire().trim_end().to_string());
+                            next_tag.to_string()
+                        });
+                        let wire = match &tag {
+                            Some(tag) => cmd.to_wire_tagged(tag),
+                            None      => cmd.to_wire(),
+                        };
+                        log.of(Category::Protocol).verbose(ClientEvent::Sending { cmd: wire.trim_end() });
+                        transcript.sent(wire.trim_end());
+                        let sent = match &tag {
+                            Some(tag) => client.send_tagged(&cmd, tag).await,
+                            None      => client.send(&cmd).await,
+                        };
+                        if sent.is_err() {
+                            eprintln!("Failed to send command.");
+                            break;
+                        }
+                        match cmd {
+                            // Offering a draw does not use up the turn.
+                            ClientCmd::DrawOffer => {
+                                println!("  Draw offered.");
+                                print_prompt(player_id);
+                            }
+                            ClientCmd::DrawAccept | ClientCmd::DrawDecline => {
+                                draw_pending = false;
+                                if my_turn {
+                                    print_prompt(player_id);
+                                }
+                            }
+                            ClientCmd::Undo => {
+                                println!("  Undo requested.");
+                                if my_turn {
+                                    print_prompt(player_id);
+                                }
+                            }
+                            ClientCmd::UndoAccept | ClientCmd::UndoDecline => {
+                                undo_pending = false;
+                                if my_turn {
+                                    print_prompt(player_id);
+                                }
+                            }
+                            // The reply is matched by its tag, so these leave
+                            // the turn alone.
+                            ClientCmd::Name(name) => {
+                                println!("  You are now {name}.");
+                                if my_turn {
+                                    print_prompt(player_id);
+                                }
+                            }
+                            ClientCmd::Skin(skin) => {
+                                println!("  Your pieces are now {skin}.");
+                                if my_turn {
+                                    print_prompt(player_id);
+                                }
+                            }
+                            ClientCmd::Chat(_) => {
+                                if my_turn {
+                                    print_prompt(player_id);
+                                }
+                            }
+                            // The RATING reply re-prompts.
+                            ClientCmd::Rating(_) => {
+                                awaiting  = true;
+                                reply_due = Some((Instant::now() + reply_timeout, false));
+                            }
+                            // Disable stdin until the server responds (OK or
+                            // ERROR), or until it is clearly not going to.
+                            _ => {
+                                awaiting  = true;
+                                my_turn   = false;
+                                reply_due = Some((Instant::now() + reply_timeout, true));
+                            }
+                        }
+                    }
+                    Err(reason) => {
+                        println!("  ? {reason}");
+                        print_help();
+                        print_prompt(player_id);
+                    }
+                }
+            }
+        }
+    }
+
+    transcript.finish();
+}
@@ -0,0 +1,113 @@
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress, Status};
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, ReadBuf};
+
+/// Raw deflate over everything the server sends after `COMPRESS deflate`.
+///
+/// The stream is one deflate stream for the life of the connection, so each
+/// `STATE` is compressed against the ones before it, and every line ends in
+/// a sync flush so the client can decode it as soon as it arrives.  The
+/// logical protocol underneath is unchanged: inflate the bytes and read
+/// lines as usual.
+pub struct LineDeflater {
+    z: Compress,
+}
+
+impl Default for LineDeflater {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LineDeflater {
+    pub fn new() -> Self {
+        Self { z: Compress::new(Compression::default(), false) }
+    }
+
+    /// The compressed bytes for `line`, ready to write to the socket.
+    pub fn compress(&mut self, line: &[u8]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(line.len() / 2 + 64);
+        let start = self.z.total_in();
+        loop {
+            let done = (self.z.total_in() - start) as usize;
+            if out.capacity() - out.len() < 64 {
+                out.reserve(out.capacity().max(64));
+            }
+            self.z
+                .compress_vec(&line[done..], &mut out, FlushCompress::Sync)
+                .expect("deflate never fails on valid input");
+            // A sync flush is complete once all input is taken and there was
+            // room left over for the output.
+            if (self.z.total_in() - start) as usize == line.len() && out.len() < out.capacity() {
+                return out;
+            }
+        }
+    }
+}
+
+/// Inflates a [`LineDeflater`] stream as it is read from `inner`.
+pub struct InflateReader<R> {
+    inner:   R,
+    z:       Decompress,
+    /// Compressed bytes read but not yet inflated.
+    input:   Vec<u8>,
+    /// Inflated bytes not yet handed to the caller.
+    output:  Vec<u8>,
+}
+
+impl<R> InflateReader<R> {
+    /// Inflate `inner`, starting with `buffered`: compressed bytes that were
+    /// already read from it before the switch.
+    pub fn new(inner: R, buffered: Vec<u8>) -> Self {
+        Self { inner, z: Decompress::new(false), input: buffered, output: Vec::new() }
+    }
+
+    /// Inflate as much of `input` as possible into `output`.
+    fn inflate(&mut self) -> io::Result<()> {
+        while !self.input.is_empty() {
+            let (before_in, before_out) = (self.z.total_in(), self.z.total_out());
+            self.output.reserve(self.input.len() * 4 + 256);
+            let status = self
+                .z
+                .decompress_vec(&self.input, &mut self.output, FlushDecompress::None)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            let used = (self.z.total_in() - before_in) as usize;
+            self.input.drain(..used);
+            if status == Status::StreamEnd {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "deflate stream ended early"));
+            }
+            if used == 0 && self.z.total_out() == before_out {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for InflateReader<R> {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+        loop {
+            if !this.output.is_empty() {
+                let n = this.output.len().min(buf.remaining());
+                buf.put_slice(&this.output[..n]);
+                this.output.drain(..n);
+                return Poll::Ready(Ok(()));
+            }
+
+            let mut chunk = [0u8; 4096];
+            let mut read = ReadBuf::new(&mut chunk);
+            match Pin::new(&mut this.inner).poll_read(cx, &mut read) {
+                Poll::Ready(Ok(())) if read.filled().is_empty() => return Poll::Ready(Ok(())),
+                Poll::Ready(Ok(())) => {
+                    this.input.extend_from_slice(read.filled());
+                    this.inflate()?;
+                }
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
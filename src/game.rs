@@ -1,10 +1,21 @@
+use crate::board::{BoardState, Piece};
+use crate::protocol::{ClientCmd, ServerMsg};
+use bevy::ecs::message::MessageCursor;
+use bevy::ecs::system::RunSystemOnce;
 use bevy::prelude::*;
+use bevy::tasks::{ComputeTaskPool, ParallelSlice, TaskPool};
+use fixedbitset::FixedBitSet;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Mutex;
+use std::sync::mpsc::Receiver;
+use std::time::Duration;
 
 //
 // PUBLIC TYPES
 //
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct PlayerId(pub u32);
 
 #[derive(Debug, Clone)]
@@ -19,49 +30,445 @@ pub struct Player {
 pub const GRID_WIDTH: i32 = 500;
 pub const GRID_HEIGHT: i32 = 500;
 pub const FIXED_TIMESTEP: f32 = 1.0 / 120.0;
+/// Speed below which a body counts as at rest for end-of-game checks.
+pub const REST_SPEED: f32 = 0.01;
+
+/// How moving pieces lose speed between steps.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FrictionModel {
+    /// No friction: pieces keep their speed until something hits them.
+    None,
+    /// Constant deceleration in world units per second²: pieces slow evenly
+    /// and stop dead, so turns settle quickly.
+    Linear(f32),
+    /// Velocity is multiplied by this factor every step.  Never quite
+    /// reaches zero; pieces creep until they fall below [`REST_SPEED`].
+    Exponential(f32),
+}
+
+impl FrictionModel {
+    /// `vel` after one step of `dt` seconds under this model.
+    pub fn apply(self, vel: Vec2, dt: f32) -> Vec2 {
+        match self {
+            Self::None => vel,
+            Self::Linear(decel) => {
+                let speed = vel.length();
+                vel.normalize_or_zero() * (speed - decel * dt).max(0.0)
+            }
+            Self::Exponential(factor) => vel * factor,
+        }
+    }
+}
+
+/// Tunable physics parameters.
+#[derive(Resource)]
+pub struct PhysicsConfig {
+    /// Hard cap on body speed, so an extreme shot cannot tunnel through
+    /// other pieces or leave the board in a single step.
+    pub max_speed: f32,
+    /// Seconds advanced per physics step.
+    pub timestep: f32,
+    /// Piece count from which collision contacts are worked out across
+    /// threads.  Either way the step ends the same.
+    pub parallel_threshold: usize,
+    /// Constant acceleration applied to every non-`Static` body; zero for a
+    /// flat table.
+    pub gravity: Vec2,
+    /// Contact friction coefficient that turns glancing hits into spin.
+    /// `None` disables rotation entirely: pieces get no [`Rotation`] or
+    /// [`AngularVelocity`] and collisions stay frictionless.
+    pub spin_friction: Option<f32>,
+    /// Rolling friction slowing every moving piece.
+    pub friction: FrictionModel,
+    /// Collision impulse above which the weaker of the two pieces shatters
+    /// and is taken off the board.  Infinite, the default, never shatters.
+    pub destroy_impulse: f32,
+}
+
+impl Default for PhysicsConfig {
+    fn default() -> Self {
+        Self {
+            max_speed: 2000.0,
+            timestep: FIXED_TIMESTEP,
+            parallel_threshold: 1000,
+            gravity: Vec2::ZERO,
+            spin_friction: None,
+            friction: FrictionModel::Exponential(0.99),
+            destroy_impulse: f32::INFINITY,
+        }
+    }
+}
+
+/// Pause switch for the physics.  While paused, motion and collisions stand
+/// still except for single steps requested with [`SimControl::step`].
+#[derive(Resource, Default)]
+pub struct SimControl {
+    pub paused: bool,
+    pub step_requested: bool,
+}
+
+impl SimControl {
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+        self.step_requested = false;
+    }
+
+    /// Let exactly one physics step run while paused.
+    pub fn step(&mut self) {
+        self.step_requested = true;
+    }
+
+    /// Whether the next physics step should run.
+    pub fn running(&self) -> bool {
+        !self.paused || self.step_requested
+    }
+}
 
 //
 // BOARD (Authoritative occupancy grid)
 //
 
+/// Side, in cells, of the square blocks [`Board`] indexes pieces by.
+const TILE: i32 = 16;
+
+/// Cells are addressed by world coordinates.  `origin` is the world cell
+/// stored at grid index (0, 0), so a board with origin (-250, -250) covers
+/// the centred space -250..250 on a 500 × 500 grid.
 #[derive(Resource)]
 pub struct Board {
-    /// Maps cell -> Entity occupying it
-    cells: Vec<Option<Entity>>,
+    width: i32,
+    height: i32,
+    origin: IVec2,
+    /// One occupancy bit per cell for each owner on the board
+    owners: Vec<(PlayerId, FixedBitSet)>,
+    /// Every piece stamped since the last clear, oldest first.  A cell
+    /// belongs to the newest piece covering it whose owner still holds it.
+    pieces: Vec<Footprint>,
+    /// For each `TILE` × `TILE` block of cells, the pieces reaching into it.
+    tiles: Vec<Vec<u32>>,
+}
+
+/// The disc of cells one piece was stamped over.
+struct Footprint {
+    entity: Entity,
+    /// Index into [`Board::owners`].
+    owner: usize,
+    centre: Vec2,
+    radius: f32,
+}
+
+impl Footprint {
+    fn covers(&self, x: i32, y: i32) -> bool {
+        (Vec2::new(x as f32, y as f32) - self.centre).length_squared() <= self.radius * self.radius
+    }
 }
 
 impl Board {
     pub fn new() -> Self {
+        Self::with_size(GRID_WIDTH, GRID_HEIGHT)
+    }
+
+    /// Empty board of `width` × `height` cells.
+    pub fn with_size(width: i32, height: i32) -> Self {
+        let (width, height) = (width.max(0), height.max(0));
+        let tiles = ((width + TILE - 1) / TILE) * ((height + TILE - 1) / TILE);
         Self {
-            cells: vec![None; (GRID_WIDTH * GRID_HEIGHT) as usize],
+            width,
+            height,
+            origin: IVec2::ZERO,
+            owners: Vec::new(),
+            pieces: Vec::new(),
+            tiles: vec![Vec::new(); tiles as usize],
         }
     }
 
+    /// Empty board of `width` × `height` cells centred on world (0, 0).
+    pub fn centered(width: i32, height: i32) -> Self {
+        Self::with_size(width, height).with_origin(IVec2::new(-width / 2, -height / 2))
+    }
+
+    /// The same board with grid cell (0, 0) at world cell `origin`.  Every
+    /// cell is cleared.
+    pub fn with_origin(self, origin: IVec2) -> Self {
+        Self { origin, ..Self::with_size(self.width, self.height) }
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    pub fn origin(&self) -> IVec2 {
+        self.origin
+    }
+
+    /// Change the dimensions, keeping the origin.  Every cell is cleared,
+    /// since old indices no longer map to the same positions.
+    pub fn resize(&mut self, width: i32, height: i32) {
+        *self = Self::with_size(width, height).with_origin(self.origin);
+    }
+
+    /// The grid cell holding world cell (`x`, `y`), if it is on the board.
+    pub fn grid_cell(&self, x: i32, y: i32) -> Option<IVec2> {
+        let cell = IVec2::new(x, y) - self.origin;
+        let on_board = cell.x >= 0 && cell.y >= 0 && cell.x < self.width && cell.y < self.height;
+        on_board.then_some(cell)
+    }
+
     #[inline]
-    fn index(x: i32, y: i32) -> usize {
-        (y * GRID_WIDTH + x) as usize
+    fn index(&self, x: i32, y: i32) -> usize {
+        let cell = IVec2::new(x, y) - self.origin;
+        (cell.y * self.width + cell.x) as usize
+    }
+
+    #[inline]
+    fn in_bounds(&self, x: i32, y: i32) -> bool {
+        self.grid_cell(x, y).is_some()
+    }
+
+    /// The tile holding grid cell `cell`.
+    fn tile(&self, cell: IVec2) -> usize {
+        let per_row = (self.width + TILE - 1) / TILE;
+        ((cell.y / TILE) * per_row + cell.x / TILE) as usize
     }
 
     pub fn clear(&mut self) {
-        self.cells.fill(None);
+        for (_, bits) in &mut self.owners {
+            bits.clear();
+        }
+        self.pieces.clear();
+        for tile in &mut self.tiles {
+            tile.clear();
+        }
+    }
+
+    /// The piece covering the cell, if any.
+    fn piece_at(&self, x: i32, y: i32) -> Option<&Footprint> {
+        let cell = self.grid_cell(x, y)?;
+        let idx = self.index(x, y);
+        self.tiles[self.tile(cell)]
+            .iter()
+            .rev()
+            .map(|&i| &self.pieces[i as usize])
+            .find(|piece| piece.covers(x, y) && self.owners[piece.owner].1.contains(idx))
     }
 
     pub fn get(&self, x: i32, y: i32) -> Option<Entity> {
-        if x >= 0 && y >= 0 && x < GRID_WIDTH && y < GRID_HEIGHT {
-            self.cells[Self::index(x, y)]
-        } else {
-            None
+        self.piece_at(x, y).map(|piece| piece.entity)
+    }
+
+    /// Whether any piece covers the cell.
+    pub fn occupied(&self, x: i32, y: i32) -> bool {
+        self.owner_at(x, y).is_some()
+    }
+
+    /// Owner of the piece covering the cell, if any.
+    pub fn owner_at(&self, x: i32, y: i32) -> Option<PlayerId> {
+        self.piece_at(x, y).map(|piece| self.owners[piece.owner].0)
+    }
+
+    pub fn set(&mut self, x: i32, y: i32, occupant: Option<(Entity, PlayerId)>) {
+        if !self.in_bounds(x, y) {
+            return;
+        }
+        if let Some(owner) = self.piece_at(x, y).map(|piece| piece.owner) {
+            let idx = self.index(x, y);
+            self.owners[owner].1.set(idx, false);
+        }
+        if let Some((entity, owner)) = occupant {
+            self.stamp(entity, owner, Vec2::new(x as f32, y as f32), 0.0);
         }
     }
 
-    pub fn set(&mut self, x: i32, y: i32, entity: Option<Entity>) {
-        if x >= 0 && y >= 0 && x < GRID_WIDTH && y < GRID_HEIGHT {
-            let idx = Self::index(x, y);
-            self.cells[idx] = entity;
+    /// Cover every cell within `radius` of `centre` with `entity`, taking
+    /// each over from whatever covered it before.
+    pub fn stamp(&mut self, entity: Entity, owner: PlayerId, centre: Vec2, radius: f32) {
+        // Round outwards: truncating would lose a cell left of or below
+        // zero.
+        let last = self.origin + IVec2::new(self.width - 1, self.height - 1);
+        let min = (centre - radius).floor().as_ivec2().max(self.origin);
+        let max = (centre + radius).ceil().as_ivec2().min(last);
+        if min.x > max.x || min.y > max.y {
+            return;
+        }
+
+        let slot = match self.owners.iter().position(|(o, _)| *o == owner) {
+            Some(slot) => slot,
+            None => {
+                let cells = (self.width * self.height) as usize;
+                self.owners.push((owner, FixedBitSet::with_capacity(cells)));
+                self.owners.len() - 1
+            }
+        };
+        let index = self.pieces.len() as u32;
+        self.pieces.push(Footprint { entity, owner: slot, centre, radius });
+        let (low, high) = (min - self.origin, max - self.origin);
+        for ty in low.y / TILE..=high.y / TILE {
+            for tx in low.x / TILE..=high.x / TILE {
+                let tile = self.tile(IVec2::new(tx, ty) * TILE);
+                self.tiles[tile].push(index);
+            }
+        }
+
+        let piece = &self.pieces[index as usize];
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                if !piece.covers(x, y) {
+                    continue;
+                }
+                let idx = self.index(x, y);
+                if let Some(before) = self.piece_at(x, y).map(|p| p.owner)
+                    && before != slot
+                {
+                    self.owners[before].1.set(idx, false);
+                }
+                self.owners[slot].1.insert(idx);
+            }
+        }
+    }
+
+    /// Entity covering the cell nearest to a world-space position.
+    pub fn entity_at(&self, pos: Vec2) -> Option<Entity> {
+        let cell = pos.round();
+        self.get(cell.x as i32, cell.y as i32)
+    }
+
+    pub fn is_occupied(&self, pos: Vec2) -> bool {
+        self.entity_at(pos).is_some()
+    }
+
+    /// First piece a ray from `origin` along `dir` runs into, with the point
+    /// where the ray enters that piece's first cell.  Cells covered by the
+    /// piece at `origin` are passed through, so a shot previewed from a
+    /// piece's centre does not report the piece itself.
+    pub fn raycast(&self, origin: Vec2, dir: Vec2) -> Option<(Entity, Vec2)> {
+        let dir = dir.normalize_or_zero();
+        if dir == Vec2::ZERO || !origin.is_finite() {
+            return None;
+        }
+        let shooter = self.entity_at(origin);
+
+        // Cells are centred on integer coordinates, hence the half-cell shift.
+        let start = origin + Vec2::splat(0.5);
+
+        // Clip the ray to the board first, so an origin far off the board
+        // costs no more than one on it.
+        let low = self.origin.as_vec2();
+        let high = low + Vec2::new(self.width as f32, self.height as f32);
+        let (mut t_in, mut t_out) = (0.0_f32, f32::INFINITY);
+        let mut entry_axis = None;
+        for axis in 0..2 {
+            if dir[axis] == 0.0 {
+                if start[axis] < low[axis] || start[axis] >= high[axis] {
+                    return None;
+                }
+                continue;
+            }
+            let a = (low[axis] - start[axis]) / dir[axis];
+            let b = (high[axis] - start[axis]) / dir[axis];
+            if a.min(b) > t_in {
+                t_in = a.min(b);
+                entry_axis = Some(axis);
+            }
+            t_out = t_out.min(a.max(b));
+        }
+        if t_in >= t_out {
+            return None;
+        }
+
+        // Visit cells in the order the ray crosses them (Amanatides–Woo),
+        // from where it enters the board.  Distances are measured from the
+        // entry point: from a far origin they would be too large for f32 to
+        // add a cell's width to.
+        let mut entry = start + dir * t_in;
+        if let Some(axis) = entry_axis {
+            // Exactly on the edge crossed, whatever the rounding above.
+            entry[axis] = if dir[axis] > 0.0 { low[axis] } else { high[axis] };
+        }
+        let last = self.origin + IVec2::new(self.width - 1, self.height - 1);
+        let mut cell = entry.floor().as_ivec2().clamp(self.origin, last);
+        let step = IVec2::new(
+            if dir.x < 0.0 { -1 } else { 1 },
+            if dir.y < 0.0 { -1 } else { 1 },
+        );
+        // Ray length per cell crossed on each axis; infinite along an axis
+        // the ray never moves on.
+        let t_delta = dir.recip().abs();
+        let to_edge = |s: f32, c: i32, d: f32| {
+            if d < 0.0 { s - c as f32 } else { (c + 1) as f32 - s }
+        };
+        let mut t_next = Vec2::new(
+            to_edge(entry.x, cell.x, dir.x) * t_delta.x,
+            to_edge(entry.y, cell.y, dir.y) * t_delta.y,
+        );
+
+        // Every step moves one cell on, so the march leaves the board after
+        // at most width + height cells.
+        let mut t = 0.0;
+        while self.in_bounds(cell.x, cell.y) {
+            if let Some(entity) = self.get(cell.x, cell.y)
+                && Some(entity) != shooter
+            {
+                return Some((entity, entry - Vec2::splat(0.5) + dir * t));
+            }
+            if t_next.x < t_next.y {
+                t = t_next.x;
+                t_next.x += t_delta.x;
+                cell.x += step.x;
+            } else {
+                t = t_next.y;
+                t_next.y += t_delta.y;
+                cell.y += step.y;
+            }
         }
+        None
+    }
+
+    /// Number of occupied cells per owner, for territory scoring.
+    pub fn area_by_owner(&self) -> HashMap<PlayerId, usize> {
+        self.owners
+            .iter()
+            .map(|(owner, bits)| (*owner, bits.count_ones(..)))
+            .filter(|&(_, area)| area > 0)
+            .collect()
+    }
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
+/// Cells covered by each owner as of the last board rebuild.
+#[derive(Resource, Default)]
+pub struct Territory(pub HashMap<PlayerId, usize>);
+
+/// Total translational kinetic energy (Σ ½·m·v²) of the non-`Static` bodies
+/// after the last physics step.  Growth without input means the solver is
+/// adding energy.
+#[derive(Resource, Default)]
+pub struct SystemEnergy(pub f32);
+
+/// How far [`shatter_pieces`] has read the collisions.  Kept in the world
+/// so that single [`step`]s, each running a fresh system, never read the
+/// same collision twice.
+#[derive(Resource, Default)]
+struct ShatterCursor(MessageCursor<CollisionEvent>);
+
+/// Physics steps run so far; paused steps do not count.  A server stamps it
+/// on each `STATE` so a predicting client can tell which of its inputs the
+/// board already includes.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SimTick(pub u64);
+
 //
 // COMPONENTS
 //
@@ -84,11 +491,19 @@ pub struct Radius(pub f32);
 #[derive(Component)]
 pub struct Static; // marker
 
+/// Orientation in radians, counter-clockwise.
+#[derive(Component)]
+pub struct Rotation(pub f32);
+
+/// Spin in radians per second, counter-clockwise.
+#[derive(Component)]
+pub struct AngularVelocity(pub f32);
+
 //
 // COMMAND API
 //
 
-#[derive(Event)]
+#[derive(Message, Debug, Clone)]
 pub enum GameCommand {
     PlacePiece {
         position: Vec2,
@@ -100,6 +515,261 @@ pub enum GameCommand {
         direction: Vec2,
         force: f32,
     },
+    /// Take a piece off the board, e.g. when it is eliminated.
+    RemovePiece {
+        entity: Entity,
+    },
+    /// Put a piece exactly where the server has it, at rest.  `entity` may
+    /// be one reserved with `spawn_empty`; it gets a piece's components.
+    SetPiece {
+        entity: Entity,
+        position: Vec2,
+        radius: f32,
+        owner: PlayerId,
+    },
+}
+
+/// Fired whenever two pieces collide with a real impulse, for sound and VFX.
+/// `impulse` is the impulse magnitude, so consumers can tell hard hits from soft.
+#[derive(Message)]
+pub struct CollisionEvent {
+    pub a: Entity,
+    pub b: Entity,
+    pub impulse: f32,
+    pub point: Vec2,
+}
+
+/// Fired when a `RemovePiece` command takes a piece off the board, or a
+/// hard enough hit shatters it.
+#[derive(Message)]
+pub struct PieceRemoved {
+    pub entity: Entity,
+    pub owner: PlayerId,
+}
+
+/// Fired once when a player is eliminated; `winner` is `None` if nobody is left.
+#[derive(Message)]
+pub struct GameEnded {
+    pub winner: Option<PlayerId>,
+}
+
+//
+// TURN STATE
+//
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GameOutcome {
+    pub winner: Option<PlayerId>,
+}
+
+#[derive(Resource, Default)]
+pub struct TurnState {
+    /// Every player who has placed a piece, in order of first placement.
+    pub players: Vec<PlayerId>,
+    /// Set once the game is decided.
+    pub outcome: Option<GameOutcome>,
+}
+
+//
+// SNAPSHOT (save/load and network sync)
+//
+
+/// One piece as stored in a [`GameSnapshot`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PieceSnapshot {
+    pub owner: PlayerId,
+    pub position: [f32; 2],
+    pub velocity: [f32; 2],
+    pub radius: f32,
+    pub mass: f32,
+    pub is_static: bool,
+}
+
+/// Every piece in the world, in entity order.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GameSnapshot {
+    pub pieces: Vec<PieceSnapshot>,
+}
+
+pub type PieceQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Owner,
+        &'static Position,
+        &'static Velocity,
+        &'static Radius,
+        &'static Mass,
+        Has<Static>,
+    ),
+>;
+
+impl GameSnapshot {
+    pub fn capture(query: &PieceQuery) -> Self {
+        let mut rows: Vec<_> = query.iter().collect();
+        rows.sort_by_key(|row| row.0);
+
+        let pieces = rows
+            .into_iter()
+            .map(|(_, owner, pos, vel, radius, mass, is_static)| PieceSnapshot {
+                owner: owner.0,
+                position: pos.0.to_array(),
+                velocity: vel.0.to_array(),
+                radius: radius.0,
+                mass: mass.0,
+                is_static,
+            })
+            .collect();
+
+        Self { pieces }
+    }
+
+    /// Despawn every existing piece and respawn the snapshot's pieces in order.
+    pub fn restore(&self, commands: &mut Commands, existing: &Query<Entity, With<Owner>>) {
+        for entity in existing {
+            commands.entity(entity).despawn();
+        }
+
+        for piece in &self.pieces {
+            let mut entity = commands.spawn((
+                Position(Vec2::from_array(piece.position)),
+                Velocity(Vec2::from_array(piece.velocity)),
+                Mass(piece.mass),
+                Radius(piece.radius),
+                Owner(piece.owner),
+            ));
+            if piece.is_static {
+                entity.insert(Static);
+            }
+        }
+    }
+}
+
+//
+// INTERPOLATION (smooth rendering between STATE frames)
+//
+
+/// The last two `STATE` frames a client received and when they arrived.
+///
+/// Frames come at the network rate, not the frame rate, so drawing each one
+/// as it lands makes pieces jump.  Rendering a little behind the clock keeps
+/// the render time between the two frames, where positions can be blended.
+#[derive(Resource, Debug, Default)]
+pub struct SnapshotBuffer {
+    delay: Duration,
+    prev: Option<(Duration, BoardState)>,
+    latest: Option<(Duration, BoardState)>,
+}
+
+impl SnapshotBuffer {
+    /// Render `delay` behind the arrival clock; about one frame interval
+    /// keeps the render time between the last two frames.
+    pub fn new(delay: Duration) -> Self {
+        Self { delay, ..Self::default() }
+    }
+
+    /// Record a frame that arrived at `at` (e.g. `Time::elapsed`).  A frame
+    /// older than the latest one is out of date and ignored.
+    pub fn push(&mut self, at: Duration, state: BoardState) {
+        if self.latest.as_ref().is_some_and(|(_, latest)| state.seq < latest.seq) {
+            return;
+        }
+        self.prev = self.latest.replace((at, state));
+    }
+
+    /// Every piece whose position is known, by index, as it stood at
+    /// `now` minus the delay.  Pieces are matched across frames by index and
+    /// owner; one without a match in the older frame is drawn where it is now.
+    pub fn interpolated_positions(&self, now: Duration) -> Vec<(usize, Vec2)> {
+        let Some((t1, latest)) = &self.latest else { return Vec::new() };
+        let (t, prev) = match &self.prev {
+            Some((t0, prev)) => {
+                let span = t1.saturating_sub(*t0).as_secs_f32();
+                let elapsed = now.saturating_sub(self.delay).saturating_sub(*t0).as_secs_f32();
+                let t = if span > 0.0 { (elapsed / span).clamp(0.0, 1.0) } else { 1.0 };
+                (t, Some(prev))
+            }
+            None => (1.0, None),
+        };
+
+        let known = |p: &&Piece| p.x.is_finite() && p.y.is_finite();
+        latest
+            .pieces
+            .iter()
+            .filter(known)
+            .map(|p| {
+                let to = Vec2::new(p.x, p.y);
+                let from = prev
+                    .and_then(|prev| prev.pieces.get(p.index))
+                    .filter(|q| q.owner == p.owner)
+                    .filter(known)
+                    .map_or(to, |q| Vec2::new(q.x, q.y));
+                (p.index, from.lerp(to, t))
+            })
+            .collect()
+    }
+}
+
+//
+// PREDICTION (client inputs not yet in a STATE)
+//
+
+/// Commands a client has applied locally ahead of the server, each with the
+/// tick it was applied at.  Once a `STATE` confirms a tick, the inputs up to
+/// it are part of the authoritative board and are dropped; the rest are
+/// replayed on top of that board.
+#[derive(Resource, Debug, Default)]
+pub struct PredictedInputs {
+    confirmed: u64,
+    inputs: VecDeque<(u64, GameCommand)>,
+}
+
+impl PredictedInputs {
+    pub fn push(&mut self, tick: u64, cmd: GameCommand) {
+        self.inputs.push_back((tick, cmd));
+    }
+
+    /// The server's board has reached `tick`.  Ticks only move forward, so
+    /// an older one (from a reordered frame) is ignored.
+    pub fn confirm(&mut self, tick: u64) {
+        if tick < self.confirmed {
+            return;
+        }
+        self.confirmed = tick;
+        while self.inputs.front().is_some_and(|&(t, _)| t <= tick) {
+            self.inputs.pop_front();
+        }
+    }
+
+    /// The last tick the server confirmed.
+    pub fn confirmed(&self) -> u64 {
+        self.confirmed
+    }
+
+    /// Inputs still to be replayed, oldest first.
+    pub fn pending(&self) -> impl Iterator<Item = &(u64, GameCommand)> {
+        self.inputs.iter()
+    }
+}
+
+//
+// PIECE QUERIES
+//
+
+pub type OwnedPieceQuery<'w, 's> =
+    Query<'w, 's, (Entity, &'static Owner, &'static Position, &'static Radius)>;
+
+/// Every piece `owner` has on the board as `(entity, position, radius)`, in
+/// entity order so callers such as bots behave the same on every run.
+pub fn pieces_of(owner: PlayerId, query: &OwnedPieceQuery) -> Vec<(Entity, Vec2, f32)> {
+    let mut pieces: Vec<_> = query
+        .iter()
+        .filter(|(_, o, ..)| o.0 == owner)
+        .map(|(entity, _, pos, radius)| (entity, pos.0, radius.0))
+        .collect();
+    pieces.sort_by_key(|piece| piece.0);
+    pieces
 }
 
 //
@@ -111,7 +781,17 @@ pub struct GamePlugin;
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(Board::new())
-            .add_event::<GameCommand>()
+            .insert_resource(TurnState::default())
+            .init_resource::<PhysicsConfig>()
+            .init_resource::<Territory>()
+            .init_resource::<SystemEnergy>()
+            .init_resource::<SimControl>()
+            .init_resource::<SimTick>()
+            .init_resource::<ShatterCursor>()
+            .add_message::<GameCommand>()
+            .add_message::<CollisionEvent>()
+            .add_message::<PieceRemoved>()
+            .add_message::<GameEnded>()
             .add_systems(
                 Update,
                 (
@@ -122,20 +802,127 @@ impl Plugin for GamePlugin {
             .add_systems(
                 FixedUpdate,
                 (
-                    integrate_motion,
-                    resolve_collisions,
-                    rebuild_board.after(resolve_collisions),
+                    (integrate_motion, integrate_rotation, resolve_collisions, advance_tick)
+                        .run_if(|control: Res<SimControl>| control.running()),
+                    shatter_pieces.after(resolve_collisions),
+                    rebuild_board.after(shatter_pieces),
+                    measure_energy.after(resolve_collisions),
+                    update_territory.after(rebuild_board),
+                    check_win_condition.after(rebuild_board),
+                    consume_step_request
+                        .after(integrate_motion)
+                        .after(integrate_rotation)
+                        .after(resolve_collisions),
                 ),
             );
     }
 }
 
+//
+// SINGLE STEP (tests and tools)
+//
+
+/// Advance the simulation by exactly one physics step of `dt` seconds,
+/// running motion, collisions and the board rebuild once each, in order.
+/// Needs no `App` or clock, so results depend only on the world's contents.
+/// Honours a [`SimControl`] in the world: a paused world does not move
+/// unless a single step was requested.
+pub fn step(world: &mut World, dt: f32) {
+    prepare_step(world);
+    world.resource_mut::<PhysicsConfig>().timestep = dt;
+
+    if let Some(mut control) = world.get_resource_mut::<SimControl>() {
+        if !control.running() {
+            return;
+        }
+        control.step_requested = false;
+    }
+
+    world
+        .run_system_once(integrate_motion)
+        .expect("integrate_motion is a valid system");
+    world
+        .run_system_once(integrate_rotation)
+        .expect("integrate_rotation is a valid system");
+    step_collisions(world);
+    world
+        .run_system_once(measure_energy)
+        .expect("measure_energy is a valid system");
+    step_board(world);
+    world.resource_mut::<SimTick>().0 += 1;
+}
+
+/// Run only the collision pass of [`step`], shattering pieces hit harder
+/// than [`PhysicsConfig::destroy_impulse`].
+pub fn step_collisions(world: &mut World) {
+    prepare_step(world);
+    world
+        .run_system_once(resolve_collisions)
+        .expect("resolve_collisions is a valid system");
+    world
+        .run_system_once(shatter_pieces)
+        .expect("shatter_pieces is a valid system");
+}
+
+/// Run only the board rebuild of [`step`].
+pub fn step_board(world: &mut World) {
+    prepare_step(world);
+    world
+        .run_system_once(rebuild_board)
+        .expect("rebuild_board is a valid system");
+}
+
+/// Apply every pending [`GameCommand`], as the plugin does ahead of each
+/// step, and drop them so the next call does not apply them again.  Spawns
+/// and despawns have taken effect when this returns.
+pub fn step_commands(world: &mut World) {
+    prepare_step(world);
+    world
+        .run_system_once(process_commands)
+        .expect("process_commands is a valid system");
+    world.resource_mut::<Messages<GameCommand>>().clear();
+}
+
+/// Run the win check the plugin runs after each step, settling
+/// [`TurnState::outcome`] and firing [`GameEnded`] once the board is at rest.
+pub fn step_outcome(world: &mut World) {
+    prepare_step(world);
+    world
+        .run_system_once(check_win_condition)
+        .expect("check_win_condition is a valid system");
+}
+
+/// Insert whatever the physics systems need that `world` lacks.
+fn prepare_step(world: &mut World) {
+    if !world.contains_resource::<Board>() {
+        world.insert_resource(Board::new());
+    }
+    world.init_resource::<Messages<GameCommand>>();
+    world.init_resource::<Messages<CollisionEvent>>();
+    world.init_resource::<Messages<PieceRemoved>>();
+    world.init_resource::<Messages<GameEnded>>();
+    world.init_resource::<TurnState>();
+    world.init_resource::<ShatterCursor>();
+    world.init_resource::<SystemEnergy>();
+    world.init_resource::<SimTick>();
+    world.get_resource_or_insert_with(PhysicsConfig::default);
+}
+
 //
 // FIXED TIMESTEP DRIVER
 //
 
-fn fixed_step_driver(mut time: ResMut<Time>) {
-    time.set_timestep(FIXED_TIMESTEP);
+fn fixed_step_driver(mut time: ResMut<Time<Fixed>>) {
+    time.set_timestep(Duration::from_secs_f32(FIXED_TIMESTEP));
+}
+
+fn advance_tick(mut tick: ResMut<SimTick>) {
+    tick.0 += 1;
+}
+
+/// A requested single step has now run.
+fn consume_step_request(mut control: ResMut<SimControl>) {
+    control.step_requested = false;
 }
 
 //
@@ -144,9 +931,17 @@ fn fixed_step_driver(mut time: ResMut<Time>) {
 
 fn process_commands(
     mut commands: Commands,
-    mut events: EventReader<GameCommand>,
+    mut events: MessageReader<GameCommand>,
+    mut turn: ResMut<TurnState>,
+    mut removed: MessageWriter<PieceRemoved>,
+    config: Res<PhysicsConfig>,
     query: Query<&Position>,
+    owners: Query<&Owner>,
 ) {
+    // Despawns are deferred, so a second removal of the same piece in this
+    // batch would still find it in `owners`.
+    let mut despawned = Vec::new();
+
     for event in events.read() {
         match event {
             GameCommand::PlacePiece {
@@ -154,13 +949,19 @@ fn process_commands(
                 radius,
                 owner,
             } => {
-                commands.spawn((
+                if !turn.players.contains(owner) {
+                    turn.players.push(*owner);
+                }
+                let mut piece = commands.spawn((
                     Position(*position),
                     Velocity(Vec2::ZERO),
                     Mass(1.0),
                     Radius(*radius),
                     Owner(*owner),
                 ));
+                if config.spin_friction.is_some() {
+                    piece.insert((Rotation(0.0), AngularVelocity(0.0)));
+                }
             }
 
             GameCommand::Shoot {
@@ -168,9 +969,55 @@ fn process_commands(
                 direction,
                 force,
             } => {
-                if let Ok(pos) = query.get(*entity) {
+                if query.contains(*entity) {
+                    // Leave the piece be rather than poison its position.
+                    if !force.is_finite() {
+                        continue;
+                    }
                     let dir = direction.normalize_or_zero();
-                    commands.entity(*entity).insert(Velocity(dir * *force));
+                    // `dir` is a unit vector, so capping the force caps the
+                    // speed without squaring anything that could overflow.
+                    let velocity = dir * force.max(-config.max_speed).min(config.max_speed);
+                    commands.entity(*entity).insert(Velocity(velocity));
+                }
+            }
+
+            GameCommand::RemovePiece { entity } => {
+                if despawned.contains(entity) {
+                    continue;
+                }
+                if let Ok(owner) = owners.get(*entity) {
+                    commands.entity(*entity).despawn();
+                    despawned.push(*entity);
+                    removed.write(PieceRemoved {
+                        entity: *entity,
+                        owner: owner.0,
+                    });
+                }
+            }
+
+            GameCommand::SetPiece {
+                entity,
+                position,
+                radius,
+                owner,
+            } => {
+                if despawned.contains(entity) {
+                    continue;
+                }
+                if !turn.players.contains(owner) {
+                    turn.players.push(*owner);
+                }
+                let Ok(mut piece) = commands.get_entity(*entity) else { continue };
+                piece.try_insert((
+                    Position(*position),
+                    Velocity(Vec2::ZERO),
+                    Mass(1.0),
+                    Radius(*radius),
+                    Owner(*owner),
+                ));
+                if config.spin_friction.is_some() {
+                    piece.try_insert_if_new((Rotation(0.0), AngularVelocity(0.0)));
                 }
             }
         }
@@ -183,10 +1030,25 @@ fn process_commands(
 
 fn integrate_motion(
     mut query: Query<(&mut Position, &mut Velocity), Without<Static>>,
+    config: Res<PhysicsConfig>,
 ) {
     for (mut pos, mut vel) in &mut query {
-        pos.0 += vel.0 * FIXED_TIMESTEP;
-        vel.0 *= 0.99; // friction
+        vel.0 += config.gravity * config.timestep;
+        pos.0 += vel.0 * config.timestep;
+        vel.0 = config.friction.apply(vel.0, config.timestep);
+    }
+}
+
+fn integrate_rotation(
+    mut query: Query<(&mut Rotation, &mut AngularVelocity), Without<Static>>,
+    config: Res<PhysicsConfig>,
+) {
+    if config.spin_friction.is_none() {
+        return;
+    }
+    for (mut rot, mut spin) in &mut query {
+        rot.0 = (rot.0 + spin.0 * config.timestep).rem_euclid(std::f32::consts::TAU);
+        spin.0 *= 0.99; // friction
     }
 }
 
@@ -194,70 +1056,452 @@ fn integrate_motion(
 // COLLISION (No Overlap Guaranteed)
 //
 
+type BodyQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static mut Position,
+        &'static mut Velocity,
+        &'static Radius,
+        &'static Mass,
+        Has<Static>,
+        Option<&'static mut AngularVelocity>,
+    ),
+>;
+
 fn resolve_collisions(
-    mut query: Query<(Entity, &mut Position, &mut Velocity, &Radius, &Mass)>,
+    mut query: BodyQuery,
+    mut collisions: MessageWriter<CollisionEvent>,
+    config: Res<PhysicsConfig>,
 ) {
-    let mut combinations = query.iter_combinations_mut();
-
-    while let Some([
-        (e1, mut p1, mut v1, r1, m1),
-        (e2, mut p2, mut v2, r2, m2),
-    ]) = combinations.fetch_next()
-    {
-        let delta = p2.0 - p1.0;
-        let dist = delta.length();
-        let min_dist = r1.0 + r2.0;
-
-        if dist < min_dist && dist > 0.0 {
-            let normal = delta / dist;
-            let penetration = min_dist - dist;
-
-            // Positional correction (no overlap)
-            p1.0 -= normal * (penetration * 0.5);
-            p2.0 += normal * (penetration * 0.5);
-
-            // Elastic impulse
-            let relative_velocity = v2.0 - v1.0;
-            let vel_along_normal = relative_velocity.dot(normal);
-
-            if vel_along_normal < 0.0 {
-                let restitution = 0.9;
-                let impulse_mag = -(1.0 + restitution) * vel_along_normal
-                    / (1.0 / m1.0 + 1.0 / m2.0);
-
-                let impulse = normal * impulse_mag;
-
-                v1.0 -= impulse / m1.0;
-                v2.0 += impulse / m2.0;
-            }
+    // Every contact is worked out from where the pieces stood at the start
+    // of the pass, then applied in ascending entity order rather than
+    // archetype order, so the same scene always produces the same result,
+    // whether or not the contacts were found across threads.
+    let mut bodies: Vec<Body> = query
+        .iter()
+        .map(|(entity, pos, vel, radius, mass, is_static, spin)| Body {
+            entity,
+            pos: pos.0,
+            vel: vel.0,
+            radius: radius.0,
+            // Static bodies have infinite mass: they push but are never pushed.
+            inv_mass: if is_static { 0.0 } else { 1.0 / mass.0 },
+            spin: spin.map(|w| w.0),
+        })
+        .collect();
+    bodies.sort_by_key(|body| body.entity);
+
+    let contacts = if bodies.len() < config.parallel_threshold {
+        (0..bodies.len()).flat_map(|i| contacts_from(&bodies, i, &config)).collect()
+    } else {
+        parallel_contacts(&bodies, &config)
+    };
+
+    let max_speed = config.max_speed;
+    for contact in contacts {
+        let Ok([(_, mut p1, mut v1, .., mut w1), (_, mut p2, mut v2, .., mut w2)]) =
+            query.get_many_mut([contact.a, contact.b])
+        else {
+            continue;
+        };
+        p1.0 += contact.push[0];
+        p2.0 += contact.push[1];
+        let Some((impulse, point)) = contact.hit else { continue };
+
+        v1.0 = (v1.0 + contact.kick[0]).clamp_length_max(max_speed);
+        v2.0 = (v2.0 + contact.kick[1]).clamp_length_max(max_speed);
+        v1.0 = (v1.0 + contact.slide[0]).clamp_length_max(max_speed);
+        v2.0 = (v2.0 + contact.slide[1]).clamp_length_max(max_speed);
+        if let Some(w) = w1.as_mut() {
+            w.0 += contact.spin[0];
+        }
+        if let Some(w) = w2.as_mut() {
+            w.0 += contact.spin[1];
         }
+
+        collisions.write(CollisionEvent { a: contact.a, b: contact.b, impulse, point });
     }
 }
 
+/// A piece as it stood at the start of a collision pass.
+struct Body {
+    entity: Entity,
+    pos: Vec2,
+    vel: Vec2,
+    radius: f32,
+    inv_mass: f32,
+    /// `None` for a piece without [`AngularVelocity`].
+    spin: Option<f32>,
+}
+
+/// What resolving one touching pair does to its two pieces.
+struct Contact {
+    a: Entity,
+    b: Entity,
+    /// Positional correction, so the pair no longer overlaps.
+    push: [Vec2; 2],
+    /// Velocity change from the elastic impulse.
+    kick: [Vec2; 2],
+    /// Velocity change from contact friction, applied after `kick`.
+    slide: [Vec2; 2],
+    /// Spin change from contact friction.
+    spin: [f32; 2],
+    /// Impulse magnitude and contact point; `None` when the pair was
+    /// already separating and only needs pushing apart.
+    hit: Option<(f32, Vec2)>,
+}
+
+/// Contacts between `bodies[i]` and every later body, in entity order.
+fn contacts_from<'a>(
+    bodies: &'a [Body],
+    i: usize,
+    config: &'a PhysicsConfig,
+) -> impl Iterator<Item = Contact> + 'a {
+    bodies[i + 1..].iter().filter_map(move |other| contact(&bodies[i], other, config))
+}
+
+/// Every contact among `bodies` (sorted by entity), worked out across the
+/// compute pool and returned in the same order a serial scan would give.
+fn parallel_contacts(bodies: &[Body], config: &PhysicsConfig) -> Vec<Contact> {
+    let pool = ComputeTaskPool::get_or_init(TaskPool::default);
+    let indices: Vec<usize> = (0..bodies.len()).collect();
+
+    // Several chunks per thread even out the triangular workload: early rows
+    // test more pairs than late ones.
+    let chunk_size = bodies.len().div_ceil(pool.thread_num() * 4).max(1);
+    indices
+        .par_chunk_map(pool, chunk_size, |_, chunk| {
+            chunk.iter().flat_map(|&i| contacts_from(bodies, i, config)).collect::<Vec<_>>()
+        })
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+/// The contact between two bodies, if they overlap.
+fn contact(b1: &Body, b2: &Body, config: &PhysicsConfig) -> Option<Contact> {
+    let (inv1, inv2) = (b1.inv_mass, b2.inv_mass);
+    let inv_total = inv1 + inv2;
+    if inv_total == 0.0 {
+        return None;
+    }
+
+    let delta = b2.pos - b1.pos;
+    let dist = delta.length();
+    let min_dist = b1.radius + b2.radius;
+    if dist >= min_dist || dist <= 0.0 {
+        return None;
+    }
+    let normal = delta / dist;
+    let penetration = min_dist - dist;
+
+    // Positional correction (no overlap), shared by inverse mass
+    let push = [
+        -normal * (penetration * inv1 / inv_total),
+        normal * (penetration * inv2 / inv_total),
+    ];
+    let mut contact = Contact {
+        a: b1.entity,
+        b: b2.entity,
+        push,
+        kick: [Vec2::ZERO; 2],
+        slide: [Vec2::ZERO; 2],
+        spin: [0.0; 2],
+        hit: None,
+    };
+
+    // Elastic impulse
+    let relative_velocity = b2.vel - b1.vel;
+    let vel_along_normal = relative_velocity.dot(normal);
+    if vel_along_normal >= 0.0 {
+        return Some(contact);
+    }
+    let restitution = 0.9;
+    let impulse_mag = -(1.0 + restitution) * vel_along_normal / inv_total;
+    let impulse = normal * impulse_mag;
+    contact.kick = [-impulse * inv1, impulse * inv2];
+    contact.hit = Some((impulse_mag, b1.pos + push[0] + normal * b1.radius));
+
+    // Coulomb friction at the contact point: sliding along the tangent is
+    // traded for spin, up to `mu` times the normal impulse.  A disc's moment
+    // of inertia is m·r²/2.
+    if let Some(mu) = config.spin_friction {
+        let (r1, r2) = (b1.radius, b2.radius);
+        let tangent = normal.perp();
+        let inv_i1 = if b1.spin.is_some() { 2.0 * inv1 / (r1 * r1) } else { 0.0 };
+        let inv_i2 = if b2.spin.is_some() { 2.0 * inv2 / (r2 * r2) } else { 0.0 };
+
+        let slide = relative_velocity.dot(tangent) - b1.spin.unwrap_or(0.0) * r1 - b2.spin.unwrap_or(0.0) * r2;
+        let denom = inv_total + inv_i1 * r1 * r1 + inv_i2 * r2 * r2;
+        let limit = mu * impulse_mag;
+        let friction = (-slide / denom).clamp(-limit, limit);
+
+        contact.slide = [-tangent * friction * inv1, tangent * friction * inv2];
+        contact.spin = [-friction * inv_i1 * r1, -friction * inv_i2 * r2];
+    }
+    Some(contact)
+}
+
+/// Take the weaker piece of every collision harder than
+/// [`PhysicsConfig::destroy_impulse`] off the board: the lighter one, else
+/// the smaller, else the second of the pair.  `Static` pieces never shatter,
+/// and a piece only shatters once however many hits it took.
+fn shatter_pieces(
+    mut commands: Commands,
+    collisions: Res<Messages<CollisionEvent>>,
+    mut cursor: ResMut<ShatterCursor>,
+    mut removed: MessageWriter<PieceRemoved>,
+    config: Res<PhysicsConfig>,
+    pieces: Query<(&Mass, &Radius, Has<Static>)>,
+    owners: Query<&Owner>,
+) {
+    let mut shattered = Vec::new();
+    for hit in cursor.0.read(&collisions) {
+        if hit.impulse <= config.destroy_impulse || shattered.contains(&hit.a) || shattered.contains(&hit.b) {
+            continue;
+        }
+        let (Ok((ma, ra, sa)), Ok((mb, rb, sb))) = (pieces.get(hit.a), pieces.get(hit.b)) else {
+            continue;
+        };
+        let weaker = match (sa, sb) {
+            (true, true) => continue,
+            (true, false) => hit.b,
+            (false, true) => hit.a,
+            _ if (ma.0, ra.0) < (mb.0, rb.0) => hit.a,
+            _ => hit.b,
+        };
+        if let Ok(owner) = owners.get(weaker) {
+            commands.entity(weaker).despawn();
+            shattered.push(weaker);
+            removed.write(PieceRemoved { entity: weaker, owner: owner.0 });
+        }
+    }
+}
+
+//
+// DIAGNOSTICS
+//
+
+fn measure_energy(
+    query: Query<(&Velocity, &Mass), Without<Static>>,
+    mut energy: ResMut<SystemEnergy>,
+) {
+    energy.0 = query
+        .iter()
+        .map(|(vel, mass)| 0.5 * mass.0 * vel.0.length_squared())
+        .sum();
+}
+
 //
 // BOARD REBUILD
 //
 
 fn rebuild_board(
     mut board: ResMut<Board>,
-    query: Query<(Entity, &Position, &Radius)>,
+    query: Query<(Entity, &Position, &Radius, &Owner)>,
 ) {
     board.clear();
 
-    for (entity, pos, radius) in &query {
-        let min_x = (pos.0.x - radius.0) as i32;
-        let max_x = (pos.0.x + radius.0) as i32;
-        let min_y = (pos.0.y - radius.0) as i32;
-        let max_y = (pos.0.y + radius.0) as i32;
+    for (entity, pos, radius, owner) in &query {
+        board.stamp(entity, owner.0, pos.0, radius.0);
+    }
+}
+
+fn update_territory(board: Res<Board>, mut territory: ResMut<Territory>) {
+    territory.0 = board.area_by_owner();
+}
+
+//
+// WIN CONDITION (elimination)
+//
 
-        for y in min_y..=max_y {
-            for x in min_x..=max_x {
-                if (Vec2::new(x as f32, y as f32) - pos.0).length_squared()
-                    <= radius.0 * radius.0
+fn check_win_condition(
+    mut turn: ResMut<TurnState>,
+    mut ended: MessageWriter<GameEnded>,
+    moving: Query<&Velocity, Without<Static>>,
+    owners: Query<&Owner>,
+) {
+    if turn.outcome.is_some() || turn.players.len() < 2 {
+        return;
+    }
+
+    // Only judge the board once everything has come to rest.
+    if moving.iter().any(|v| v.0.length() > REST_SPEED) {
+        return;
+    }
+
+    let survivors: Vec<PlayerId> = turn
+        .players
+        .iter()
+        .copied()
+        .filter(|&p| owners.iter().any(|o| o.0 == p))
+        .collect();
+
+    if survivors.len() < turn.players.len() && survivors.len() <= 1 {
+        let outcome = GameOutcome {
+            winner: survivors.first().copied(),
+        };
+        turn.outcome = Some(outcome);
+        ended.write(GameEnded {
+            winner: outcome.winner,
+        });
+    }
+}
+
+//
+// NETWORK SYNC (server-authoritative rendering)
+//
+
+/// Drives the simulation from a server connection.  The network task parses
+/// each line and sends the `ServerMsg` down the channel held by
+/// [`ServerInbox`]; every newer `STATE` then becomes [`GameCommand`]s that
+/// put the local pieces where the server has them.  Our own moves, sent down
+/// [`SentMoves`], are predicted in the meantime.
+pub struct NetworkSyncPlugin;
+
+impl Plugin for NetworkSyncPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(NetworkPieces::default())
+            .add_systems(Update, apply_server_messages.before(process_commands));
+    }
+}
+
+/// Messages received from the server, in arrival order.
+#[derive(Resource)]
+pub struct ServerInbox(pub Mutex<Receiver<ServerMsg>>);
+
+/// Our own moves as the network task sends them, so they show on the board
+/// before the server's answer arrives.
+#[derive(Resource)]
+pub struct SentMoves(pub Mutex<Receiver<ClientCmd>>);
+
+/// Entity for each server piece index, and how far the server's board has
+/// been applied.
+#[derive(Resource, Default)]
+pub struct NetworkPieces {
+    pub entities: HashMap<usize, Entity>,
+    /// `seq` of the last `STATE` applied; older frames are dropped.
+    pub seq: Option<u64>,
+    /// Our player, once `READY` has said which one we are.
+    pub me: Option<PlayerId>,
+}
+
+impl NetworkPieces {
+    /// Local prediction for one of our own moves, to be sent as a
+    /// `GameCommand` while the server's answer is in flight.
+    pub fn predict(&self, cmd: &ClientCmd, owner: PlayerId) -> Option<GameCommand> {
+        match *cmd {
+            ClientCmd::Place { x, y, radius } => Some(GameCommand::PlacePiece {
+                position: Vec2::new(x, y),
+                radius,
+                owner,
+            }),
+            ClientCmd::Shoot { index, dx, dy, force } => Some(GameCommand::Shoot {
+                entity: *self.entities.get(&index)?,
+                direction: Vec2::new(dx, dy),
+                force,
+            }),
+            ClientCmd::ShootVel { index, vx, vy } => Some(GameCommand::Shoot {
+                entity: *self.entities.get(&index)?,
+                direction: Vec2::new(vx, vy),
+                force: vx.hypot(vy),
+            }),
+            _ => None,
+        }
+    }
+}
+
+fn apply_server_messages(
+    mut commands: Commands,
+    inbox: Option<Res<ServerInbox>>,
+    sent: Option<Res<SentMoves>>,
+    mut synced: ResMut<NetworkPieces>,
+    mut game: MessageWriter<GameCommand>,
+    pieces: Query<Entity, With<Owner>>,
+) {
+    // Only the newest board of a batch matters: it replaces the others.
+    let mut latest: Option<BoardState> = None;
+    if let Some(inbox) = inbox {
+        for msg in inbox.0.lock().unwrap().try_iter() {
+            match msg {
+                ServerMsg::Ready { player_id, .. } => {
+                    synced.me = Some(PlayerId(player_id as u32));
+                    synced.seq = None;
+                }
+                ServerMsg::State(board)
+                    if synced.seq.is_none_or(|seq| board.seq > seq)
+                        && latest.as_ref().is_none_or(|l| board.seq > l.seq) =>
                 {
-                    board.set(x, y, Some(entity));
+                    latest = Some(board);
                 }
+                _ => {}
+            }
+        }
+    }
+    if let Some(board) = latest {
+        reconcile(&mut commands, &mut synced, &mut game, &pieces, &board);
+    }
+
+    // Our own moves show at once; the next `STATE` corrects them.
+    if let (Some(sent), Some(me)) = (sent, synced.me) {
+        for cmd in sent.0.lock().unwrap().try_iter() {
+            if let Some(cmd) = synced.predict(&cmd, me) {
+                game.write(cmd);
+            }
+        }
+    }
+}
+
+/// Make the local pieces match `board`: the server's board replaces
+/// whatever was predicted.
+fn reconcile(
+    commands: &mut Commands,
+    synced: &mut NetworkPieces,
+    game: &mut MessageWriter<GameCommand>,
+    pieces: &Query<Entity, With<Owner>>,
+    board: &BoardState,
+) {
+    synced.seq = Some(board.seq);
+    let known: HashSet<Entity> = synced.entities.values().copied().collect();
+    let mut listed = HashSet::new();
+
+    for piece in &board.pieces {
+        listed.insert(piece.index);
+        // A piece out of sight (`?`) is still on the board; leave ours be.
+        if ![piece.x, piece.y, piece.radius].iter().all(|v| v.is_finite()) {
+            continue;
+        }
+        let entity = match synced.entities.get(&piece.index) {
+            Some(&entity) if pieces.contains(entity) => entity,
+            _ => {
+                let entity = commands.spawn_empty().id();
+                synced.entities.insert(piece.index, entity);
+                entity
             }
+        };
+        game.write(GameCommand::SetPiece {
+            entity,
+            position: Vec2::new(piece.x, piece.y),
+            radius: piece.radius,
+            owner: PlayerId(piece.owner as u32),
+        });
+    }
+
+    // Pieces the server no longer has.
+    synced.entities.retain(|index, entity| {
+        let kept = listed.contains(index);
+        if !kept {
+            game.write(GameCommand::RemovePiece { entity: *entity });
+        }
+        kept
+    });
+
+    // Predicted pieces, which the server's own now stand in for.
+    for entity in pieces {
+        if !known.contains(&entity) {
+            commands.entity(entity).despawn();
         }
     }
-}
\ No newline at end of file
+}
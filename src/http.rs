@@ -0,0 +1,168 @@
+use crate::logger::{Category, Logger};
+use crate::registry::{GameRegistry, GameSnapshot};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time::timeout;
+
+// ── HTTP API ──────────────────────────────────────────────────────────────────
+//
+// A read-only view of the running games for dashboards, enabled with
+// --http-bind.  Just enough HTTP/1.1 for a GET: every response closes the
+// connection, and all but the board image are JSON.
+//
+//   GET /games      — [{ "id", "players", "pieces", "turn" }, …] in id order;
+//                     "players" holds each player's name, or null
+//   GET /games/<id> — one game as above, plus its whole board:
+//                     "board": { "seq", "tick", "pieces": [{ "owner", "x",
+//                     "y", "radius" }, …] }
+//   GET /games/<id>/board.svg
+//                   — the board as an image, for sharing in chat
+//
+// Boards are shown in full, fog of war or not, so keep the address away
+// from players.
+
+/// Longest request head accepted; a GET needs far less.
+const MAX_REQUEST: usize = 8 * 1024;
+
+/// How long a client has to send its request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Answer requests on `listener` from `registry` until the listener fails.
+pub async fn serve_http(listener: TcpListener, registry: Arc<GameRegistry>, log: Arc<Logger>) {
+    match listener.local_addr() {
+        Ok(addr) => log.info(format_args!("HTTP API listening on {addr}")),
+        Err(e)   => log.warn(format_args!("Could not read the HTTP API's bound address: {e}")),
+    }
+    loop {
+        let (stream, addr) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                log.warn(format_args!("HTTP accept error: {e}"));
+                break;
+            }
+        };
+        let registry = Arc::clone(&registry);
+        let log = Arc::clone(&log);
+        tokio::spawn(async move {
+            if let Err(e) = handle(stream, &registry, &log).await {
+                log.of(Category::Http).debug(format_args!("HTTP request from {addr} failed: {e}"));
+            }
+        });
+    }
+}
+
+/// A response body and how to label it.
+enum Body {
+    Json(Value),
+    Svg(String),
+}
+
+fn error(message: &str) -> Body {
+    Body::Json(json!({ "error": message }))
+}
+
+async fn handle(mut stream: TcpStream, registry: &GameRegistry, log: &Logger) -> std::io::Result<()> {
+    let (status, body) = match timeout(REQUEST_TIMEOUT, read_head(&mut stream)).await {
+        Ok(Ok(Some(head))) => {
+            let request_line = head.lines().next().unwrap_or("");
+            log.of(Category::Http).debug(format_args!("HTTP {request_line}"));
+            respond(request_line, registry)
+        }
+        Ok(Ok(None)) => (400, error("bad request")),
+        Ok(Err(e))   => return Err(e),
+        Err(_)       => (408, error("request timeout")),
+    };
+    let (content_type, body) = match body {
+        Body::Json(value) => ("application/json", value.to_string()),
+        Body::Svg(svg)    => ("image/svg+xml", svg),
+    };
+    let head = format!(
+        "HTTP/1.1 {status} {}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n",
+        reason(status),
+        body.len(),
+        if status == 405 { "Allow: GET\r\n" } else { "" },
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(body.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// The request head up to the blank line, or `None` if it is too long, not
+/// UTF-8, or cut short.
+async fn read_head(stream: &mut TcpStream) -> std::io::Result<Option<String>> {
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 || head.len() + n > MAX_REQUEST {
+            return Ok(None);
+        }
+        head.extend_from_slice(&buf[..n]);
+    }
+    Ok(String::from_utf8(head).ok())
+}
+
+/// The status and body for a request line such as `GET /games HTTP/1.1`.
+fn respond(request_line: &str, registry: &GameRegistry) -> (u16, Body) {
+    let mut t = request_line.split_whitespace();
+    let (Some(method), Some(target), Some(_version), None) = (t.next(), t.next(), t.next(), t.next()) else {
+        return (400, error("bad request"));
+    };
+    if method != "GET" {
+        return (405, error("only GET is supported"));
+    }
+    let path = target.split_once('?').map_or(target, |(path, _)| path);
+    let path = path.strip_suffix('/').filter(|p| !p.is_empty()).unwrap_or(path);
+
+    let Some(rest) = path.strip_prefix("/games") else { return (404, error("not found")) };
+    if rest.is_empty() {
+        return (200, Body::Json(registry.list().iter().map(summary).collect()));
+    }
+    let (id, svg) = match rest.strip_suffix("/board.svg") {
+        Some(id) => (id, true),
+        None     => (rest, false),
+    };
+    let Some(id) = id.strip_prefix('/').and_then(|id| id.parse().ok()) else {
+        return (404, error("not found"));
+    };
+    match registry.get(id) {
+        Some(game) if svg => (200, Body::Svg(game.board.to_svg(game.board_size))),
+        Some(game)        => (200, Body::Json(detail(&game))),
+        None              => (404, error("no such game")),
+    }
+}
+
+fn summary(game: &GameSnapshot) -> Value {
+    json!({
+        "id":      game.id,
+        "players": game.players,
+        "pieces":  game.board.pieces.len(),
+        "turn":    game.turn,
+    })
+}
+
+fn detail(game: &GameSnapshot) -> Value {
+    let pieces: Vec<Value> = game
+        .board
+        .pieces
+        .iter()
+        .map(|p| json!({ "owner": p.owner, "x": p.x, "y": p.y, "radius": p.radius }))
+        .collect();
+    let mut out = summary(game);
+    out["board"] = json!({ "seq": game.board.seq, "tick": game.board.tick, "pieces": pieces });
+    out
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        _   => "",
+    }
+}
@@ -1,4 +1,17 @@
+pub mod board;
+pub mod bot;
+pub mod client;
+pub mod compress;
 #[cfg(feature = "game")]
 pub mod game;
+pub mod http;
 pub mod logger;
+pub mod net;
+pub mod protocol;
+pub mod rating;
+pub mod registry;
+pub mod replay;
+pub mod rng;
+pub mod server;
 pub mod session;
+pub mod state;
@@ -1,4 +1,7 @@
+use std::collections::HashMap;
 use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 /// Log verbosity level — ordered from least to most detailed.
 ///
@@ -31,6 +34,57 @@ impl fmt::Display for Level {
     }
 }
 
+impl Level {
+    /// The verbosity (number of `-v` flags) at which this level is written.
+    pub fn verbosity(self) -> u8 {
+        match self {
+            Level::Warn    => 0,
+            Level::Info    => 0,
+            Level::Verbose => 1,
+            Level::Debug   => 2,
+            Level::Trace   => 3,
+        }
+    }
+}
+
+/// What a message is about, so one kind of message can be made more or less
+/// talkative than the rest with [`Logger::with_category`].  Messages logged
+/// without a category follow the logger's own verbosity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, clap::ValueEnum)]
+pub enum Category {
+    /// Players connecting, waiting in the lobby and leaving.
+    Connection,
+    /// Lines sent and received on the wire.
+    Protocol,
+    /// Moves and the boards they lead to.
+    Board,
+    /// Requests to the HTTP API.
+    Http,
+}
+
+/// Parse `<category>=<level>`, e.g. `board=trace`, into the category and the
+/// verbosity that shows messages up to that level.  `warn` and `info` are
+/// always shown, so the quietest a category can be made is `info`.
+pub fn parse_category_level(s: &str) -> Result<(Category, u8), String> {
+    use clap::ValueEnum;
+    let (category, level) = s.split_once('=').ok_or("expected <category>=<level>, e.g. board=trace")?;
+    let category = Category::from_str(category.trim(), true).map_err(|_| {
+        let names: Vec<String> = Category::value_variants()
+            .iter()
+            .filter_map(|c| c.to_possible_value().map(|v| v.get_name().to_string()))
+            .collect();
+        format!("unknown category '{}'; expected one of {}", category.trim(), names.join(", "))
+    })?;
+    let level = match level.trim().to_ascii_lowercase().as_str() {
+        "info"    => Level::Info,
+        "verbose" => Level::Verbose,
+        "debug"   => Level::Debug,
+        "trace"   => Level::Trace,
+        other     => return Err(format!("unknown level '{other}'; expected info, verbose, debug or trace")),
+    };
+    Ok((category, level.verbosity()))
+}
+
 /// Lightweight, verbosity-gated logger.
 ///
 /// Every log method accepts any value that implements [`fmt::Display`],
@@ -41,27 +95,238 @@ impl fmt::Display for Level {
 /// logger.info(ServerEvent::GameStarted { id: 1 });
 /// logger.debug(format_args!("raw bytes: {:?}", buf));
 /// logger.verbose("player connected");
+/// logger.of(Category::Board).trace(state_line);
 /// ```
 pub struct Logger {
-    verbosity: u8,
+    verbosity:  u8,
+    categories: HashMap<Category, u8>,  // verbosity for each category that has its own
+    sink:       Sink,
+    detail:     Option<(Level, Sink)>,  // levels past this one go to this sink
+    dedup:      Option<Mutex<Dedup>>,
+}
+
+/// Repeat tracking for [`Logger::with_dedup`].
+struct Dedup {
+    every:   Duration,
+    last:    Option<(Level, String)>,
+    repeats: u32,
+    since:   Instant,
+}
+
+/// Where emitted lines go.
+pub enum Sink {
+    Stderr,
+    Stdout,
+    /// Kept in memory for a [`CaptureHandle`] to read back.
+    Capture(CaptureHandle),
+}
+
+impl Sink {
+    /// A capture sink and the handle that reads it.
+    pub fn capture() -> (Self, CaptureHandle) {
+        let handle = CaptureHandle { events: Arc::new(Mutex::new(Vec::new())) };
+        (Self::Capture(handle.clone()), handle)
+    }
+}
+
+/// Read side of a [`Sink::Capture`].  Clones share the same buffer.
+#[derive(Clone)]
+pub struct CaptureHandle {
+    events: Arc<Mutex<Vec<(Level, String)>>>,
+}
+
+impl CaptureHandle {
+    /// Everything logged so far, oldest first.
+    pub fn events(&self) -> Vec<(Level, String)> {
+        self.events.lock().unwrap().clone()
+    }
+
+    /// Whether any line at `level` contains `needle`.
+    pub fn contains(&self, level: Level, needle: &str) -> bool {
+        self.events.lock().unwrap().iter().any(|(l, msg)| *l == level && msg.contains(needle))
+    }
 }
 
 impl Logger {
     pub fn new(verbosity: u8) -> Self {
-        Self { verbosity }
+        Self { verbosity, categories: HashMap::new(), sink: Sink::Stderr, detail: None, dedup: None }
+    }
+
+    /// Write messages in `category` at `verbosity` rather than the logger's
+    /// own, e.g. `trace` for moves while connections stay at `info`.
+    pub fn with_category(mut self, category: Category, verbosity: u8) -> Self {
+        self.categories.insert(category, verbosity);
+        self
+    }
+
+    /// A logger that keeps every message, at every level, for the returned
+    /// handle to inspect instead of printing it.  Meant for tests.
+    pub fn capturing() -> (Self, CaptureHandle) {
+        let (sink, handle) = Sink::capture();
+        (Self { sink, ..Self::new(u8::MAX) }, handle)
+    }
+
+    /// Send messages up to and including `through` to `urgent` and every
+    /// more detailed level to `detail`.
+    pub fn split(mut self, through: Level, urgent: Sink, detail: Sink) -> Self {
+        self.sink = urgent;
+        self.detail = Some((through, detail));
+        self
+    }
+
+    /// Warnings on stderr and the rest on stdout, so normal output can be
+    /// piped while problems stay on the terminal.  With `info_to_stderr`,
+    /// `Info` stays on stderr too.
+    pub fn split_streams(self, info_to_stderr: bool) -> Self {
+        let through = if info_to_stderr { Level::Info } else { Level::Warn };
+        self.split(through, Sink::Stderr, Sink::Stdout)
+    }
+
+    /// Collapse runs of identical messages: the first is written, the rest
+    /// are counted and reported as one `… (last message repeated N times)`
+    /// line when a different message arrives, when a repeat comes in more
+    /// than `every` after the last report, or on [`Logger::flush`].
+    pub fn with_dedup(mut self, every: Duration) -> Self {
+        self.dedup = Some(Mutex::new(Dedup { every, last: None, repeats: 0, since: Instant::now() }));
+        self
+    }
+
+    /// Write out any repeat count still held back by dedup.
+    pub fn flush(&self) {
+        if let Some(dedup) = &self.dedup {
+            let mut dedup = dedup.lock().unwrap();
+            self.report_repeats(&mut dedup);
+        }
+    }
+
+    fn report_repeats(&self, dedup: &mut Dedup) {
+        if dedup.repeats > 0
+            && let Some((level, _)) = &dedup.last
+        {
+            let times = if dedup.repeats == 1 { "time" } else { "times" };
+            self.write(*level, &format!("… (last message repeated {} {times})", dedup.repeats));
+        }
+        dedup.repeats = 0;
+        dedup.since = Instant::now();
+    }
+
+    fn write(&self, level: Level, msg: &str) {
+        let sink = match &self.detail {
+            Some((through, detail)) if level > *through => detail,
+            _ => &self.sink,
+        };
+        match sink {
+            Sink::Stderr => eprintln!("[{level}] {msg}"),
+            Sink::Stdout => println!("[{level}] {msg}"),
+            Sink::Capture(handle) => handle.events.lock().unwrap().push((level, msg.to_string())),
+        }
+    }
+
+    /// Whether uncategorised messages at `level` are written at this
+    /// verbosity.
+    pub fn enabled(&self, level: Level) -> bool {
+        self.enabled_in(level, None)
+    }
+
+    /// Whether messages at `level` in `category` are written.
+    pub fn enabled_in(&self, level: Level, category: Option<Category>) -> bool {
+        let verbosity = category.and_then(|c| self.categories.get(&c)).copied().unwrap_or(self.verbosity);
+        verbosity >= level.verbosity()
+    }
+
+    /// Log messages about `category` through the returned view.
+    pub fn of(&self, category: Category) -> InCategory<'_, Self> {
+        InCategory { log: self, category }
+    }
+
+    /// A logger for one part of the program, such as a single game, that
+    /// tags each line with `scope` and a running count.
+    pub fn scoped(self: &Arc<Self>, scope: impl Into<String>) -> ScopedLogger {
+        ScopedLogger { log: Arc::clone(self), scope: scope.into(), seq: Mutex::new(0) }
     }
 
     fn emit(&self, level: Level, msg: &dyn fmt::Display) {
-        let min_v: u8 = match level {
-            Level::Warn    => 0,
-            Level::Info    => 0,
-            Level::Verbose => 1,
-            Level::Debug   => 2,
-            Level::Trace   => 3,
+        self.emit_in(level, None, msg);
+    }
+
+    fn emit_in(&self, level: Level, category: Option<Category>, msg: &dyn fmt::Display) {
+        self.emit_scoped(level, category, None, msg);
+    }
+
+    /// Emit `msg`, numbered within `scope` if given.  The number is only
+    /// taken for a line that is written, and dedup compares lines without
+    /// it, since it differs every time.
+    fn emit_scoped(
+        &self,
+        level: Level,
+        category: Option<Category>,
+        scope: Option<(&str, &mut u64)>,
+        msg: &dyn fmt::Display,
+    ) {
+        if !self.enabled_in(level, category) {
+            return;
+        }
+        let key = match &scope {
+            Some((name, _)) => format!("[{name}] {msg}"),
+            None => msg.to_string(),
+        };
+        let line = |scope: Option<(&str, &mut u64)>| match scope {
+            Some((name, seq)) => {
+                *seq += 1;
+                format!("[{name} #{seq}] {msg}")
+            }
+            None => key.clone(),
         };
-        if self.verbosity >= min_v {
-            eprintln!("[{level}] {msg}");
+        let Some(dedup) = &self.dedup else {
+            self.write(level, &line(scope));
+            return;
+        };
+
+        let mut dedup = dedup.lock().unwrap();
+        if dedup.last.as_ref().is_some_and(|(l, m)| *l == level && *m == key) {
+            dedup.repeats += 1;
+            if dedup.since.elapsed() >= dedup.every {
+                self.report_repeats(&mut dedup);
+            }
+            return;
         }
+        self.report_repeats(&mut dedup);
+        self.write(level, &line(scope));
+        dedup.last = Some((level, key));
+    }
+
+    pub fn warn   (&self, msg: impl fmt::Display) { self.emit(Level::Warn,    &msg); }
+    pub fn info   (&self, msg: impl fmt::Display) { self.emit(Level::Info,    &msg); }
+    pub fn verbose(&self, msg: impl fmt::Display) { self.emit(Level::Verbose, &msg); }
+    pub fn debug  (&self, msg: impl fmt::Display) { self.emit(Level::Debug,   &msg); }
+    pub fn trace  (&self, msg: impl fmt::Display) { self.emit(Level::Trace,   &msg); }
+}
+
+/// Writes through a shared [`Logger`], prefixing each line with
+/// `[<scope> #<k>]`.  `k` counts the lines written in this scope from 1, so
+/// the lines of one game can be put back in order even where they
+/// interleave with other games' lines.  Lines filtered out by verbosity, or
+/// collapsed as repeats by dedup, are not counted.
+pub struct ScopedLogger {
+    log:   Arc<Logger>,
+    scope: String,
+    seq:   Mutex<u64>,
+}
+
+impl ScopedLogger {
+    fn emit(&self, level: Level, msg: &dyn fmt::Display) {
+        self.emit_in(level, None, msg);
+    }
+
+    fn emit_in(&self, level: Level, category: Option<Category>, msg: &dyn fmt::Display) {
+        // Held while writing, so lines go out in the order they are counted.
+        let mut seq = self.seq.lock().unwrap();
+        self.log.emit_scoped(level, category, Some((&self.scope, &mut seq)), msg);
+    }
+
+    /// Log messages about `category` through the returned view.
+    pub fn of(&self, category: Category) -> InCategory<'_, Self> {
+        InCategory { log: self, category }
     }
 
     pub fn warn   (&self, msg: impl fmt::Display) { self.emit(Level::Warn,    &msg); }
@@ -70,3 +335,35 @@ impl Logger {
     pub fn debug  (&self, msg: impl fmt::Display) { self.emit(Level::Debug,   &msg); }
     pub fn trace  (&self, msg: impl fmt::Display) { self.emit(Level::Trace,   &msg); }
 }
+
+/// A logger that messages in one [`Category`] can be written through.
+pub trait Emit {
+    fn emit_in(&self, level: Level, category: Option<Category>, msg: &dyn fmt::Display);
+}
+
+impl Emit for Logger {
+    fn emit_in(&self, level: Level, category: Option<Category>, msg: &dyn fmt::Display) {
+        Logger::emit_in(self, level, category, msg);
+    }
+}
+
+impl Emit for ScopedLogger {
+    fn emit_in(&self, level: Level, category: Option<Category>, msg: &dyn fmt::Display) {
+        ScopedLogger::emit_in(self, level, category, msg);
+    }
+}
+
+/// Logs through `log` as messages about `category`, gated by that
+/// category's verbosity.  Made by [`Logger::of`] and [`ScopedLogger::of`].
+pub struct InCategory<'a, L> {
+    log:      &'a L,
+    category: Category,
+}
+
+impl<L: Emit> InCategory<'_, L> {
+    pub fn warn   (&self, msg: impl fmt::Display) { self.log.emit_in(Level::Warn,    Some(self.category), &msg); }
+    pub fn info   (&self, msg: impl fmt::Display) { self.log.emit_in(Level::Info,    Some(self.category), &msg); }
+    pub fn verbose(&self, msg: impl fmt::Display) { self.log.emit_in(Level::Verbose, Some(self.category), &msg); }
+    pub fn debug  (&self, msg: impl fmt::Display) { self.log.emit_in(Level::Debug,   Some(self.category), &msg); }
+    pub fn trace  (&self, msg: impl fmt::Display) { self.log.emit_in(Level::Trace,   Some(self.category), &msg); }
+}
@@ -1,10 +1,46 @@
-fn main() {
-    println!("Seb n Vic Multiplayer Game ™");
-    println!();
-    println!("Usage:");
-    println!("  Start the server:   cargo run --bin server");
-    println!("  Connect a client:   cargo run --bin client [host:port]");
-    println!();
-    println!("The server listens on port 7878.");
-    println!("Run two clients to start a game. Default host is 127.0.0.1:7878.");
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
+use seb_mul_game::client::{self, ClientArgs};
+use seb_mul_game::server::{self, ServerArgs};
+
+// ── CLI ───────────────────────────────────────────────────────────────────────
+
+#[derive(Parser, Debug)]
+#[command(
+    name    = "tilez",
+    version,
+    about   = "Seb n Vic Multiplayer Game ™",
+    long_about = "Seb n Vic Multiplayer Game ™\n\n\
+                  Start a server with `tilez serve`, then run `tilez connect` twice\n\
+                  to start a game.  The server listens on port 7878 by default."
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Run a dedicated server
+    Serve(ServerArgs),
+    /// Connect to a server and play
+    Connect(ClientArgs),
+}
+
+// ── MAIN ──────────────────────────────────────────────────────────────────────
+
+#[tokio::main]
+async fn main() {
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    match cli.command {
+        Command::Serve(args) => {
+            let serve = matches.subcommand_matches("serve").expect("parsed as serve");
+            let args = args.with_config_file(serve).unwrap_or_else(|e| {
+                eprintln!("{e}");
+                std::process::exit(1);
+            });
+            server::run(args).await
+        }
+        Command::Connect(args) => client::run(args).await,
+    }
 }
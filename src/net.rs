@@ -0,0 +1,152 @@
+use socket2::{Domain, Protocol, Socket, Type};
+use std::collections::HashMap;
+use std::io;
+use std::net::{IpAddr, Ipv6Addr, SocketAddr, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use tokio::net::TcpListener;
+
+/// `clap` value parser for a `<host>:<port>` address.
+///
+/// Hostnames are resolved here, while arguments are parsed, so a typo or an
+/// unknown host is reported straight away instead of as a bind or connect
+/// failure later on.  The first resolved address is used.
+pub fn parse_addr(s: &str) -> Result<SocketAddr, String> {
+    let malformed = || format!("invalid address '{s}': expected <host>:<port>");
+    let (host, port) = s.rsplit_once(':').ok_or_else(malformed)?;
+    if host.is_empty() || port.parse::<u16>().is_err() {
+        return Err(malformed());
+    }
+    resolve(s, s)
+}
+
+/// Resolve the address to bind or connect to from the command line.
+///
+/// A full `addr` wins when given; otherwise `host` (an IP address or a
+/// hostname) and `port` are combined and resolved.
+pub fn compose_addr(addr: Option<SocketAddr>, host: &str, port: u16) -> Result<SocketAddr, String> {
+    match addr {
+        Some(addr) => Ok(addr),
+        None => resolve((host, port), host),
+    }
+}
+
+fn resolve(target: impl ToSocketAddrs, shown: &str) -> Result<SocketAddr, String> {
+    resolve_all(target, shown).map(|addrs| addrs[0])
+}
+
+/// Every address a `<host>:<port>` resolves to, for a client that tries
+/// them in turn.  Checked while arguments are parsed, like [`parse_addr`].
+#[derive(Debug, Clone)]
+pub struct Candidates(pub Vec<SocketAddr>);
+
+/// `clap` value parser for a `<host>:<port>` address to connect to.
+pub fn parse_candidates(s: &str) -> Result<Candidates, String> {
+    parse_addr(s)?;
+    resolve_all(s, s).map(Candidates)
+}
+
+/// The addresses to connect to from the command line, in the order to try
+/// them: a full `addr` wins when given, otherwise `host` and `port` are
+/// combined.  Families alternate, IPv6 first, as happy eyeballs (RFC 8305)
+/// suggests, so one broken family does not hold up the other.
+pub fn compose_candidates(addr: Option<Candidates>, host: &str, port: u16) -> Result<Vec<SocketAddr>, String> {
+    let addrs = match addr {
+        Some(Candidates(addrs)) => addrs,
+        None => resolve_all((host, port), host)?,
+    };
+    let (v6, v4): (Vec<_>, Vec<_>) = addrs.into_iter().partition(SocketAddr::is_ipv6);
+    let (mut v6, mut v4) = (v6.into_iter(), v4.into_iter());
+    let mut ordered = Vec::new();
+    loop {
+        match (v6.next(), v4.next()) {
+            (None, None) => return Ok(ordered),
+            (a, b) => ordered.extend(a.into_iter().chain(b)),
+        }
+    }
+}
+
+fn resolve_all(target: impl ToSocketAddrs, shown: &str) -> Result<Vec<SocketAddr>, String> {
+    let mut addrs: Vec<SocketAddr> = Vec::new();
+    for addr in target.to_socket_addrs().map_err(|e| format!("could not resolve '{shown}': {e}"))? {
+        if !addrs.contains(&addr) {
+            addrs.push(addr);
+        }
+    }
+    if addrs.is_empty() {
+        return Err(format!("'{shown}' did not resolve to any address"));
+    }
+    Ok(addrs)
+}
+
+/// Bind a listening socket.  With `dual_stack` the socket is IPv6 with
+/// `IPV6_V6ONLY` cleared so it also accepts IPv4 clients; an IPv4 wildcard
+/// such as the default `0.0.0.0` is widened to `[::]` on the same port.
+pub fn bind_listener(addr: SocketAddr, dual_stack: bool) -> io::Result<TcpListener> {
+    let addr = match addr {
+        SocketAddr::V4(v4) if dual_stack && v4.ip().is_unspecified() =>
+            SocketAddr::new(Ipv6Addr::UNSPECIFIED.into(), v4.port()),
+        SocketAddr::V4(_) if dual_stack => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "--dual-stack needs an IPv6 or wildcard bind address",
+            ));
+        }
+        _ => addr,
+    };
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+    if addr.is_ipv6() {
+        socket.set_only_v6(!dual_stack)?;
+    }
+    socket.set_reuse_address(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(1024)?;
+    TcpListener::from_std(socket.into())
+}
+
+/// Peer address with IPv4-mapped IPv6 (`[::ffff:a.b.c.d]`) shown as plain IPv4.
+pub fn canonical(addr: SocketAddr) -> SocketAddr {
+    SocketAddr::new(addr.ip().to_canonical(), addr.port())
+}
+
+/// Counts open connections per peer IP and refuses any beyond `max`.
+pub struct ConnLimiter {
+    max:    u32,
+    counts: Mutex<HashMap<IpAddr, u32>>,
+}
+
+impl ConnLimiter {
+    pub fn new(max: u32) -> Arc<Self> {
+        Arc::new(Self { max, counts: Mutex::new(HashMap::new()) })
+    }
+
+    /// Reserve a slot for `ip`, or `None` if it is already at the limit.
+    /// The slot is released when the returned guard is dropped.
+    pub fn try_acquire(self: &Arc<Self>, ip: IpAddr) -> Option<ConnGuard> {
+        let mut counts = self.counts.lock().unwrap();
+        let n = counts.entry(ip).or_insert(0);
+        if *n >= self.max {
+            return None;
+        }
+        *n += 1;
+        Some(ConnGuard { limiter: Arc::clone(self), ip })
+    }
+}
+
+/// One connection's slot in a [`ConnLimiter`].
+pub struct ConnGuard {
+    limiter: Arc<ConnLimiter>,
+    ip:      IpAddr,
+}
+
+impl Drop for ConnGuard {
+    fn drop(&mut self) {
+        let mut counts = self.limiter.counts.lock().unwrap();
+        if let Some(n) = counts.get_mut(&self.ip) {
+            *n -= 1;
+            if *n == 0 {
+                counts.remove(&self.ip);
+            }
+        }
+    }
+}
@@ -0,0 +1,866 @@
+use crate::board::BoardState;
+use std::fmt;
+
+// ── PROTOCOL SPEC ─────────────────────────────────────────────────────────────
+//
+// Line-delimited UTF-8 over TCP.  Both binaries speak it exclusively through
+// the types in this module so the two ends cannot drift apart.
+//
+// Client → Server (one line per message):
+//   HELLO [deflate]        — handshake; answered with OK.  With deflate
+//                            the OK is followed by COMPRESS deflate, unless
+//                            the connection is already compressed
+//   PLACE <x> <y> <radius>
+//   SHOOT <piece_index> <dx> <dy> <force>
+//   SHOOT_VEL <piece_index> <vx> <vy>
+//                          — shoot with a velocity rather than a direction
+//                            and force: moves the piece exactly (vx, vy),
+//                            so its speed must not exceed the max force
+//   READY_ACK              — the client has taken in READY and what came
+//                            with it.  Under --ready-ack-timeout the first
+//                            turn is not announced until every player has
+//                            acked, or the timeout runs out; otherwise it
+//                            is ignored.  Never answered
+//   RESYNC                 — request a fresh STATE (allowed out of turn)
+//   DRAW_OFFER             — propose a draw; does not use up your turn
+//   DRAW_ACCEPT            — accept a pending offer; the game is drawn once
+//                            every other player has accepted
+//   DRAW_DECLINE           — refuse a pending offer
+//                            (the three DRAW_* commands are allowed out of turn)
+//   UNDO                   — ask to take back the last move
+//   UNDO_ACCEPT            — agree to a pending undo; answered OK, and the
+//                            move is taken back once every other player has
+//                            agreed
+//   UNDO_DECLINE           — refuse a pending undo
+//                            (the three UNDO* commands are allowed out of turn)
+//   SKIN <color>           — the colour your pieces are drawn in; one of
+//                            red, orange, yellow, green, teal, blue, purple,
+//                            pink, white or black.  Cosmetic only
+//   NAME <name>            — identify yourself for ratings; 1–16 of [A-Za-z0-9_-]
+//                            ("ERROR name taken" if another player at the
+//                            table has it, in any case)
+//   RATING [<name>]        — query a rating; defaults to your own name
+//   CHAT <text>            — say something to the other players; <text> is
+//                            the rest of the line, 1–200 characters
+//                            (SKIN, NAME, RATING and CHAT are allowed out of
+//                            turn)
+//
+//   Until READY only HELLO, SKIN and NAME are accepted; anything else is refused
+//   with ERROR not started.
+//
+//   Any command may end with a tag, #<tag> (1–16 of [A-Za-z0-9_-]), which
+//   the server echoes on its OK or ERROR reply to that command.
+//
+// Server → Client (one line per message):
+//   COMPRESS deflate       — every byte after this line is one raw deflate
+//                            stream, sync-flushed at the end of each line;
+//                            inflate it and read lines as before.  Only the
+//                            server's side is compressed.  A 200-piece STATE
+//                            is about 4.9 KB as text; compressed, the first
+//                            is about 2.4 KB and each one after a move that
+//                            leaves most pieces in place about 0.1 KB
+//   WAITING                — holding for the rest of the players
+//   READY <player_id> <n> [<team>]
+//                          — game begins; your id is one of 0..n, and <team>
+//                            is given in team games (a bare READY <player_id>
+//                            means n = 2)
+//   YOUR_TURN
+//   OPPONENT_TURN
+//   TURN_DEADLINE <secs>   — follows a turn announcement when turns are
+//                            timed: the seconds the player on turn has left.
+//                            This server does not time turns yet, so it
+//                            never sends one
+//   OK [#<tag>]            — move accepted; the tag only goes to the sender
+//   ERROR [#<tag>] <code> <reason>
+//                          — move rejected; try again.  <code> is one of the
+//                            ErrorCode names below and is what a client
+//                            should branch on; <reason> is for people and
+//                            may change wording
+//   STATE <seq> <tick> <n> [<owner> <x> <y> <r>]×n
+//                          — <seq> increases by one per accepted move;
+//                            <tick> is the simulation step the board shows
+//                            and never goes down, even across an undo
+//                            (under fog of war, hidden values are sent as ?)
+//   DRAW_OFFERED           — opponent proposes a draw
+//   DRAW_DECLINED          — a player refused the pending draw offer
+//   UNDO_REQUESTED         — another player asks to take back the last move;
+//                            if agreed, the restored STATE and the turn
+//                            announcement follow
+//   UNDO_DECLINED          — a player refused the pending undo
+//   OPPONENT_ERROR <reason>
+//                          — the player on turn had a move rejected; only
+//                            sent when the server runs with --broadcast-errors
+//   RULES <board_size> <min_radius> <max_radius> <max_force> <pieces> <turn_secs>
+//                          — sent right after READY: the limits this game
+//                            enforces.  <pieces> is the most each player may
+//                            place and <turn_secs> the time allowed per
+//                            turn; either is - when there is no limit
+//   PIECE_LIMIT <n>        — sent after READY when each player may place at most n
+//   PHASE <phase>          — phased mode only; <phase> is placement or shooting
+//   RATING <name> <elo>    — reply to RATING; <elo> is a whole number
+//   SKIN <player_id> <color>
+//                          — that player's pieces are drawn in <color>; sent
+//                            to everyone for each skin chosen before the game
+//                            and again whenever a player picks a new one
+//   CHAT <player_id> <text>
+//                          — another player's chat line
+//   GAME_OVER <result>     — game finished; <result> is DRAW, WIN <player_id>
+//                            or, in team games, WIN_TEAM <team>
+//   ELIMINATED <player_id> — that player had no legal move and is out; sent
+//                            only while two or more others play on
+//   DISCONNECTED           — a player left; game over
+//
+//   Error codes, with the refusals each covers:
+//     NOT_YOUR_TURN    the move or request is not yours to make now
+//     NOT_STARTED      only HELLO, SKIN and NAME are taken before READY
+//     ELIMINATED       you are out of the game
+//     WRONG_PHASE      a PLACE in the shooting phase or a SHOOT in placement
+//     PIECE_LIMIT      you have placed as many pieces as you may
+//     BAD_NUMBER       a coordinate, direction or force is not finite, or
+//                      the result overflows
+//     BAD_RADIUS       the radius is not positive or outside the RULES
+//     OUT_OF_BOUNDS    the piece would not lie within the board
+//     OVERLAP          the piece would overlap or land on another
+//     BAD_INDEX        there is no piece with that index
+//     NOT_YOUR_PIECE   the piece belongs to someone else
+//     BAD_SHOT         the force, speed or direction is not allowed
+//     NOTHING_PENDING  no draw offer, undo request or move to act on
+//     PENDING          another player's offer or request awaits an answer
+//     ALREADY_DONE     you have already offered, asked or agreed
+//     NAME_TAKEN       another player at the table has that name
+//     NO_NAME          RATING without a name; set one first
+//     BAD_COMMAND      the line is not a command
+//     OTHER            anything else, and any code this end does not know
+//
+//   A game opens with READY, RULES, then PIECE_LIMIT and PHASE if they
+//   apply and a SKIN for each player who chose one in the lobby.  A STATE
+//   follows when players are dealt starting pieces, and then the first
+//   turn announcement.
+//
+//   An accepted move is answered, in this order, with OK to every player,
+//   the new STATE, PHASE if the move changed it and ELIMINATED for anyone
+//   it knocked out.  Then comes either the turn announcement or, when the
+//   move ended the game, GAME_OVER.  Nothing follows GAME_OVER: the server
+//   closes the connection.
+//
+//   Coordinates, radii, directions, velocities and forces are written with
+//   exactly three decimals both ways, in PLACE, SHOOT and SHOOT_VEL as in
+//   STATE.  A value read off the wire writes back out the same, so the
+//   board a client is shown holds exactly what it sent.
+
+// ── WIRE NUMBERS ──────────────────────────────────────────────────────────────
+
+/// Decimal places of every float written to the wire by either end.
+pub const WIRE_DECIMALS: usize = 3;
+
+/// A float as written to the wire: fixed notation, [`WIRE_DECIMALS`] places.
+pub struct WireF32(pub f32);
+
+impl fmt::Display for WireF32 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.*}", WIRE_DECIMALS, self.0)
+    }
+}
+
+// ── CLIENT → SERVER ───────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClientCmd {
+    /// `deflate`: the client can read a compressed stream.
+    Hello  { deflate: bool },
+    Place { x: f32, y: f32, radius: f32 },
+    Shoot { index: usize, dx: f32, dy: f32, force: f32 },
+    /// A shot given as the velocity itself, not normalised.
+    ShootVel { index: usize, vx: f32, vy: f32 },
+    ReadyAck,
+    Resync,
+    DrawOffer,
+    DrawAccept,
+    DrawDecline,
+    Undo,
+    UndoAccept,
+    UndoDecline,
+    Name   (String),
+    Rating (Option<String>),
+    Chat   (String),
+    /// One of [`SKINS`].
+    Skin   (String),
+}
+
+impl ClientCmd {
+    /// Parse a wire line exactly as the server receives it.
+    pub fn parse(line: &str) -> Option<Self> {
+        if let Some(text) = line.strip_prefix("CHAT ") {
+            return Some(Self::Chat(valid_chat(text.trim())?.to_string()));
+        }
+        let mut t = line.split_whitespace();
+        match t.next()? {
+            "PLACE" => Some(Self::Place {
+                x:      t.next()?.parse().ok()?,
+                y:      t.next()?.parse().ok()?,
+                radius: t.next()?.parse().ok()?,
+            }),
+            "SHOOT" => Some(Self::Shoot {
+                index: t.next()?.parse().ok()?,
+                dx:    t.next()?.parse().ok()?,
+                dy:    t.next()?.parse().ok()?,
+                force: t.next()?.parse().ok()?,
+            }),
+            "SHOOT_VEL" => Some(Self::ShootVel {
+                index: t.next()?.parse().ok()?,
+                vx:    t.next()?.parse().ok()?,
+                vy:    t.next()?.parse().ok()?,
+            }),
+            "HELLO"        => Some(Self::Hello { deflate: t.any(|w| w == "deflate") }),
+            "READY_ACK"    => Some(Self::ReadyAck),
+            "RESYNC"       => Some(Self::Resync),
+            "DRAW_OFFER"   => Some(Self::DrawOffer),
+            "DRAW_ACCEPT"  => Some(Self::DrawAccept),
+            "DRAW_DECLINE" => Some(Self::DrawDecline),
+            "UNDO"         => Some(Self::Undo),
+            "UNDO_ACCEPT"  => Some(Self::UndoAccept),
+            "UNDO_DECLINE" => Some(Self::UndoDecline),
+            "NAME"         => Some(Self::Name(valid_name(t.next()?)?.to_string())),
+            "SKIN"         => Some(Self::Skin(valid_skin(t.next()?)?.to_string())),
+            "RATING"       => match t.next() {
+                None       => Some(Self::Rating(None)),
+                Some(name) => Some(Self::Rating(Some(valid_name(name)?.to_string()))),
+            },
+            _ => None,
+        }
+    }
+
+    /// Parse a line typed by the player (case-insensitive keyword).
+    ///
+    /// Stricter than [`ClientCmd::parse`] and explains what is wrong, so the
+    /// client can reject obvious mistakes before they reach the server.
+    pub fn parse_input(raw: &str) -> Result<Self, String> {
+        let mut t = raw.split_whitespace();
+        match t.next().unwrap_or("").to_ascii_uppercase().as_str() {
+            "PLACE" => {
+                let x      = parse_f32(&mut t, "x")?;
+                let y      = parse_f32(&mut t, "y")?;
+                let radius = parse_f32(&mut t, "radius")?;
+                if radius <= 0.0 {
+                    return Err("radius must be > 0".into());
+                }
+                Ok(Self::Place { x, y, radius })
+            }
+            "SHOOT" => {
+                let index = parse_index(&mut t)?;
+                let dx    = parse_f32(&mut t, "dx")?;
+                let dy    = parse_f32(&mut t, "dy")?;
+                let force = parse_f32(&mut t, "force")?;
+                if force <= 0.0 {
+                    return Err("force must be > 0".into());
+                }
+                Ok(Self::Shoot { index, dx, dy, force })
+            }
+            "SHOOT_VEL" => {
+                let index = parse_index(&mut t)?;
+                let vx    = parse_f32(&mut t, "vx")?;
+                let vy    = parse_f32(&mut t, "vy")?;
+                if vx == 0.0 && vy == 0.0 {
+                    return Err("velocity must be non-zero".into());
+                }
+                Ok(Self::ShootVel { index, vx, vy })
+            }
+            "DRAW"    => Ok(Self::DrawOffer),
+            "ACCEPT"  => Ok(Self::DrawAccept),
+            "DECLINE" => Ok(Self::DrawDecline),
+            "UNDO" => match t.next().map(str::to_ascii_uppercase).as_deref() {
+                None            => Ok(Self::Undo),
+                Some("ACCEPT")  => Ok(Self::UndoAccept),
+                Some("DECLINE") => Ok(Self::UndoDecline),
+                Some(_) => Err("use 'undo', 'undo accept' or 'undo decline'".into()),
+            },
+            "NAME" => {
+                let name = t.next().ok_or("missing name")?;
+                let name = valid_name(name).ok_or(NAME_RULE)?;
+                Ok(Self::Name(name.to_string()))
+            }
+            "RATING" => match t.next() {
+                None => Ok(Self::Rating(None)),
+                Some(name) => {
+                    let name = valid_name(name).ok_or(NAME_RULE)?;
+                    Ok(Self::Rating(Some(name.to_string())))
+                }
+            },
+            "" => Err("empty input".into()),
+            kw => Err(format!("unknown command '{kw}'")),
+        }
+    }
+
+    /// Serialise to the wire format expected by the server.
+    pub fn to_wire(&self) -> String {
+        match self {
+            Self::Place { x, y, radius } =>
+                format!("PLACE {} {} {}\n", WireF32(*x), WireF32(*y), WireF32(*radius)),
+            Self::Shoot { index, dx, dy, force } =>
+                format!("SHOOT {index} {} {} {}\n", WireF32(*dx), WireF32(*dy), WireF32(*force)),
+            Self::ShootVel { index, vx, vy } =>
+                format!("SHOOT_VEL {index} {} {}\n", WireF32(*vx), WireF32(*vy)),
+            Self::Hello { deflate: false } =>
+                "HELLO\n".to_string(),
+            Self::Hello { deflate: true } =>
+                "HELLO deflate\n".to_string(),
+            Self::ReadyAck =>
+                "READY_ACK\n".to_string(),
+            Self::Resync =>
+                "RESYNC\n".to_string(),
+            Self::DrawOffer =>
+                "DRAW_OFFER\n".to_string(),
+            Self::DrawAccept =>
+                "DRAW_ACCEPT\n".to_string(),
+            Self::DrawDecline =>
+                "DRAW_DECLINE\n".to_string(),
+            Self::Undo =>
+                "UNDO\n".to_string(),
+            Self::UndoAccept =>
+                "UNDO_ACCEPT\n".to_string(),
+            Self::UndoDecline =>
+                "UNDO_DECLINE\n".to_string(),
+            Self::Name(name) =>
+                format!("NAME {name}\n"),
+            Self::Rating(None) =>
+                "RATING\n".to_string(),
+            Self::Rating(Some(name)) =>
+                format!("RATING {name}\n"),
+            Self::Chat(text) =>
+                format!("CHAT {text}\n"),
+            Self::Skin(skin) =>
+                format!("SKIN {skin}\n"),
+        }
+    }
+
+    /// Like [`ClientCmd::to_wire`] with a trailing `#<tag>` for the server
+    /// to echo back.
+    pub fn to_wire_tagged(&self, tag: &str) -> String {
+        format!("{} #{tag}\n", self.to_wire().trim_end())
+    }
+
+    /// Whether this command is a move that uses up the sender's turn.
+    pub fn is_move(&self) -> bool {
+        matches!(self, Self::Place { .. } | Self::Shoot { .. } | Self::ShootVel { .. })
+    }
+
+    /// How far a shot moves its piece: the force of a `SHOOT`, or the speed
+    /// of a `SHOOT_VEL`.  `None` for anything else.
+    pub fn shot_force(&self) -> Option<f32> {
+        match *self {
+            Self::Shoot { force, .. }      => Some(force),
+            Self::ShootVel { vx, vy, .. } => Some(vx.hypot(vy)),
+            _ => None,
+        }
+    }
+}
+
+/// Split an optional trailing `#<tag>` off a client line, returning the
+/// command part and the tag.
+pub fn split_tag(line: &str) -> (&str, Option<&str>) {
+    let line = line.trim_end();
+    if let Some((cmd, last)) = line.rsplit_once(char::is_whitespace)
+        && let Some(tag) = last.strip_prefix('#')
+        && valid_name(tag).is_some()
+    {
+        return (cmd.trim_end(), Some(tag));
+    }
+    (line, None)
+}
+
+pub const NAME_RULE: &str = "names are 1-16 letters, digits, '_' or '-'";
+
+pub fn valid_name(name: &str) -> Option<&str> {
+    let ok = (1..=16).contains(&name.len())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    ok.then_some(name)
+}
+
+/// The colours a player may pick for their pieces.
+pub const SKINS: [&str; 10] = ["red", "orange", "yellow", "green", "teal", "blue", "purple", "pink", "white", "black"];
+
+pub const SKIN_RULE: &str = "skins are red, orange, yellow, green, teal, blue, purple, pink, white or black";
+
+pub fn valid_skin(skin: &str) -> Option<&str> {
+    SKINS.contains(&skin).then_some(skin)
+}
+
+pub const CHAT_RULE: &str = "chat lines are 1-200 characters with no control characters";
+
+/// `text` if it can be sent as a chat line.  It must already be trimmed, so
+/// that it reads back unchanged.
+pub fn valid_chat(text: &str) -> Option<&str> {
+    let len = text.chars().count();
+    let ok = (1..=200).contains(&len)
+        && text.trim() == text
+        && !text.chars().any(char::is_control);
+    ok.then_some(text)
+}
+
+/// A piece index as typed by the player.  Whether the piece exists is for
+/// the caller to check against the board.
+pub(crate) fn parse_index<'a>(t: &mut impl Iterator<Item = &'a str>) -> Result<usize, String> {
+    let raw = t.next().ok_or("missing piece index")?;
+    raw.parse::<usize>().map_err(|_| {
+        if raw.starts_with('-') && raw[1..].parse::<u64>().is_ok() {
+            "piece index cannot be negative".to_string()
+        } else {
+            "piece index must be a whole number".to_string()
+        }
+    })
+}
+
+pub(crate) fn parse_f32<'a>(
+    t: &mut impl Iterator<Item = &'a str>,
+    name: &str,
+) -> Result<f32, String> {
+    t.next()
+        .ok_or_else(|| format!("missing {name}"))?
+        .parse::<f32>()
+        .map_err(|_| format!("{name} must be a number"))
+}
+
+// ── SERVER → CLIENT ───────────────────────────────────────────────────────────
+
+/// How a finished game was decided.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameResult {
+    Draw,
+    Win(u8),
+    TeamWin(u8),
+}
+
+impl GameResult {
+    fn parse(s: &str) -> Option<Self> {
+        let mut t = s.split_whitespace();
+        match t.next()? {
+            "DRAW" => Some(Self::Draw),
+            "WIN"  => Some(Self::Win(t.next()?.parse().ok()?)),
+            "WIN_TEAM" => Some(Self::TeamWin(t.next()?.parse().ok()?)),
+            _ => None,
+        }
+    }
+
+    fn to_wire(self) -> String {
+        match self {
+            Self::Draw        => "DRAW".to_string(),
+            Self::Win(player) => format!("WIN {player}"),
+            Self::TeamWin(team) => format!("WIN_TEAM {team}"),
+        }
+    }
+}
+
+impl fmt::Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Draw        => write!(f, "draw"),
+            Self::Win(player) => write!(f, "player {player} wins"),
+            Self::TeamWin(team) => write!(f, "team {team} wins"),
+        }
+    }
+}
+
+/// The limits a game enforces, as announced by `RULES`, so a client can
+/// check moves before sending them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RulesInfo {
+    pub board_size:   f32,
+    pub min_radius:   f32,
+    pub max_radius:   f32,
+    pub max_force:    f32,
+    /// Most pieces each player may place; `None` is unlimited.
+    pub piece_limit:  Option<u32>,
+    /// Seconds allowed per turn; `None` when turns are not timed.
+    pub turn_timeout: Option<u32>,
+}
+
+impl RulesInfo {
+    fn parse(s: &str) -> Option<Self> {
+        let mut t = s.split_whitespace();
+        let mut number = || t.next()?.parse::<f32>().ok().filter(|v| v.is_finite());
+        let (board_size, min_radius, max_radius, max_force) = (number()?, number()?, number()?, number()?);
+        let mut limit = || match t.next()? {
+            "-" => Some(None),
+            n   => n.parse().ok().map(Some),
+        };
+        let (piece_limit, turn_timeout) = (limit()?, limit()?);
+        if t.next().is_some() {
+            return None;
+        }
+        Some(Self { board_size, min_radius, max_radius, max_force, piece_limit, turn_timeout })
+    }
+
+    fn to_wire(self) -> String {
+        let limit = |n: Option<u32>| n.map_or("-".to_string(), |n| n.to_string());
+        format!(
+            "{} {} {} {} {} {}",
+            WireF32(self.board_size),
+            WireF32(self.min_radius),
+            WireF32(self.max_radius),
+            WireF32(self.max_force),
+            limit(self.piece_limit),
+            limit(self.turn_timeout),
+        )
+    }
+}
+
+impl fmt::Display for RulesInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Rules: {size}×{size} board, radius {} to {}, force up to {}",
+            self.min_radius,
+            self.max_radius,
+            self.max_force,
+            size = self.board_size,
+        )?;
+        if let Some(n) = self.piece_limit {
+            write!(f, ", at most {n} piece(s) each")?;
+        }
+        if let Some(secs) = self.turn_timeout {
+            write!(f, ", {secs}s per turn")?;
+        }
+        write!(f, ".")
+    }
+}
+
+/// Stage of a game.  Only phased games leave `Open`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// Placing and shooting may be freely interleaved.
+    Open,
+    /// Only `PLACE` is legal.
+    Placement,
+    /// Only `SHOOT` is legal.
+    Shooting,
+}
+
+impl Phase {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "open"      => Some(Self::Open),
+            "placement" => Some(Self::Placement),
+            "shooting"  => Some(Self::Shooting),
+            _ => None,
+        }
+    }
+
+    fn to_wire(self) -> &'static str {
+        match self {
+            Self::Open      => "open",
+            Self::Placement => "placement",
+            Self::Shooting  => "shooting",
+        }
+    }
+}
+
+/// Why a command was refused: the stable part of an `ERROR` line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    NotYourTurn,
+    NotStarted,
+    Eliminated,
+    WrongPhase,
+    PieceLimit,
+    BadNumber,
+    BadRadius,
+    OutOfBounds,
+    Overlap,
+    BadIndex,
+    NotYourPiece,
+    BadShot,
+    NothingPending,
+    Pending,
+    AlreadyDone,
+    NameTaken,
+    NoName,
+    BadCommand,
+    Other,
+}
+
+impl ErrorCode {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "NOT_YOUR_TURN"   => Some(Self::NotYourTurn),
+            "NOT_STARTED"     => Some(Self::NotStarted),
+            "ELIMINATED"      => Some(Self::Eliminated),
+            "WRONG_PHASE"     => Some(Self::WrongPhase),
+            "PIECE_LIMIT"     => Some(Self::PieceLimit),
+            "BAD_NUMBER"      => Some(Self::BadNumber),
+            "BAD_RADIUS"      => Some(Self::BadRadius),
+            "OUT_OF_BOUNDS"   => Some(Self::OutOfBounds),
+            "OVERLAP"         => Some(Self::Overlap),
+            "BAD_INDEX"       => Some(Self::BadIndex),
+            "NOT_YOUR_PIECE"  => Some(Self::NotYourPiece),
+            "BAD_SHOT"        => Some(Self::BadShot),
+            "NOTHING_PENDING" => Some(Self::NothingPending),
+            "PENDING"         => Some(Self::Pending),
+            "ALREADY_DONE"    => Some(Self::AlreadyDone),
+            "NAME_TAKEN"      => Some(Self::NameTaken),
+            "NO_NAME"         => Some(Self::NoName),
+            "BAD_COMMAND"     => Some(Self::BadCommand),
+            "OTHER"           => Some(Self::Other),
+            _ => None,
+        }
+    }
+
+    fn to_wire(self) -> &'static str {
+        match self {
+            Self::NotYourTurn    => "NOT_YOUR_TURN",
+            Self::NotStarted     => "NOT_STARTED",
+            Self::Eliminated     => "ELIMINATED",
+            Self::WrongPhase     => "WRONG_PHASE",
+            Self::PieceLimit     => "PIECE_LIMIT",
+            Self::BadNumber      => "BAD_NUMBER",
+            Self::BadRadius      => "BAD_RADIUS",
+            Self::OutOfBounds    => "OUT_OF_BOUNDS",
+            Self::Overlap        => "OVERLAP",
+            Self::BadIndex       => "BAD_INDEX",
+            Self::NotYourPiece   => "NOT_YOUR_PIECE",
+            Self::BadShot        => "BAD_SHOT",
+            Self::NothingPending => "NOTHING_PENDING",
+            Self::Pending        => "PENDING",
+            Self::AlreadyDone    => "ALREADY_DONE",
+            Self::NameTaken      => "NAME_TAKEN",
+            Self::NoName         => "NO_NAME",
+            Self::BadCommand     => "BAD_COMMAND",
+            Self::Other          => "OTHER",
+        }
+    }
+}
+
+pub enum ServerMsg {
+    Compress,
+    Waiting,
+    Ready      { player_id: u8, players: u8, team: Option<u8> },
+    YourTurn,
+    OpponentTurn,
+    Ok         { tag: Option<String> },
+    Error      { tag: Option<String>, code: ErrorCode, reason: String },
+    State      (BoardState),
+    DrawOffered,
+    DrawDeclined,
+    UndoRequested,
+    UndoDeclined,
+    OpponentError (String),
+    Rules      (RulesInfo),
+    PieceLimit (u32),
+    TurnDeadline (u32),
+    Phase      (Phase),
+    Rating     { name: String, elo: u32 },
+    Chat       { from: u8, text: String },
+    Skin       { player: u8, skin: String },
+    GameOver   (GameResult),
+    Eliminated (u8),
+    Disconnected,
+    Unknown    (String),
+}
+
+impl ServerMsg {
+    pub fn parse(line: &str) -> Self {
+        if line == "COMPRESS deflate" { return Self::Compress; }
+        if line == "WAITING"        { return Self::Waiting; }
+        if line == "YOUR_TURN"      { return Self::YourTurn; }
+        if line == "OPPONENT_TURN"  { return Self::OpponentTurn; }
+        if line == "OK"             { return Self::Ok { tag: None }; }
+        if line == "DRAW_OFFERED"   { return Self::DrawOffered; }
+        if line == "DRAW_DECLINED"  { return Self::DrawDeclined; }
+        if line == "UNDO_REQUESTED" { return Self::UndoRequested; }
+        if line == "UNDO_DECLINED"  { return Self::UndoDeclined; }
+        if line == "DISCONNECTED"   { return Self::Disconnected; }
+
+        if let Some(rest) = line.strip_prefix("READY ") {
+            let mut t = rest.split_whitespace().map(str::parse::<u8>);
+            if let Some(Ok(player_id)) = t.next() {
+                let players = t.next().unwrap_or(Ok(2));
+                let team = t.next().transpose();
+                if let (Ok(players), Ok(team), None) = (players, team, t.next()) {
+                    return Self::Ready { player_id, players, team };
+                }
+            }
+        }
+        if let Some(tag) = line.strip_prefix("OK #")
+            && valid_name(tag).is_some()
+        {
+            return Self::Ok { tag: Some(tag.to_string()) };
+        }
+        if let Some(rest) = line.strip_prefix("ERROR ") {
+            let (tag, body) = match rest.strip_prefix('#').and_then(|t| t.split_once(' ')) {
+                Some((tag, body)) if valid_name(tag).is_some() => (Some(tag.to_string()), body),
+                _ => (None, rest),
+            };
+            // A line without a code we know keeps all of its text.
+            let body = body.trim();
+            let (first, reason) = body.split_once(' ').unwrap_or((body, ""));
+            let (code, reason) = match ErrorCode::parse(first) {
+                Some(code) => (code, reason.trim()),
+                None       => (ErrorCode::Other, body),
+            };
+            return Self::Error { tag, code, reason: reason.to_string() };
+        }
+        if let Some(rest) = line.strip_prefix("OPPONENT_ERROR ") {
+            return Self::OpponentError(rest.trim().to_string());
+        }
+        if let Some(rest) = line.strip_prefix("STATE ")
+            && let Some(board) = BoardState::parse(rest)
+        {
+            return Self::State(board);
+        }
+        if let Some(rest) = line.strip_prefix("RULES ")
+            && let Some(rules) = RulesInfo::parse(rest)
+        {
+            return Self::Rules(rules);
+        }
+        if let Some(rest) = line.strip_prefix("PIECE_LIMIT ")
+            && let Ok(n) = rest.trim().parse::<u32>()
+        {
+            return Self::PieceLimit(n);
+        }
+        if let Some(rest) = line.strip_prefix("TURN_DEADLINE ")
+            && let Ok(secs) = rest.trim().parse::<u32>()
+        {
+            return Self::TurnDeadline(secs);
+        }
+        if let Some(rest) = line.strip_prefix("PHASE ")
+            && let Some(phase) = Phase::parse(rest.trim())
+        {
+            return Self::Phase(phase);
+        }
+        if let Some(rest) = line.strip_prefix("RATING ") {
+            let mut t = rest.split_whitespace();
+            if let (Some(name), Some(elo)) = (t.next(), t.next())
+                && let Ok(elo) = elo.parse::<u32>()
+            {
+                return Self::Rating { name: name.to_string(), elo };
+            }
+        }
+        if let Some(rest) = line.strip_prefix("CHAT ")
+            && let Some((from, text)) = rest.split_once(' ')
+            && let Ok(from) = from.parse::<u8>()
+            && let Some(text) = valid_chat(text.trim())
+        {
+            return Self::Chat { from, text: text.to_string() };
+        }
+        if let Some(rest) = line.strip_prefix("SKIN ") {
+            let mut t = rest.split_whitespace();
+            if let (Some(Ok(player)), Some(skin), None) = (t.next().map(str::parse::<u8>), t.next(), t.next())
+                && let Some(skin) = valid_skin(skin)
+            {
+                return Self::Skin { player, skin: skin.to_string() };
+            }
+        }
+        if let Some(rest) = line.strip_prefix("GAME_OVER ")
+            && let Some(result) = GameResult::parse(rest.trim())
+        {
+            return Self::GameOver(result);
+        }
+        if let Some(rest) = line.strip_prefix("ELIMINATED ")
+            && let Ok(id) = rest.trim().parse::<u8>()
+        {
+            return Self::Eliminated(id);
+        }
+        Self::Unknown(line.to_string())
+    }
+
+    /// Serialise to a newline-terminated line ready to write to a socket.
+    pub fn to_wire(&self) -> String {
+        match self {
+            Self::Compress             => "COMPRESS deflate\n".to_string(),
+            Self::Waiting              => "WAITING\n".to_string(),
+            Self::Ready { player_id, players, team: None } => format!("READY {player_id} {players}\n"),
+            Self::Ready { player_id, players, team: Some(team) } =>
+                format!("READY {player_id} {players} {team}\n"),
+            Self::YourTurn             => "YOUR_TURN\n".to_string(),
+            Self::OpponentTurn         => "OPPONENT_TURN\n".to_string(),
+            Self::Ok { tag: None }     => "OK\n".to_string(),
+            Self::Ok { tag: Some(tag) } => format!("OK #{tag}\n"),
+            Self::Error { tag: None, code, reason } => format!("ERROR {} {reason}\n", code.to_wire()),
+            Self::Error { tag: Some(tag), code, reason } => format!("ERROR #{tag} {} {reason}\n", code.to_wire()),
+            Self::State(board)         => format!("STATE {}\n", board.wire_payload()),
+            Self::DrawOffered          => "DRAW_OFFERED\n".to_string(),
+            Self::DrawDeclined         => "DRAW_DECLINED\n".to_string(),
+            Self::UndoRequested        => "UNDO_REQUESTED\n".to_string(),
+            Self::UndoDeclined         => "UNDO_DECLINED\n".to_string(),
+            Self::OpponentError(reason) => format!("OPPONENT_ERROR {reason}\n"),
+            Self::Rules(rules)         => format!("RULES {}\n", rules.to_wire()),
+            Self::PieceLimit(n)        => format!("PIECE_LIMIT {n}\n"),
+            Self::TurnDeadline(secs)   => format!("TURN_DEADLINE {secs}\n"),
+            Self::Phase(phase)         => format!("PHASE {}\n", phase.to_wire()),
+            Self::Rating { name, elo } => format!("RATING {name} {elo}\n"),
+            Self::Chat { from, text }  => format!("CHAT {from} {text}\n"),
+            Self::Skin { player, skin } => format!("SKIN {player} {skin}\n"),
+            Self::GameOver(result)     => format!("GAME_OVER {}\n", result.to_wire()),
+            Self::Eliminated(player)   => format!("ELIMINATED {player}\n"),
+            Self::Disconnected         => "DISCONNECTED\n".to_string(),
+            Self::Unknown(raw)         => format!("{raw}\n"),
+        }
+    }
+}
+
+/// Each server message knows how to display itself to the player.
+impl fmt::Display for ServerMsg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServerMsg::Compress =>
+                write!(f, "The server is compressing what it sends."),
+            ServerMsg::Waiting =>
+                write!(f, "Waiting for more players to connect…"),
+            ServerMsg::Ready { player_id, players: 2, team: None } =>
+                write!(f, "Game on!  You are Player {player_id}."),
+            ServerMsg::Ready { player_id, players, team: None } =>
+                write!(f, "Game on!  {players} players; you are Player {player_id}."),
+            ServerMsg::Ready { player_id, players, team: Some(team) } =>
+                write!(f, "Game on!  {players} players; you are Player {player_id} on team {team}."),
+            ServerMsg::YourTurn =>
+                write!(f, ""),          // prompt is printed separately
+            ServerMsg::OpponentTurn =>
+                write!(f, "Opponent's turn — waiting…"),
+            ServerMsg::Ok { .. } =>
+                write!(f, "Move accepted."),
+            ServerMsg::Error { reason, .. } =>
+                write!(f, "Rejected: {reason}"),
+            ServerMsg::State(board) =>
+                write!(f, "Board:\n{board}"),
+            ServerMsg::DrawOffered =>
+                write!(f, "Opponent offers a draw — type 'accept' or 'decline'."),
+            ServerMsg::DrawDeclined =>
+                write!(f, "The draw offer was declined."),
+            ServerMsg::UndoRequested =>
+                write!(f, "Opponent asks to undo the last move — type 'undo accept' or 'undo decline'."),
+            ServerMsg::UndoDeclined =>
+                write!(f, "The undo request was declined."),
+            ServerMsg::OpponentError(reason) =>
+                write!(f, "Opponent's move was rejected: {reason}"),
+            ServerMsg::Rules(rules) =>
+                write!(f, "{rules}"),
+            ServerMsg::TurnDeadline(secs) =>
+                write!(f, "{secs}s left to move."),
+            ServerMsg::PieceLimit(n) =>
+                write!(f, "Each player may place at most {n} piece(s)."),
+            ServerMsg::Phase(Phase::Open) =>
+                write!(f, "Place and shoot in any order."),
+            ServerMsg::Phase(Phase::Placement) =>
+                write!(f, "Placement phase — place your pieces; shooting comes later."),
+            ServerMsg::Phase(Phase::Shooting) =>
+                write!(f, "Shooting phase — no more placing; shoot your pieces."),
+            ServerMsg::Rating { name, elo } =>
+                write!(f, "{name} is rated {elo}."),
+            ServerMsg::Chat { from, text } =>
+                write!(f, "P{from}: {text}"),
+            ServerMsg::Skin { player, skin } =>
+                write!(f, "Player {player} plays in {skin}."),
+            ServerMsg::GameOver(GameResult::Draw) =>
+                write!(f, "Game over — it's a draw."),
+            ServerMsg::GameOver(GameResult::Win(player)) =>
+                write!(f, "Game over — Player {player} wins."),
+            ServerMsg::GameOver(GameResult::TeamWin(team)) =>
+                write!(f, "Game over — team {team} wins."),
+            ServerMsg::Eliminated(player) =>
+                write!(f, "Player {player} has no legal move and is out."),
+            ServerMsg::Disconnected =>
+                write!(f, "A player disconnected.  Game over."),
+            ServerMsg::Unknown(raw) =>
+                write!(f, "(unknown message: {raw:?})"),
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Rating every player starts with.
+pub const DEFAULT_RATING: f64 = 1200.0;
+
+/// Expected score of a player rated `ra` against one rated `rb`.
+pub fn expected_score(ra: f64, rb: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf((rb - ra) / 400.0))
+}
+
+/// Standard Elo update after one game.  `score_a` is 1.0 for a win by A,
+/// 0.5 for a draw and 0.0 for a loss; returns the new `(ra, rb)`.
+pub fn elo_update(ra: f64, rb: f64, score_a: f64, k: f64) -> (f64, f64) {
+    let ea = expected_score(ra, rb);
+    let delta = k * (score_a - ea);
+    (ra + delta, rb - delta)
+}
+
+/// Elo ratings for named players, shared by every game on the server.
+pub struct Ratings {
+    k:     f64,
+    table: Mutex<HashMap<String, f64>>,
+}
+
+impl Ratings {
+    pub fn new(k: f64) -> Self {
+        Self { k, table: Mutex::new(HashMap::new()) }
+    }
+
+    /// Current rating for `name`; unknown players have [`DEFAULT_RATING`].
+    pub fn get(&self, name: &str) -> f64 {
+        let table = self.table.lock().unwrap();
+        table.get(name).copied().unwrap_or(DEFAULT_RATING)
+    }
+
+    /// Apply a decisive result and return the new `(winner, loser)` ratings.
+    pub fn record_win(&self, winner: &str, loser: &str) -> (f64, f64) {
+        let mut table = self.table.lock().unwrap();
+        let rw = table.get(winner).copied().unwrap_or(DEFAULT_RATING);
+        let rl = table.get(loser).copied().unwrap_or(DEFAULT_RATING);
+        let (rw, rl) = elo_update(rw, rl, 1.0, self.k);
+        table.insert(winner.to_string(), rw);
+        table.insert(loser.to_string(), rl);
+        (rw, rl)
+    }
+}
@@ -0,0 +1,48 @@
+use crate::board::BoardState;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+/// What a running game looks like from outside it.
+#[derive(Debug, Clone)]
+pub struct GameSnapshot {
+    pub id:         u32,
+    /// Each player's name, by player id, if they gave one.
+    pub players:    Vec<Option<String>>,
+    pub turn:       u8,
+    /// The whole board, with nothing hidden by fog of war.
+    pub board:      BoardState,
+    /// Side length of the square board in world units.
+    pub board_size: f32,
+}
+
+/// The games running on a server, shared by every game task and read by
+/// anything reporting on them.  Each game publishes a fresh snapshot as it
+/// goes and withdraws it when it ends.
+#[derive(Default)]
+pub struct GameRegistry {
+    games: Mutex<BTreeMap<u32, GameSnapshot>>,
+}
+
+impl GameRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the latest state of a game, replacing any earlier snapshot.
+    pub fn publish(&self, snapshot: GameSnapshot) {
+        self.games.lock().unwrap().insert(snapshot.id, snapshot);
+    }
+
+    pub fn remove(&self, id: u32) {
+        self.games.lock().unwrap().remove(&id);
+    }
+
+    pub fn get(&self, id: u32) -> Option<GameSnapshot> {
+        self.games.lock().unwrap().get(&id).cloned()
+    }
+
+    /// Every running game, in id order.
+    pub fn list(&self) -> Vec<GameSnapshot> {
+        self.games.lock().unwrap().values().cloned().collect()
+    }
+}
@@ -0,0 +1,226 @@
+use crate::board::BoardState;
+use crate::protocol::{ClientCmd, WireF32};
+use crate::state::{GameState, Piece, Refusal, Rules};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::fs::File;
+use tokio::io::{AsyncWriteExt, BufWriter};
+
+/// Bumped whenever the record layout changes incompatibly.
+pub const REPLAY_VERSION: u32 = 4;
+
+/// One line of a `.replay` file.
+///
+/// A replay is a header, followed by every *accepted* move (and every agreed
+/// undo) in the order the server applied it, followed by a single `End`
+/// record.  Starting from `GameState::with_rules(seed, rules)` and
+/// re-applying the records reproduces the game exactly.
+///
+/// ```text
+/// {"type":"header","version":4,"game_id":3,"seed":42,"rules":{…},"started_ms":1760000000000}
+/// {"type":"move","player":0,"at_ms":1760000004210,"cmd":{"kind":"place","x":10.0,"y":10.0,"radius":2.0}}
+/// {"type":"end","at_ms":1760000009000,"outcome":{"kind":"disconnected","player":1}}
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ReplayRecord {
+    Header {
+        version: u32,
+        game_id: u32,
+        seed: u64,
+        #[serde(default)]
+        rules: Rules,
+        started_ms: u64,
+    },
+    Move   { player: u8, at_ms: u64, cmd: ReplayCmd },
+    /// The last move was taken back by agreement.
+    Undo   { at_ms: u64 },
+    End    { at_ms: u64, outcome: ReplayOutcome },
+}
+
+/// A move exactly as the server applied it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ReplayCmd {
+    Place { x: f32, y: f32, radius: f32 },
+    Shoot { index: usize, dx: f32, dy: f32, force: f32 },
+}
+
+impl ReplayCmd {
+    /// The record for a wire command, if it is a board-changing move.
+    pub fn from_client(cmd: &ClientCmd) -> Option<Self> {
+        match *cmd {
+            ClientCmd::Place { x, y, radius } => Some(Self::Place { x, y, radius }),
+            ClientCmd::Shoot { index, dx, dy, force } => Some(Self::Shoot { index, dx, dy, force }),
+            // Recorded as the shot the server turns it into.
+            ClientCmd::ShootVel { index, vx, vy } => Some(Self::Shoot { index, dx: vx, dy: vy, force: vx.hypot(vy) }),
+            ClientCmd::Hello { .. }
+            | ClientCmd::ReadyAck
+            | ClientCmd::Resync
+            | ClientCmd::DrawOffer
+            | ClientCmd::DrawAccept
+            | ClientCmd::DrawDecline
+            | ClientCmd::Undo
+            | ClientCmd::UndoAccept
+            | ClientCmd::UndoDecline
+            | ClientCmd::Name(_)
+            | ClientCmd::Rating(_)
+            | ClientCmd::Chat(_)
+            | ClientCmd::Skin(_) => None,
+        }
+    }
+
+    /// Re-apply this move to `state` under the authoritative rules.
+    pub fn apply(&self, state: &mut GameState, player: u8) -> Result<(), Refusal> {
+        match *self {
+            Self::Place { x, y, radius } => state.place(player, x, y, radius),
+            Self::Shoot { index, dx, dy, force } => state.shoot(player, index, dx, dy, force),
+        }
+    }
+}
+
+/// Renders in the client → server wire syntax, e.g. `SHOOT 0 1.000 0.000 5.000`.
+impl fmt::Display for ReplayCmd {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Place { x, y, radius } =>
+                write!(f, "PLACE {} {} {}", WireF32(*x), WireF32(*y), WireF32(*radius)),
+            Self::Shoot { index, dx, dy, force } =>
+                write!(f, "SHOOT {index} {} {} {}", WireF32(*dx), WireF32(*dy), WireF32(*force)),
+        }
+    }
+}
+
+/// How the game finished.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ReplayOutcome {
+    /// The given player dropped their connection.
+    Disconnected { player: u8 },
+    /// Both players agreed to a draw.
+    Draw,
+    /// The given player had no legal move; `winner` is `None` for a draw.
+    Stalemate { player: u8, winner: Option<u8> },
+    /// In a team game, the given player had no legal move and `team` wins.
+    TeamWin { player: u8, team: u8 },
+}
+
+impl ReplayOutcome {
+    /// The winning player, if the game was decided rather than drawn or abandoned.
+    pub fn winner(&self) -> Option<u8> {
+        match *self {
+            Self::Stalemate { winner, .. } => winner,
+            Self::Disconnected { .. } | Self::Draw | Self::TeamWin { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for ReplayOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Disconnected { player } => write!(f, "player {player} disconnected"),
+            Self::Draw => write!(f, "draw by agreement"),
+            Self::Stalemate { player, winner: Some(w) } =>
+                write!(f, "player {player} had no legal move — player {w} wins"),
+            Self::Stalemate { player, winner: None } =>
+                write!(f, "player {player} had no legal move — draw"),
+            Self::TeamWin { player, team } =>
+                write!(f, "player {player} had no legal move — team {team} wins"),
+        }
+    }
+}
+
+/// Re-run `records` from a fresh `GameState::with_rules(seed, rules)` and
+/// check they leave the same board as `live`: the same pieces, turn, phase
+/// and eliminated players.  On a mismatch, says what differs first.
+/// `Header` and `End` records are skipped.
+pub fn verify(seed: u64, rules: &Rules, records: &[ReplayRecord], live: &GameState) -> Result<(), String> {
+    let mut state = GameState::with_rules(seed, rules.clone());
+    for (n, record) in records.iter().enumerate() {
+        let applied = match record {
+            ReplayRecord::Move { player, cmd, .. } => cmd.apply(&mut state, *player),
+            ReplayRecord::Undo { .. } => state.undo(),
+            ReplayRecord::Header { .. } | ReplayRecord::End { .. } => Ok(()),
+        };
+        applied.map_err(|reason| format!("record {} was refused on replay: {reason}", n + 1))?;
+    }
+
+    let (replayed, pieces) = (state.pieces(), live.pieces());
+    if replayed.len() != pieces.len() {
+        return Err(format!("{} piece(s) replayed, {} on the live board", replayed.len(), pieces.len()));
+    }
+    let show = |p: &Piece| format!("P{} at ({}, {}) radius {}", p.owner, p.x, p.y, p.radius);
+    if let Some((i, (a, b))) = replayed.iter().zip(pieces).enumerate().find(|(_, (a, b))| a != b) {
+        return Err(format!("piece #{i} is {} replayed but {} live", show(a), show(b)));
+    }
+    if state.turn() != live.turn() {
+        return Err(format!("P{} on turn replayed but P{} live", state.turn(), live.turn()));
+    }
+    if state.phase() != live.phase() {
+        return Err(format!("{:?} phase replayed but {:?} live", state.phase(), live.phase()));
+    }
+    if state.eliminated() != live.eliminated() {
+        return Err(format!("{:?} out replayed but {:?} live", state.eliminated(), live.eliminated()));
+    }
+    Ok(())
+}
+
+/// The board in one line of a server log, if it is a `STATE` trace line
+/// (written at `-vvv`, or `--log-level board=trace`), with the id of the
+/// game it belongs to:
+/// `[TRCE] [game <id> #<n>] STATE <payload>`.
+pub fn logged_state(line: &str) -> Option<(u64, BoardState)> {
+    let rest = line.trim_end().strip_prefix("[TRCE] [game ")?;
+    let (game, rest) = rest.split_once(" #")?;
+    let (_, payload) = rest.split_once("] STATE ")?;
+    Some((game.parse().ok()?, BoardState::parse(payload)?))
+}
+
+/// Milliseconds since the Unix epoch, used to timestamp records.
+pub fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Appends newline-delimited JSON records to `<dir>/<game_id>.replay`.
+///
+/// Records are buffered; call [`ReplayWriter::finish`] when the game ends so
+/// the tail of the file is flushed to disk.
+pub struct ReplayWriter {
+    out: BufWriter<File>,
+}
+
+impl ReplayWriter {
+    /// Create (or truncate) the replay file for `game_id` and write its header.
+    pub async fn create(dir: &Path, game_id: u32, seed: u64, rules: &Rules) -> std::io::Result<Self> {
+        tokio::fs::create_dir_all(dir).await?;
+        let file = File::create(dir.join(format!("{game_id}.replay"))).await?;
+        let mut writer = Self { out: BufWriter::new(file) };
+        writer
+            .record(&ReplayRecord::Header {
+                version: REPLAY_VERSION,
+                game_id,
+                seed,
+                rules: rules.clone(),
+                started_ms: now_ms(),
+            })
+            .await?;
+        Ok(writer)
+    }
+
+    pub async fn record(&mut self, rec: &ReplayRecord) -> std::io::Result<()> {
+        let mut line = serde_json::to_string(rec)?;
+        line.push('\n');
+        self.out.write_all(line.as_bytes()).await
+    }
+
+    /// Write the `End` record and flush everything to disk.
+    pub async fn finish(mut self, outcome: ReplayOutcome) -> std::io::Result<()> {
+        self.record(&ReplayRecord::End { at_ms: now_ms(), outcome }).await?;
+        self.out.flush().await
+    }
+}
@@ -0,0 +1,39 @@
+/// Small deterministic PRNG (SplitMix64).
+///
+/// Game randomness must be reproducible from a replay's seed on any machine
+/// and any future build, so the algorithm is spelled out here rather than
+/// borrowed from a crate whose output may change between versions.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `0..n`; `n` must be non-zero.
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+
+    /// Uniform in `[0, 1)`.
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+/// Per-game seed: the server's base seed offset by the game id, so every
+/// game differs but any one of them can be reproduced from the pair.
+pub fn game_seed(base: u64, game_id: u32) -> u64 {
+    Rng::new(base ^ u64::from(game_id)).next_u64()
+}
@@ -0,0 +1,1499 @@
+use crate::board::BoardState;
+use crate::compress::LineDeflater;
+use crate::http::serve_http;
+use crate::logger::{parse_category_level, Category, Logger, ScopedLogger};
+use crate::net::{bind_listener, canonical, compose_addr, parse_addr, ConnGuard, ConnLimiter};
+use crate::protocol::{split_tag, ClientCmd, ErrorCode, GameResult, Phase, ServerMsg};
+use crate::rating::Ratings;
+use crate::registry::{GameRegistry, GameSnapshot};
+use crate::replay::{self, now_ms, ReplayCmd, ReplayOutcome, ReplayRecord, ReplayWriter};
+use crate::rng::game_seed;
+use crate::state::{FogConfig, GameState, PhaseMode, Refusal, Rules, StalemateRule};
+use clap::parser::ValueSource;
+use clap::{ArgAction, ArgMatches, Parser};
+use serde::Deserialize;
+use socket2::SockRef;
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::ErrorKind;
+use std::mem::MaybeUninit;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::task::Poll;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::{mpsc, OwnedSemaphorePermit, Semaphore};
+use tokio::task::JoinHandle;
+use tokio::time::{timeout, timeout_at, Instant};
+
+// ── CLI ───────────────────────────────────────────────────────────────────────
+
+/// Command-line options for the dedicated server.
+#[derive(Parser, Debug)]
+#[command(
+    name    = "server",
+    version,
+    about   = "Seb n Vic Multiplayer Game — dedicated server",
+    long_about = "Accepts groups of TCP clients (pairs by default) and runs authoritative\n\
+                  game sessions.\n\
+                  Protocol is line-delimited UTF-8; see src/protocol.rs for the full spec."
+)]
+pub struct ServerArgs {
+    /// Read settings from a TOML file; options given here override it
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Full address to listen on; overrides --host and --port
+    #[arg(short, long, value_name = "ADDR", value_parser = parse_addr)]
+    bind: Option<SocketAddr>,
+
+    /// IP address or hostname to listen on
+    #[arg(long, default_value = "0.0.0.0")]
+    host: String,
+
+    /// Port to listen on
+    #[arg(short, long, default_value_t = 7878)]
+    port: u16,
+
+    /// Serve IPv4 and IPv6 clients from one IPv6 socket
+    #[arg(long)]
+    dual_stack: bool,
+
+    /// Serve a read-only JSON view of the running games on this address
+    /// (default: off).  Boards are shown in full, fog of war or not
+    #[arg(long, value_name = "ADDR", value_parser = parse_addr)]
+    http_bind: Option<SocketAddr>,
+
+    /// Most simultaneous connections from one IP address (default: no limit)
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    max_conns_per_ip: Option<u32>,
+
+    /// Increase output verbosity (-v verbose, -vv debug, -vvv trace)
+    #[arg(short, long, action = ArgAction::Count)]
+    verbose: u8,
+
+    /// Log one category of message at its own level, e.g. board=trace or
+    /// connection=info; repeat for more.  Categories: connection, protocol,
+    /// board, http
+    #[arg(long, value_name = "CATEGORY=LEVEL", value_parser = parse_category_level)]
+    log_level: Vec<(Category, u8)>,
+
+    /// Collapse identical consecutive log lines into a repeat count
+    #[arg(long)]
+    dedup_logs: bool,
+
+    /// Log warnings to stderr and everything else to stdout
+    #[arg(long)]
+    split_logs: bool,
+
+    /// With --split-logs, keep info messages on stderr as well
+    #[arg(long, requires = "split_logs")]
+    info_to_stderr: bool,
+
+    /// Maximum number of games that can run concurrently
+    #[arg(short = 'g', long, default_value_t = 16)]
+    max_games: u32,
+
+    /// Record every game as newline-delimited JSON in <DIR>/<game_id>.replay
+    #[arg(long, value_name = "DIR")]
+    replay_dir: Option<PathBuf>,
+
+    /// When a game ends, replay its moves on a fresh board and warn if the
+    /// result differs from the live game
+    #[arg(long)]
+    verify_replay: bool,
+
+    /// Base RNG seed; each game's seed is derived from it and the game id
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+
+    /// Side length of the square board in world units
+    #[arg(long, default_value_t = 500.0, value_parser = parse_positive)]
+    board_size: f32,
+
+    /// Largest radius a piece may be placed with; no smaller than the
+    /// minimum radius of 1
+    #[arg(long, default_value_t = 50.0, value_parser = parse_max_radius)]
+    max_radius: f32,
+
+    /// Largest force a single shot may use
+    #[arg(long, default_value_t = 500.0, value_parser = parse_positive)]
+    max_force: f32,
+
+    /// Reject shots that move their piece less than this distance
+    /// (default: any shot is allowed)
+    #[arg(long, value_name = "DIST")]
+    min_shoot_distance: Option<f32>,
+
+    /// Outcome for a player left with no legal move
+    #[arg(long, value_enum, default_value_t = StalemateRule::Loss)]
+    stalemate: StalemateRule,
+
+    /// Whether placing and shooting are interleaved or done in two phases
+    #[arg(long, value_enum, default_value_t = PhaseMode::Open)]
+    phase_mode: PhaseMode,
+
+    /// Pieces each player places before shooting starts (phased mode)
+    #[arg(long, default_value_t = 3, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pieces_per_player: u32,
+
+    /// Cap on the pieces each player may place over a game (default: no cap)
+    #[arg(long, value_name = "N")]
+    max_pieces_per_player: Option<u32>,
+
+    /// Players per game
+    #[arg(long, default_value_t = 2, value_name = "N", value_parser = clap::value_parser!(u8).range(2..))]
+    players: u8,
+
+    /// Split the players into N teams; player p plays for team p % N
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u8).range(2..))]
+    teams: Option<u8>,
+
+    /// Pieces dealt to each player, in a ring around the centre, before the
+    /// first turn
+    #[arg(long, default_value_t = 0, value_name = "K")]
+    starting_pieces: u32,
+
+    /// Fog of war: only reveal enemy pieces within this distance of your own
+    #[arg(long, value_name = "DIST")]
+    fog_sight: Option<f32>,
+
+    /// Fog of war: never reveal the radius of enemy pieces
+    #[arg(long)]
+    fog_hide_radius: bool,
+
+    /// Tell the other players, without the move itself, when a move is rejected
+    #[arg(long)]
+    broadcast_errors: bool,
+
+    /// Lines queued for a player who is not reading before they are dropped
+    #[arg(long, default_value_t = 256, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    send_queue: u32,
+
+    /// Elo K-factor: the most a rating can move after one game
+    #[arg(long, default_value_t = 32.0, value_name = "K")]
+    elo_k: f64,
+
+    /// Seconds a finished game waits for its last lines to reach players
+    #[arg(long, default_value_t = 5, value_name = "SECS")]
+    flush_timeout: u64,
+
+    /// Hold the first turn until every player has sent READY_ACK, for at
+    /// most this many seconds (default: start at once)
+    #[arg(long, value_name = "SECS")]
+    ready_ack_timeout: Option<u64>,
+}
+
+impl ServerArgs {
+    /// The game settings these options describe; the address and logging
+    /// options are not part of them.
+    pub fn config(&self) -> ServerConfig {
+        ServerConfig {
+            max_games:         self.max_games,
+            max_conns_per_ip:  self.max_conns_per_ip,
+            seed:              self.seed,
+            rules: Rules {
+                board_size:            self.board_size,
+                max_radius:            self.max_radius,
+                max_force:             self.max_force,
+                min_shoot_distance:    self.min_shoot_distance,
+                stalemate:             self.stalemate,
+                phase_mode:            self.phase_mode,
+                pieces_per_player:     self.pieces_per_player,
+                max_pieces_per_player: self.max_pieces_per_player,
+                players:               self.players,
+                teams:                 self.teams,
+                starting_pieces:       self.starting_pieces,
+                ..Rules::default()
+            },
+            replay_dir:        self.replay_dir.clone(),
+            verify_replay:     self.verify_replay,
+            fog:               FogConfig { sight: self.fog_sight, hide_radius: self.fog_hide_radius },
+            broadcast_errors:  self.broadcast_errors,
+            send_queue:        self.send_queue as usize,
+            flush_timeout:     Duration::from_secs(self.flush_timeout),
+            ready_ack_timeout: self.ready_ack_timeout.map(Duration::from_secs),
+            elo_k:             self.elo_k,
+        }
+    }
+
+    /// The logger the logging options ask for.
+    pub fn logger(&self) -> Logger {
+        let mut log = Logger::new(self.verbose);
+        for &(category, verbosity) in &self.log_level {
+            log = log.with_category(category, verbosity);
+        }
+        if self.split_logs {
+            log = log.split_streams(self.info_to_stderr);
+        }
+        if self.dedup_logs {
+            log = log.with_dedup(Duration::from_secs(10));
+        }
+        log
+    }
+
+    /// Fill in every option that `matches` did not take from the command line
+    /// with its value in the `--config` file, if one was given.  `matches`
+    /// must be the ones these arguments were parsed from.
+    pub fn with_config_file(mut self, matches: &ArgMatches) -> Result<Self, String> {
+        let Some(path) = self.config.clone() else { return Ok(self) };
+        let shown = path.display();
+        let text = std::fs::read_to_string(&path).map_err(|e| format!("cannot read {shown}: {e}"))?;
+        let file = ConfigFile::parse(&text).map_err(|e| format!("{shown}: {e}"))?;
+
+        let on_cli = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+        macro_rules! fill {
+            ($($field:ident),* $(,)?) => {$(
+                if let Some(value) = file.$field && !on_cli(stringify!($field)) {
+                    self.$field = value.into();
+                }
+            )*};
+        }
+        fill!(
+            host, port, dual_stack, max_conns_per_ip, verbose, dedup_logs, split_logs, info_to_stderr,
+            max_games, replay_dir, verify_replay, seed, board_size, max_radius, max_force, min_shoot_distance, stalemate,
+            phase_mode, pieces_per_player, max_pieces_per_player, players, teams, starting_pieces, fog_sight,
+            fog_hide_radius,
+            broadcast_errors, send_queue, elo_k, flush_timeout, ready_ack_timeout,
+        );
+        if let Some(bind) = &file.bind && !on_cli("bind") {
+            self.bind = Some(parse_addr(bind).map_err(|e| format!("{shown}: bind: {e}"))?);
+        }
+        if let Some(bind) = &file.http_bind && !on_cli("http_bind") {
+            self.http_bind = Some(parse_addr(bind).map_err(|e| format!("{shown}: http_bind: {e}"))?);
+        }
+        if let Some(levels) = &file.log_level && !on_cli("log_level") {
+            self.log_level = levels
+                .iter()
+                .map(|l| parse_category_level(l).map_err(|e| format!("{shown}: log_level: {e}")))
+                .collect::<Result<_, _>>()?;
+        }
+
+        // The command line enforces these through its value parsers; values
+        // from the file have to be checked here.
+        let positive = |v: f32| v.is_finite() && v > 0.0;
+        let bad = if !positive(self.board_size) {
+            Some("board_size must be a positive number")
+        } else if !positive(self.max_radius) {
+            Some("max_radius must be a positive number")
+        } else if self.max_radius < Rules::default().min_radius {
+            Some("max_radius is below the smallest radius a piece may have")
+        } else if !positive(self.max_force) {
+            Some("max_force must be a positive number")
+        } else if self.pieces_per_player < 1 {
+            Some("pieces_per_player must be at least 1")
+        } else if self.players < 2 {
+            Some("players must be at least 2")
+        } else if self.teams.is_some_and(|t| t < 2) {
+            Some("teams must be at least 2")
+        } else if self.send_queue < 1 {
+            Some("send_queue must be at least 1")
+        } else if self.max_conns_per_ip == Some(0) {
+            Some("max_conns_per_ip must be at least 1")
+        } else if self.info_to_stderr && !self.split_logs {
+            Some("info_to_stderr needs split_logs")
+        } else {
+            None
+        };
+        match bad {
+            Some(msg) => Err(format!("{shown}: {msg}")),
+            None      => Ok(self),
+        }
+    }
+}
+
+/// A finite number above zero, for the board size and the move limits.
+fn parse_positive(s: &str) -> Result<f32, String> {
+    match s.parse::<f32>() {
+        Ok(v) if v.is_finite() && v > 0.0 => Ok(v),
+        _ => Err("must be a positive number".into()),
+    }
+}
+
+/// A radius cap no smaller than the fixed minimum radius, so that some piece
+/// can still be placed.
+fn parse_max_radius(s: &str) -> Result<f32, String> {
+    let min = Rules::default().min_radius;
+    match parse_positive(s)? {
+        v if v >= min => Ok(v),
+        _ => Err(format!("must be at least the minimum radius, {min}")),
+    }
+}
+
+/// The `--config` file.  Keys are the long option names with `_` in place of
+/// `-`; anything left out keeps its command-line default.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    bind:                  Option<String>,
+    host:                  Option<String>,
+    port:                  Option<u16>,
+    dual_stack:            Option<bool>,
+    http_bind:             Option<String>,
+    max_conns_per_ip:      Option<u32>,
+    verbose:               Option<u8>,
+    log_level:             Option<Vec<String>>,
+    dedup_logs:            Option<bool>,
+    split_logs:            Option<bool>,
+    info_to_stderr:        Option<bool>,
+    max_games:             Option<u32>,
+    replay_dir:            Option<PathBuf>,
+    verify_replay:         Option<bool>,
+    seed:                  Option<u64>,
+    board_size:            Option<f32>,
+    max_radius:            Option<f32>,
+    max_force:             Option<f32>,
+    min_shoot_distance:    Option<f32>,
+    stalemate:             Option<StalemateRule>,
+    phase_mode:            Option<PhaseMode>,
+    pieces_per_player:     Option<u32>,
+    max_pieces_per_player: Option<u32>,
+    players:               Option<u8>,
+    teams:                 Option<u8>,
+    starting_pieces:       Option<u32>,
+    fog_sight:             Option<f32>,
+    fog_hide_radius:       Option<bool>,
+    broadcast_errors:      Option<bool>,
+    send_queue:            Option<u32>,
+    elo_k:                 Option<f64>,
+    flush_timeout:         Option<u64>,
+    ready_ack_timeout:     Option<u64>,
+}
+
+impl ConfigFile {
+    fn parse(text: &str) -> Result<Self, String> {
+        let doc = toml_edit::Document::parse(text).map_err(|e| e.to_string())?;
+        let table = toml_to_json(doc.as_item());
+        serde_json::from_value(table.clone()).map_err(|e| {
+            // serde_json does not say which key a type error came from, so
+            // find it by trying the keys one at a time.
+            let serde_json::Value::Object(keys) = table else { return e.to_string() };
+            if e.to_string().starts_with("unknown field") {
+                return e.to_string();
+            }
+            keys.into_iter()
+                .find_map(|(key, value)| {
+                    let one = serde_json::Value::Object([(key.clone(), value)].into_iter().collect());
+                    serde_json::from_value::<Self>(one).err().map(|e| format!("{key}: {e}"))
+                })
+                .unwrap_or_else(|| e.to_string())
+        })
+    }
+}
+
+/// The same data as JSON, which serde can deserialize from.  Dates and times
+/// become their TOML text.
+fn toml_to_json(item: &toml_edit::Item) -> serde_json::Value {
+    use serde_json::Value as Json;
+    use toml_edit::{Item, Value};
+
+    fn value(v: &Value) -> Json {
+        match v {
+            Value::String(s)      => Json::from(s.value().as_str()),
+            Value::Integer(i)     => Json::from(*i.value()),
+            Value::Float(f)       => Json::from(*f.value()),
+            Value::Boolean(b)     => Json::from(*b.value()),
+            Value::Datetime(d)    => Json::from(d.value().to_string()),
+            Value::Array(a)       => a.iter().map(value).collect(),
+            Value::InlineTable(t) => Json::Object(t.iter().map(|(k, v)| (k.to_string(), value(v))).collect()),
+        }
+    }
+
+    match item {
+        Item::None             => Json::Null,
+        Item::Value(v)         => value(v),
+        Item::Table(t)         => Json::Object(t.iter().map(|(k, v)| (k.to_string(), toml_to_json(v))).collect()),
+        Item::ArrayOfTables(a) => a.iter().map(|t| toml_to_json(&Item::Table(t.clone()))).collect(),
+    }
+}
+
+// ── CONFIG ────────────────────────────────────────────────────────────────────
+
+/// Everything [`serve`] needs besides a listener and a logger.  The defaults
+/// match the command-line defaults.
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    /// Games run at once; further players wait to be accepted.
+    pub max_games: u32,
+    /// Simultaneous connections allowed from one IP; `None` is unlimited.
+    pub max_conns_per_ip: Option<u32>,
+    /// Base RNG seed; each game's seed is derived from it and the game id.
+    pub seed: u64,
+    /// Rules for every game, including how many players sit at a table.
+    pub rules: Rules,
+    /// Record each game to `<dir>/<game_id>.replay`.
+    pub replay_dir: Option<PathBuf>,
+    /// Check each finished game by replaying its moves from scratch.
+    pub verify_replay: bool,
+    /// What each player is shown of enemy pieces.
+    pub fog: FogConfig,
+    /// Tell the other players when a move is rejected.
+    pub broadcast_errors: bool,
+    /// Lines queued for a player who is not reading before they are dropped.
+    pub send_queue: usize,
+    /// How long a finished game waits for its last lines to reach players.
+    pub flush_timeout: Duration,
+    /// How long the first turn waits for every player's READY_ACK; `None`
+    /// does not wait.
+    pub ready_ack_timeout: Option<Duration>,
+    /// Elo K-factor.
+    pub elo_k: f64,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            max_games:         16,
+            max_conns_per_ip:  None,
+            seed:              0,
+            rules:             Rules::default(),
+            replay_dir:        None,
+            verify_replay:     false,
+            fog:               FogConfig::default(),
+            broadcast_errors:  false,
+            send_queue:        256,
+            flush_timeout:     Duration::from_secs(5),
+            ready_ack_timeout: None,
+            elo_k:             32.0,
+        }
+    }
+}
+
+// ── DISPLAY EVENTS ────────────────────────────────────────────────────────────
+//
+// Every loggable occurrence is an `Event` variant.  Implementing `Display`
+// here means the logger receives a rich, human-readable message while still
+// using Rust's zero-cost formatting machinery (no allocation until a variant
+// is actually emitted at the current verbosity level).
+//
+// Events about one game are logged through that game's `ScopedLogger`, which
+// tags them `[game <id> #<k>]`, so the variants leave the game id out.
+
+enum Event {
+    Listening      { addr: SocketAddr },
+    WaitingForPlayers { players: u8 },
+    PlayerConnected { n: u8, addr: SocketAddr },
+    GameStarted    { seed: u64 },
+    GameEnded,
+    GameOver       { result: GameResult },
+    DrawOffered    { player: u8 },
+    UndoRequested  { player: u8 },
+    MoveUndone     { seq: u64 },
+    PlayerNamed    { player: u8, name: String },
+    PlayerSkin     { player: u8, skin: String },
+    RatingsUpdated { winner: String, rw: f64, loser: String, rl: f64 },
+    PlayerMsg      { player: u8, msg: String },
+    PlayerDisconnected { player: u8 },
+    SendQueueFull  { player: u8 },
+    ReadyAcksMissing { players: usize },
+    WriteFailed    { player: u8, reason: String },
+    LeftLobby      { addr: SocketAddr },
+    PlayerEliminated { player: u8 },
+    InvalidCmd     { player: u8, raw: String },
+    AcceptError    { reason: String },
+    ConnRefused    { addr: SocketAddr },
+    ReplayError    { reason: String },
+    ReplayDiverged { reason: String },
+    InvariantBroken { reason: String },
+    SlotsFull,
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Event::Listening { addr } =>
+                write!(f, "Server listening on {addr}"),
+            Event::WaitingForPlayers { players } =>
+                write!(f, "Waiting for {players} players to connect"),
+            Event::PlayerConnected { n, addr } =>
+                write!(f, "Player {n} connected from {addr}"),
+            Event::GameStarted { seed } =>
+                write!(f, "Game started (seed {seed})"),
+            Event::GameEnded =>
+                write!(f, "Game ended"),
+            Event::GameOver { result } =>
+                write!(f, "Game over: {result}"),
+            Event::DrawOffered { player } =>
+                write!(f, "P{player} offered a draw"),
+            Event::UndoRequested { player } =>
+                write!(f, "P{player} asked to undo the last move"),
+            Event::MoveUndone { seq } =>
+                write!(f, "Last move undone (now at seq {seq})"),
+            Event::PlayerNamed { player, name } =>
+                write!(f, "P{player} is now known as {name}"),
+            Event::PlayerSkin { player, skin } =>
+                write!(f, "P{player} now plays in {skin}"),
+            Event::RatingsUpdated { winner, rw, loser, rl } =>
+                write!(f, "Ratings: {winner} → {rw:.0}, {loser} → {rl:.0}"),
+            Event::PlayerMsg { player, msg } =>
+                write!(f, "P{player} → {msg}"),
+            Event::PlayerDisconnected { player } =>
+                write!(f, "Player {player} disconnected"),
+            Event::SendQueueFull { player } =>
+                write!(f, "Player {player} is not keeping up; disconnecting"),
+            Event::ReadyAcksMissing { players } =>
+                write!(f, "{players} player(s) sent no READY_ACK in time; starting anyway"),
+            Event::WriteFailed { player, reason } =>
+                write!(f, "Write to player {player} failed: {reason}"),
+            Event::LeftLobby { addr } =>
+                write!(f, "{addr} left before the game started"),
+            Event::PlayerEliminated { player } =>
+                write!(f, "P{player} has no legal move and is out"),
+            Event::InvalidCmd { player, raw } =>
+                write!(f, "P{player} sent unrecognised command: {raw:?}"),
+            Event::AcceptError { reason } =>
+                write!(f, "Accept error: {reason}"),
+            Event::ConnRefused { addr } =>
+                write!(f, "Refused {addr}: too many connections from this address"),
+            Event::ReplayError { reason } =>
+                write!(f, "Replay recording failed: {reason}"),
+            Event::ReplayDiverged { reason } =>
+                write!(f, "Replaying the moves does not reproduce the game: {reason}"),
+            Event::InvariantBroken { reason } =>
+                write!(f, "Game state invariant broken: {reason}"),
+            Event::SlotsFull =>
+                write!(f, "Max concurrent games reached — new connections will queue"),
+        }
+    }
+}
+
+// ── PER-GAME SESSION ──────────────────────────────────────────────────────────
+
+/// The sending half of a player's connection.  Once a `COMPRESS deflate`
+/// line goes out, everything after it is deflated.
+struct LineWriter {
+    inner:   OwnedWriteHalf,
+    deflate: Option<LineDeflater>,
+}
+
+impl LineWriter {
+    fn new(inner: OwnedWriteHalf) -> Self {
+        Self { inner, deflate: None }
+    }
+
+    fn compressing(&self) -> bool {
+        self.deflate.is_some()
+    }
+
+    async fn write_line(&mut self, line: &str) -> std::io::Result<()> {
+        match &mut self.deflate {
+            Some(z) => self.inner.write_all(&z.compress(line.as_bytes())).await,
+            None => {
+                self.inner.write_all(line.as_bytes()).await?;
+                if line == ServerMsg::Compress.to_wire() {
+                    self.deflate = Some(LineDeflater::new());
+                }
+                Ok(())
+            }
+        }
+    }
+
+    async fn send(&mut self, msg: &ServerMsg) -> std::io::Result<()> {
+        self.write_line(&msg.to_wire()).await
+    }
+}
+
+/// Everything `run_game` needs to know about the game besides its sockets.
+struct GameConfig {
+    game_id:           u32,
+    seed:              u64,
+    rules:             Rules,
+    replay_dir:        Option<PathBuf>,
+    verify_replay:     bool,
+    ratings:           Arc<Ratings>,
+    fog:               FogConfig,
+    broadcast_errors:  bool,
+    send_queue:        usize,
+    flush_timeout:     Duration,
+    ready_ack_timeout: Option<Duration>,
+    /// Where to publish the game for the HTTP API; `None` when it is off.
+    registry:          Option<Arc<GameRegistry>>,
+}
+
+type PlayerLines = Lines<BufReader<OwnedReadHalf>>;
+
+/// A connected player, from acceptance until their game ends.
+struct Seat {
+    lines:  PlayerLines,
+    writer: LineWriter,
+    addr:   SocketAddr,
+    name:   Option<String>,
+    skin:   Option<String>,
+    /// Their place in the lobby pool, given up once they are dealt into a
+    /// game.
+    place:  Option<OwnedSemaphorePermit>,
+    _guard: ConnGuard,
+}
+
+impl Seat {
+    fn new(stream: TcpStream, addr: SocketAddr, guard: ConnGuard, place: OwnedSemaphorePermit) -> Self {
+        let (reader, writer) = stream.into_split();
+        Self {
+            lines: BufReader::new(reader).lines(),
+            writer: LineWriter::new(writer),
+            addr,
+            name: None,
+            skin: None,
+            place: Some(place),
+            _guard: guard,
+        }
+    }
+
+    /// Whether the player has closed their end already.  Asks the socket
+    /// itself, since the runtime may not have heard of a hang-up that only
+    /// just arrived.
+    fn hung_up(&mut self) -> bool {
+        let stream: &TcpStream = self.lines.get_mut().get_ref().as_ref();
+        let mut byte = [MaybeUninit::uninit()];
+        match SockRef::from(stream).peek(&mut byte) {
+            Ok(n)  => n == 0,
+            Err(e) => e.kind() != ErrorKind::WouldBlock,
+        }
+    }
+}
+
+/// The next line, or `None` for a hang-up, from any seat in `seats`.  Never
+/// resolves while `seats` is empty.  Cancellation-safe: a partly read line
+/// stays buffered in its seat.
+async fn next_lobby_line(seats: &mut [Seat]) -> (usize, Option<String>) {
+    std::future::poll_fn(|cx| {
+        for (i, seat) in seats.iter_mut().enumerate() {
+            if let Poll::Ready(res) = Pin::new(&mut seat.lines).poll_next_line(cx) {
+                return Poll::Ready((i, res.ok().flatten()));
+            }
+        }
+        Poll::Pending
+    })
+    .await
+}
+
+/// Whether `name` is already used by a player other than `me` in `names`.
+/// Names are compared without regard to case, so a scoreboard never shows
+/// two of the same.
+fn name_taken<'a>(name: &str, me: usize, names: impl IntoIterator<Item = &'a Option<String>>) -> bool {
+    let name = name.to_lowercase();
+    names
+        .into_iter()
+        .enumerate()
+        .any(|(i, other)| i != me && other.as_ref().is_some_and(|other| other.to_lowercase() == name))
+}
+
+/// Answer a line from `pool[player]`, still waiting for their game.  Only
+/// the handshake is accepted before `READY`; anything else is refused
+/// rather than held back for the game.
+async fn handle_lobby_line(
+    pool: &mut [Seat],
+    player: usize,
+    line: &str,
+    log: &ScopedLogger,
+) -> std::io::Result<()> {
+    let (body, tag) = split_tag(line.trim());
+    let tag = tag.map(str::to_string);
+    let cmd = ClientCmd::parse(body);
+    let taken = matches!(&cmd, Some(ClientCmd::Name(name)) if name_taken(name, player, pool.iter().map(|s| &s.name)));
+    let seat = &mut pool[player];
+    let player = player as u8;
+    log.of(Category::Protocol).verbose(Event::PlayerMsg { player, msg: line.trim().to_string() });
+    let mut compress = false;
+    let reply = match cmd {
+        Some(ClientCmd::Hello { deflate }) => {
+            compress = deflate && !seat.writer.compressing();
+            ServerMsg::Ok { tag }
+        }
+        Some(ClientCmd::Name(_)) if taken => {
+            ServerMsg::Error { tag, code: ErrorCode::NameTaken, reason: "name taken".into() }
+        }
+        Some(ClientCmd::Name(name)) => {
+            log.verbose(Event::PlayerNamed { player, name: name.clone() });
+            seat.name = Some(name);
+            ServerMsg::Ok { tag }
+        }
+        Some(ClientCmd::Skin(skin)) => {
+            log.verbose(Event::PlayerSkin { player, skin: skin.clone() });
+            seat.skin = Some(skin);
+            ServerMsg::Ok { tag }
+        }
+        _ => ServerMsg::Error { tag, code: ErrorCode::NotStarted, reason: "not started".into() },
+    };
+    seat.writer.send(&reply).await?;
+    if compress {
+        seat.writer.send(&ServerMsg::Compress).await?;
+    }
+    Ok(())
+}
+
+/// Forward each line one player sends to the game loop, followed by `None`
+/// once their connection closes.
+async fn forward_lines(player: u8, mut lines: PlayerLines, tx: mpsc::Sender<(u8, Option<String>)>) {
+    loop {
+        let line = lines.next_line().await.ok().flatten();
+        let closed = line.is_none();
+        if tx.send((player, line)).await.is_err() || closed {
+            break;
+        }
+    }
+}
+
+/// One player's outgoing lines.  A task of its own does the writing, so a
+/// player who stops reading fills their queue instead of stalling the game.
+/// A failed write is reported to the game loop as that player hanging up.
+struct Outbox {
+    tx:          mpsc::Sender<String>,
+    task:        JoinHandle<()>,
+    overflowed:  bool,
+    compressing: bool,
+}
+
+impl Outbox {
+    fn new(
+        mut writer: LineWriter,
+        capacity: usize,
+        player: u8,
+        hangup: mpsc::Sender<(u8, Option<String>)>,
+        log: Arc<ScopedLogger>,
+    ) -> Self {
+        let compressing = writer.compressing();
+        let (tx, mut rx) = mpsc::channel::<String>(capacity);
+        let task = tokio::spawn(async move {
+            while let Some(line) = rx.recv().await {
+                if let Err(e) = writer.write_line(&line).await {
+                    log.warn(Event::WriteFailed { player, reason: e.to_string() });
+                    let _ = hangup.send((player, None)).await;
+                    break;
+                }
+            }
+        });
+        Self { tx, task, overflowed: false, compressing }
+    }
+
+    /// Queue a newline-terminated line.  On overflow the writer is stopped,
+    /// which closes the connection, and everything after is dropped.
+    fn push(&mut self, line: String) {
+        if self.overflowed {
+            return;
+        }
+        if let Err(TrySendError::Full(_)) = self.tx.try_send(line) {
+            self.overflowed = true;
+            self.task.abort();
+        }
+    }
+
+    fn send(&mut self, msg: &ServerMsg) {
+        self.push(msg.to_wire());
+    }
+
+    /// Switch to a compressed stream, unless it already is one.
+    fn start_compressing(&mut self) {
+        if !self.compressing {
+            self.compressing = true;
+            self.send(&ServerMsg::Compress);
+        }
+    }
+
+    fn overflowed(&self) -> bool {
+        self.overflowed
+    }
+
+    /// Let the queue drain, giving up on a player who is not reading.
+    async fn close(self, wait: Duration) {
+        let Self { tx, mut task, .. } = self;
+        drop(tx);
+        if timeout(wait, &mut task).await.is_err() {
+            task.abort();
+        }
+    }
+}
+
+fn broadcast(writers: &mut [Outbox], msg: &ServerMsg) {
+    for w in writers {
+        w.send(msg);
+    }
+}
+
+/// Send `msg` to every player except `player`.
+fn send_others(writers: &mut [Outbox], player: u8, msg: &ServerMsg) {
+    for (i, w) in writers.iter_mut().enumerate() {
+        if i != player as usize {
+            w.send(msg);
+        }
+    }
+}
+
+/// Wait up to `wait` for a READY_ACK from each of `n` players.  Every line
+/// that arrives meanwhile, the acks included, is kept in `held` for the game
+/// loop; a disconnect ends the wait early.
+async fn await_ready_acks(
+    rx: &mut mpsc::Receiver<(u8, Option<String>)>,
+    n: usize,
+    wait: Duration,
+    held: &mut VecDeque<(u8, Option<String>)>,
+    log: &ScopedLogger,
+) {
+    let deadline = Instant::now() + wait;
+    let mut acked = vec![false; n];
+    while acked.contains(&false) {
+        match timeout_at(deadline, rx.recv()).await {
+            Ok(Some((player, Some(line)))) => {
+                if ClientCmd::parse(split_tag(line.trim()).0) == Some(ClientCmd::ReadyAck) {
+                    acked[player as usize] = true;
+                }
+                held.push_back((player, Some(line)));
+            }
+            Ok(Some(closed)) => {
+                held.push_back(closed);
+                return;
+            }
+            Ok(None) => return,
+            Err(_) => {
+                let players = acked.iter().filter(|&&a| !a).count();
+                log.warn(Event::ReadyAcksMissing { players });
+                return;
+            }
+        }
+    }
+}
+
+/// Tell the player on turn it is theirs and everyone else to wait.
+fn announce_turn(writers: &mut [Outbox], turn: u8) {
+    for (i, w) in writers.iter_mut().enumerate() {
+        let msg = if i == turn as usize { ServerMsg::YourTurn } else { ServerMsg::OpponentTurn };
+        w.send(&msg);
+    }
+}
+
+async fn run_game(seats: Vec<Seat>, cfg: GameConfig, log: Arc<ScopedLogger>) {
+    let GameConfig {
+        game_id,
+        seed,
+        rules,
+        replay_dir,
+        verify_replay,
+        ratings,
+        fog,
+        broadcast_errors,
+        send_queue,
+        flush_timeout,
+        ready_ack_timeout,
+        registry,
+    } = cfg;
+    let n = seats.len();
+
+    // One reader task per player funnels every line into a single channel,
+    // so the loop below waits on all of them at once.  Writer tasks use the
+    // same channel to report a dead connection.  Per-IP slots are held
+    // until the game ends.
+    let (tx, mut rx) = mpsc::channel(n * 4);
+    let mut writers = Vec::with_capacity(n);
+    let mut readers = Vec::with_capacity(n);
+    let mut names   = Vec::with_capacity(n);
+    let mut skins   = Vec::with_capacity(n);
+    let mut _guards = Vec::with_capacity(n);
+    for (i, seat) in seats.into_iter().enumerate() {
+        log.of(Category::Connection).info(Event::PlayerConnected { n: i as u8 + 1, addr: seat.addr });
+        writers.push(Outbox::new(seat.writer, send_queue, i as u8, tx.clone(), Arc::clone(&log)));
+        names.push(seat.name);
+        skins.push(seat.skin);
+        _guards.push(seat._guard);
+        readers.push(tokio::spawn(forward_lines(i as u8, seat.lines, tx.clone())));
+    }
+    drop(tx);
+    log.info(Event::GameStarted { seed });
+
+    let mut replay = match replay_dir {
+        Some(dir) => match ReplayWriter::create(&dir, game_id, seed, &rules).await {
+            Ok(w) => Some(w),
+            Err(e) => {
+                log.warn(Event::ReplayError { reason: e.to_string() });
+                None
+            }
+        },
+        None => None,
+    };
+
+    // Every move and undo, kept for --verify-replay.
+    let mut records: Option<Vec<ReplayRecord>> = verify_replay.then(Vec::new);
+
+    let mut state = GameState::with_rules(seed, rules);
+
+    // Announce game start and the seeded initial turn order.
+    for (i, w) in writers.iter_mut().enumerate() {
+        let player_id = i as u8;
+        let team = state.rules().teams.map(|_| state.team_of(player_id));
+        w.send(&ServerMsg::Ready { player_id, players: n as u8, team });
+    }
+    broadcast(&mut writers, &ServerMsg::Rules(state.rules().into()));
+    if let Some(limit) = state.rules().max_pieces_per_player {
+        broadcast(&mut writers, &ServerMsg::PieceLimit(limit));
+    }
+    if state.phase() != Phase::Open {
+        broadcast(&mut writers, &ServerMsg::Phase(state.phase()));
+    }
+    for (player, skin) in skins.iter().enumerate() {
+        if let Some(skin) = skin {
+            broadcast(&mut writers, &ServerMsg::Skin { player: player as u8, skin: skin.clone() });
+        }
+    }
+    if !state.pieces().is_empty() {
+        for (i, w) in writers.iter_mut().enumerate() {
+            w.push(state.state_line_for(i as u8, fog));
+        }
+    }
+    // Lines read while waiting for acks, for the loop below to handle first.
+    let mut held = VecDeque::new();
+    if let Some(wait) = ready_ack_timeout {
+        await_ready_acks(&mut rx, n, wait, &mut held, &log).await;
+    }
+    announce_turn(&mut writers, state.turn());
+
+    // Sequence number of the last snapshot published.
+    let mut published = None;
+    let outcome = loop {
+        // Only a move changes what the snapshot shows, so the board is not
+        // copied for every line read.
+        if let Some(registry) = &registry
+            && published != Some(state.seq())
+        {
+            registry.publish(GameSnapshot {
+                id:         game_id,
+                players:    names.clone(),
+                turn:       state.turn(),
+                board:      BoardState::from(&state),
+                board_size: state.rules().board_size,
+            });
+            published = Some(state.seq());
+        }
+
+        // A player who has fallen a whole queue behind is cut off like a
+        // disconnect rather than left to hold up everyone else.
+        if let Some(player) = writers.iter().position(Outbox::overflowed) {
+            let player = player as u8;
+            log.warn(Event::SendQueueFull { player });
+            send_others(&mut writers, player, &ServerMsg::Disconnected);
+            break ReplayOutcome::Disconnected { player };
+        }
+
+        let next = match held.pop_front() {
+            Some(line) => Some(line),
+            None => rx.recv().await,
+        };
+        let (player, line) = match next {
+            Some((player, Some(line))) => (player, line),
+            closed => {
+                // Each reader reports its own disconnect before it stops.
+                let player = closed.map_or(0, |(player, _)| player);
+                log.of(Category::Connection).info(Event::PlayerDisconnected { player });
+                send_others(&mut writers, player, &ServerMsg::Disconnected);
+                break ReplayOutcome::Disconnected { player };
+            }
+        };
+
+        let trimmed = line.trim().to_string();
+        log.of(Category::Protocol).verbose(Event::PlayerMsg { player, msg: trimmed.clone() });
+
+        // Replies to the sender echo the command's tag, if it had one.
+        let (body, tag) = split_tag(&trimmed);
+        let ok = ServerMsg::Ok { tag: tag.map(str::to_string) };
+        let error = |code: ErrorCode, reason: &str| ServerMsg::Error {
+            tag:    tag.map(str::to_string),
+            code,
+            reason: reason.to_string(),
+        };
+        let refused = |refusal: Refusal| error(refusal.code(), refusal.reason());
+        let cmd = ClientCmd::parse(body);
+
+        // Requests that do not depend on whose turn it is.
+        let me = player as usize;
+        match &cmd {
+            Some(ClientCmd::Hello { deflate }) => {
+                writers[me].send(&ok);
+                if *deflate {
+                    writers[me].start_compressing();
+                }
+                continue;
+            }
+            // Only counted before the first turn; a late ack is dropped.
+            Some(ClientCmd::ReadyAck) => continue,
+            Some(ClientCmd::Resync) => {
+                log.of(Category::Protocol).debug(format!("P{player} RESYNC at seq {}", state.seq()));
+                writers[me].push(state.state_line_for(player, fog));
+                continue;
+            }
+            Some(ClientCmd::Name(name)) => {
+                if name_taken(name, me, &names) {
+                    writers[me].send(&error(ErrorCode::NameTaken, "name taken"));
+                    continue;
+                }
+                log.verbose(Event::PlayerNamed { player, name: name.clone() });
+                names[me] = Some(name.clone());
+                writers[me].send(&ok);
+                continue;
+            }
+            Some(ClientCmd::Skin(skin)) => {
+                log.verbose(Event::PlayerSkin { player, skin: skin.clone() });
+                skins[me] = Some(skin.clone());
+                writers[me].send(&ok);
+                broadcast(&mut writers, &ServerMsg::Skin { player, skin: skin.clone() });
+                continue;
+            }
+            Some(ClientCmd::Rating(name)) => {
+                let msg = match name.as_ref().or(names[me].as_ref()) {
+                    Some(name) => ServerMsg::Rating {
+                        name: name.clone(),
+                        elo:  ratings.get(name).round() as u32,
+                    },
+                    None => error(ErrorCode::NoName, "set a name first"),
+                };
+                writers[me].send(&msg);
+                continue;
+            }
+            Some(ClientCmd::Chat(text)) => {
+                send_others(&mut writers, player, &ServerMsg::Chat { from: player, text: text.clone() });
+                writers[me].send(&ok);
+                continue;
+            }
+            Some(ClientCmd::DrawOffer) => {
+                match state.offer_draw(player) {
+                    Ok(()) => {
+                        log.verbose(Event::DrawOffered { player });
+                        send_others(&mut writers, player, &ServerMsg::DrawOffered);
+                    }
+                    Err(reason) => writers[me].send(&refused(reason)),
+                }
+                continue;
+            }
+            Some(ClientCmd::DrawDecline) => {
+                match state.decline_draw(player) {
+                    Ok(()) => send_others(&mut writers, player, &ServerMsg::DrawDeclined),
+                    Err(reason) => writers[me].send(&refused(reason)),
+                }
+                continue;
+            }
+            Some(ClientCmd::DrawAccept) => {
+                match state.accept_draw(player) {
+                    Ok(true) => {
+                        let result = GameResult::Draw;
+                        log.info(Event::GameOver { result });
+                        broadcast(&mut writers, &ServerMsg::GameOver(result));
+                        break ReplayOutcome::Draw;
+                    }
+                    // Still waiting on other players to accept.
+                    Ok(false) => writers[me].send(&ok),
+                    Err(reason) => writers[me].send(&refused(reason)),
+                }
+                continue;
+            }
+            Some(ClientCmd::Undo) => {
+                match state.request_undo(player) {
+                    Ok(()) => {
+                        log.verbose(Event::UndoRequested { player });
+                        send_others(&mut writers, player, &ServerMsg::UndoRequested);
+                    }
+                    Err(reason) => writers[me].send(&refused(reason)),
+                }
+                continue;
+            }
+            Some(ClientCmd::UndoDecline) => {
+                match state.decline_undo(player) {
+                    Ok(()) => send_others(&mut writers, player, &ServerMsg::UndoDeclined),
+                    Err(reason) => writers[me].send(&refused(reason)),
+                }
+                continue;
+            }
+            Some(ClientCmd::UndoAccept) => {
+                let phase_before = state.phase();
+                match state.accept_undo(player) {
+                    Ok(true) => {
+                        log.verbose(Event::MoveUndone { seq: state.seq() });
+                        writers[me].send(&ok);
+                        let rec = ReplayRecord::Undo { at_ms: now_ms() };
+                        if let Some(w) = replay.as_mut()
+                            && let Err(e) = w.record(&rec).await
+                        {
+                            log.warn(Event::ReplayError { reason: e.to_string() });
+                            replay = None;
+                        }
+                        if let Some(records) = records.as_mut() {
+                            records.push(rec);
+                        }
+                        for (i, w) in writers.iter_mut().enumerate() {
+                            w.push(state.state_line_for(i as u8, fog));
+                        }
+                        if state.phase() != phase_before {
+                            broadcast(&mut writers, &ServerMsg::Phase(state.phase()));
+                        }
+                        announce_turn(&mut writers, state.turn());
+                    }
+                    // Still waiting on other players to agree.
+                    Ok(false) => writers[me].send(&ok),
+                    Err(reason) => writers[me].send(&refused(reason)),
+                }
+                continue;
+            }
+            _ => {}
+        }
+
+        // Reject out-of-turn messages without advancing state.
+        if player != state.turn() {
+            writers[me].send(&refused(Refusal::NotYourTurn));
+            continue;
+        }
+
+        let phase_before = state.phase();
+        let out_before = state.eliminated().len();
+
+        let replay_cmd = cmd.as_ref().and_then(ReplayCmd::from_client);
+        let result = match cmd {
+            Some(ClientCmd::Place { x, y, radius }) => {
+                log.of(Category::Board).debug(format!("P{player} PLACE x={x:.3} y={y:.3} r={radius:.3}"));
+                state.place(player, x, y, radius)
+            }
+            Some(ClientCmd::Shoot { index, dx, dy, force }) => {
+                log.of(Category::Board).debug(format!("P{player} SHOOT #{index} dir=({dx:.3},{dy:.3}) force={force:.3}"));
+                state.shoot(player, index, dx, dy, force)
+            }
+            Some(ClientCmd::ShootVel { index, vx, vy }) => {
+                log.of(Category::Board).debug(format!("P{player} SHOOT_VEL #{index} v=({vx:.3},{vy:.3})"));
+                state.shoot_velocity(player, index, vx, vy)
+            }
+            Some(
+                ClientCmd::Hello { .. }
+                | ClientCmd::ReadyAck
+                | ClientCmd::Resync
+                | ClientCmd::DrawOffer
+                | ClientCmd::DrawAccept
+                | ClientCmd::DrawDecline
+                | ClientCmd::Undo
+                | ClientCmd::UndoAccept
+                | ClientCmd::UndoDecline
+                | ClientCmd::Name(_)
+                | ClientCmd::Rating(_)
+                | ClientCmd::Chat(_)
+                | ClientCmd::Skin(_),
+            ) => unreachable!("handled above"),
+            None => {
+                log.warn(Event::InvalidCmd { player, raw: trimmed.clone() });
+                writers[me].send(&error(ErrorCode::BadCommand, "unrecognised command"));
+                if broadcast_errors {
+                    send_others(&mut writers, player, &ServerMsg::OpponentError("unrecognised command".into()));
+                }
+                continue;
+            }
+        };
+
+        match result {
+            Ok(()) => {
+                if let Err(reason) = state.check_invariants() {
+                    log.warn(Event::InvariantBroken { reason });
+                }
+
+                if let Some(cmd) = replay_cmd {
+                    let rec = ReplayRecord::Move { player, at_ms: now_ms(), cmd };
+                    if let Some(w) = replay.as_mut()
+                        && let Err(e) = w.record(&rec).await
+                    {
+                        log.warn(Event::ReplayError { reason: e.to_string() });
+                        replay = None;
+                    }
+                    if let Some(records) = records.as_mut() {
+                        records.push(rec);
+                    }
+                }
+
+                log.of(Category::Board).trace(state.state_line().trim_end());
+                writers[me].send(&ok);
+                send_others(&mut writers, player, &ServerMsg::Ok { tag: None });
+                for (i, w) in writers.iter_mut().enumerate() {
+                    w.push(state.state_line_for(i as u8, fog));
+                }
+                if state.phase() != phase_before {
+                    log.of(Category::Board).verbose(format!("phase → {:?}", state.phase()));
+                    broadcast(&mut writers, &ServerMsg::Phase(state.phase()));
+                }
+                for &out in &state.eliminated()[out_before..] {
+                    log.info(Event::PlayerEliminated { player: out });
+                    broadcast(&mut writers, &ServerMsg::Eliminated(out));
+                }
+
+                // The player now on turn may have nothing left to do.
+                if let Some(result) = state.stalemate() {
+                    let stuck = state.turn();
+                    log.info(Event::GameOver { result });
+                    broadcast(&mut writers, &ServerMsg::GameOver(result));
+                    break match result {
+                        GameResult::Win(p) => ReplayOutcome::Stalemate { player: stuck, winner: Some(p) },
+                        GameResult::Draw => ReplayOutcome::Stalemate { player: stuck, winner: None },
+                        GameResult::TeamWin(team) => ReplayOutcome::TeamWin { player: stuck, team },
+                    };
+                }
+
+                // Signal the new active player.
+                announce_turn(&mut writers, state.turn());
+            }
+            Err(reason) => {
+                writers[me].send(&refused(reason));
+                if broadcast_errors {
+                    send_others(&mut writers, player, &ServerMsg::OpponentError(reason.to_string()));
+                }
+            }
+        }
+    };
+
+    if let Some(registry) = &registry {
+        registry.remove(game_id);
+    }
+
+    if let Some(records) = records {
+        match replay::verify(seed, state.rules(), &records, &state) {
+            Ok(()) => log.of(Category::Board).debug(format!("Replayed {} record(s) to the same board", records.len())),
+            Err(reason) => log.warn(Event::ReplayDiverged { reason }),
+        }
+    }
+
+    // Readers may still be parked on a player who never hung up.
+    for reader in readers {
+        reader.abort();
+    }
+    for w in writers {
+        w.close(flush_timeout).await;
+    }
+
+    // Only decisive games are rated: every named winner beats every named
+    // player on the losing side.
+    let winners = match outcome {
+        ReplayOutcome::TeamWin { team, .. } => state.team_members(team),
+        _ => outcome.winner().into_iter().collect(),
+    };
+    for winner in winners.iter().filter_map(|&p| names[p as usize].as_ref()) {
+        let losers = names
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| !winners.contains(&(i as u8)))
+            .filter_map(|(_, name)| name.as_ref());
+        for loser in losers {
+            let (rw, rl) = ratings.record_win(winner, loser);
+            log.verbose(Event::RatingsUpdated {
+                winner: winner.clone(),
+                rw,
+                loser: loser.clone(),
+                rl,
+            });
+        }
+    }
+
+    if let Some(w) = replay
+        && let Err(e) = w.finish(outcome).await
+    {
+        log.warn(Event::ReplayError { reason: e.to_string() });
+    }
+
+    log.info(Event::GameEnded);
+}
+
+/// Accept the next connection whose IP is under the per-IP limit.  Refused
+/// connections are closed immediately.
+async fn accept_player(
+    listener: &TcpListener,
+    limiter: &Arc<ConnLimiter>,
+    log: &Logger,
+) -> std::io::Result<(TcpStream, SocketAddr, ConnGuard)> {
+    loop {
+        let (stream, addr) = listener.accept().await?;
+        let addr = canonical(addr);
+        match limiter.try_acquire(addr.ip()) {
+            Some(guard) => return Ok((stream, addr, guard)),
+            None => log.of(Category::Connection).warn(Event::ConnRefused { addr }),
+        }
+    }
+}
+
+/// Accept players into the lobby pool, one place in it per connection, and
+/// hand them to the matchmaker.  Runs apart from matchmaking so a player who
+/// is slow to show up, or a connection that drops at once, never holds up
+/// the next accept.  With the pool full, new connections queue in the OS
+/// backlog.  Ends once the matchmaker is gone.
+async fn accept_players(
+    listener: TcpListener,
+    limiter: Arc<ConnLimiter>,
+    places: Arc<Semaphore>,
+    pool: mpsc::Sender<Seat>,
+    log: Arc<Logger>,
+) {
+    loop {
+        let Ok(place) = Arc::clone(&places).acquire_owned().await else { break };
+        match accept_player(&listener, &limiter, &log).await {
+            Ok((stream, addr, guard)) => {
+                if pool.send(Seat::new(stream, addr, guard, place)).await.is_err() {
+                    break;
+                }
+            }
+            Err(e) => log.warn(Event::AcceptError { reason: e.to_string() }),
+        }
+    }
+}
+
+// ── ENTRY POINT ───────────────────────────────────────────────────────────────
+
+/// Bind the configured address and serve games until the process is stopped.
+pub async fn run(args: ServerArgs) {
+    if let Some(teams) = args.teams
+        && teams > args.players
+    {
+        eprintln!("--teams {teams} needs at least {teams} players (got --players {})", args.players);
+        std::process::exit(1);
+    }
+    if let Err(e) = args.config().rules.starting_layout() {
+        eprintln!("--starting-pieces {}: {e}", args.starting_pieces);
+        std::process::exit(1);
+    }
+    let addr = compose_addr(args.bind, &args.host, args.port).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(1);
+    });
+    let listener = bind_listener(addr, args.dual_stack).unwrap_or_else(|e| {
+        eprintln!("Failed to bind to {addr}: {e}");
+        std::process::exit(1);
+    });
+
+    let log = Arc::new(args.logger());
+    let Some(addr) = args.http_bind else {
+        return serve(listener, args.config(), log).await;
+    };
+    let http = bind_listener(addr, false).unwrap_or_else(|e| {
+        eprintln!("Failed to bind the HTTP API to {addr}: {e}");
+        std::process::exit(1);
+    });
+    let registry = Arc::new(GameRegistry::new());
+    tokio::spawn(serve_http(http, Arc::clone(&registry), Arc::clone(&log)));
+    serve_with_registry(listener, args.config(), registry, log).await;
+}
+
+/// Accept groups of players from an already-bound `listener` and run their
+/// games, seating the longest-waiting live connections first.  Embedders
+/// and tests call this directly, e.g. with a [`Logger::capturing`] logger to
+/// assert on server events.
+pub async fn serve(listener: TcpListener, config: ServerConfig, log: Arc<Logger>) {
+    serve_games(listener, config, None, log).await;
+}
+
+/// Like [`serve`], publishing every running game to `registry`.
+pub async fn serve_with_registry(
+    listener: TcpListener,
+    config: ServerConfig,
+    registry: Arc<GameRegistry>,
+    log: Arc<Logger>,
+) {
+    serve_games(listener, config, Some(registry), log).await;
+}
+
+async fn serve_games(
+    listener: TcpListener,
+    config: ServerConfig,
+    registry: Option<Arc<GameRegistry>>,
+    log: Arc<Logger>,
+) {
+    let max_games = config.max_games.max(1) as usize;
+    let slots = Arc::new(Semaphore::new(max_games));
+
+    // Report the address actually bound, which resolves port 0 to the
+    // ephemeral port the OS picked.
+    match listener.local_addr() {
+        Ok(addr) => log.info(Event::Listening { addr }),
+        Err(e)   => log.warn(format_args!("Could not read the bound address: {e}")),
+    }
+    log.verbose(format!("Max concurrent games: {max_games}"));
+
+    let game_counter = Arc::new(AtomicU32::new(0));
+    let ratings = Arc::new(Ratings::new(config.elo_k));
+    let limiter = ConnLimiter::new(config.max_conns_per_ip.unwrap_or(u32::MAX));
+
+    // The pool holds one table's worth of players: enough to start the
+    // next game the moment a slot frees up.
+    let table = config.rules.players as usize;
+    let places = Arc::new(Semaphore::new(table));
+    let (arrivals_tx, mut arrivals) = mpsc::channel(1);
+    tokio::spawn(accept_players(listener, limiter, places, arrivals_tx, Arc::clone(&log)));
+
+    loop {
+        let game_id = game_counter.fetch_add(1, Ordering::Relaxed);
+        let game_log = Arc::new(log.scoped(format!("game {game_id}")));
+        game_log.verbose(Event::WaitingForPlayers { players: config.rules.players });
+
+        // Fill the table from the pool in order of arrival.  Everyone who
+        // cannot start at once is told to hold, and is answered while they
+        // wait.  A game slot is taken only once the table is full.
+        let mut pool: Vec<Seat> = Vec::with_capacity(table);
+        let permit = loop {
+            tokio::select! {
+                seat = arrivals.recv() => {
+                    let Some(mut seat) = seat else { return };
+                    let starts = pool.len() + 1 == table && slots.available_permits() > 0;
+                    if !starts && seat.writer.send(&ServerMsg::Waiting).await.is_err() {
+                        game_log.of(Category::Connection).info(Event::LeftLobby { addr: seat.addr });
+                        continue;
+                    }
+                    if pool.is_empty() && slots.available_permits() == 0 {
+                        log.verbose(Event::SlotsFull);
+                    }
+                    pool.push(seat);
+                }
+                (i, line) = next_lobby_line(&mut pool) => {
+                    let gone = match line {
+                        Some(line) => handle_lobby_line(&mut pool, i, &line, &game_log).await.is_err(),
+                        None => true,
+                    };
+                    if gone {
+                        game_log.of(Category::Connection).info(Event::LeftLobby { addr: pool[i].addr });
+                        pool.remove(i);
+                    }
+                }
+                permit = Arc::clone(&slots).acquire_owned(), if pool.len() == table => {
+                    let Ok(permit) = permit else { return };
+                    // Whoever hung up while waiting is not dealt in.
+                    pool.retain_mut(|seat| {
+                        let gone = seat.hung_up();
+                        if gone {
+                            game_log.of(Category::Connection).info(Event::LeftLobby { addr: seat.addr });
+                        }
+                        !gone
+                    });
+                    if pool.len() == table {
+                        break permit;
+                    }
+                }
+            }
+        };
+        let seats: Vec<Seat> = pool
+            .into_iter()
+            .map(|mut seat| {
+                drop(seat.place.take());
+                seat
+            })
+            .collect();
+
+        let cfg = GameConfig {
+            game_id,
+            seed: game_seed(config.seed, game_id),
+            rules: config.rules.clone(),
+            replay_dir: config.replay_dir.clone(),
+            verify_replay: config.verify_replay,
+            ratings: Arc::clone(&ratings),
+            fog: config.fog,
+            broadcast_errors: config.broadcast_errors,
+            send_queue: config.send_queue,
+            flush_timeout: config.flush_timeout,
+            ready_ack_timeout: config.ready_ack_timeout,
+            registry: registry.clone(),
+        };
+        tokio::spawn(async move {
+            // The permit is held for the lifetime of the game task.
+            let _permit = permit;
+            run_game(seats, cfg, game_log).await;
+        });
+    }
+}
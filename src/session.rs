@@ -1,10 +1,34 @@
-use tokio::net::TcpStream;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader, ReadHalf, WriteHalf};
 
-pub struct Session<L: GameLogic> {
-    reader: tokio::io::ReadHalf<TcpStream>,
-    writer: tokio::io::WriteHalf<TcpStream>,
+/// Drives a [`GameLogic`] over any byte stream — a `TcpStream` in production,
+/// an in-memory duplex pipe in tests.
+///
+/// Messages are newline-delimited, matching the wire protocol: one message
+/// may arrive split over several reads, or several in a single read.
+pub struct Session<S, L: GameLogic> {
+    reader: BufReader<ReadHalf<S>>,
+    writer: WriteHalf<S>,
     logic: L,
+    stats: Arc<SessionStats>,
+}
+
+/// Traffic counters for one [`Session`], readable while it runs.  Bytes
+/// include the newline delimiters.
+#[derive(Debug, Default)]
+pub struct SessionStats {
+    bytes_read: AtomicU64,
+    bytes_written: AtomicU64,
+    messages_in: AtomicU64,
+    messages_out: AtomicU64,
+}
+
+impl SessionStats {
+    pub fn bytes_read(&self) -> u64 { self.bytes_read.load(Ordering::Relaxed) }
+    pub fn bytes_written(&self) -> u64 { self.bytes_written.load(Ordering::Relaxed) }
+    pub fn messages_in(&self) -> u64 { self.messages_in.load(Ordering::Relaxed) }
+    pub fn messages_out(&self) -> u64 { self.messages_out.load(Ordering::Relaxed) }
 }
 
 pub trait GameLogic {
@@ -13,33 +37,50 @@ pub trait GameLogic {
     fn on_message(&mut self, msg: Self::Message) -> Option<Self::Message>;
 }
 
-impl<L: GameLogic> Session<L>
+impl<S, L: GameLogic> Session<S, L>
 where
+    S: AsyncRead + AsyncWrite,
     L::Message: From<Vec<u8>> + Into<Vec<u8>>,
 {
-    pub fn new(stream: TcpStream, logic: L) -> Self {
+    pub fn new(stream: S, logic: L) -> Self {
         let (reader, writer) = tokio::io::split(stream);
-        Self { reader, writer, logic }
+        Self { reader: BufReader::new(reader), writer, logic, stats: Arc::default() }
     }
 
-    pub async fn run(mut self) -> tokio::io::Result<()> {
-        let mut buffer = vec![0u8; 1024];
+    /// A handle on this session's counters.  Take it before [`Session::run`]
+    /// to watch throughput live or read the totals afterwards.
+    pub fn stats(&self) -> Arc<SessionStats> {
+        Arc::clone(&self.stats)
+    }
 
-        loop {
-            let n = self.reader.read(&mut buffer).await?;
+    /// Feed each message to the logic and write back its responses until the
+    /// peer closes the stream.  A final unterminated message is still
+    /// delivered.  Returns the logic so callers can inspect its end state.
+    pub async fn run(mut self) -> tokio::io::Result<L> {
+        let mut frame = Vec::new();
 
+        loop {
+            frame.clear();
+            let n = self.reader.read_until(b'\n', &mut frame).await?;
             if n == 0 {
                 break; // connection closed
             }
+            self.stats.bytes_read.fetch_add(n as u64, Ordering::Relaxed);
+            self.stats.messages_in.fetch_add(1, Ordering::Relaxed);
+            if frame.last() == Some(&b'\n') {
+                frame.pop();
+            }
 
-            let msg = L::Message::from(buffer[..n].to_vec());
+            let msg = L::Message::from(frame.clone());
 
             if let Some(response) = self.logic.on_message(msg) {
                 let bytes: Vec<u8> = response.into();
                 self.writer.write_all(&bytes).await?;
+                self.stats.bytes_written.fetch_add(bytes.len() as u64, Ordering::Relaxed);
+                self.stats.messages_out.fetch_add(1, Ordering::Relaxed);
             }
         }
 
-        Ok(())
+        Ok(self.logic)
     }
-}
\ No newline at end of file
+}
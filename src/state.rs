@@ -0,0 +1,980 @@
+use crate::board::BoardState;
+use crate::protocol::{ErrorCode, GameResult, Phase, RulesInfo, ServerMsg};
+use crate::rng::Rng;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A piece on the authoritative board.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Piece {
+    pub owner:  u8,
+    pub x:      f32,
+    pub y:      f32,
+    pub radius: f32,
+}
+
+/// What happens to a player who has no legal move on their turn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum StalemateRule {
+    /// The stuck player loses.
+    Loss,
+    /// The game is drawn.
+    Draw,
+}
+
+/// How placing and shooting are scheduled over a game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum PhaseMode {
+    /// Place and shoot in any order.
+    Open,
+    /// Each player places `pieces_per_player` pieces, then only shooting is legal.
+    PlacementThenShoot,
+}
+
+/// Per-game rule settings, fixed for the lifetime of a `GameState`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Rules {
+    /// Side length of the square board; pieces are placed wholly inside
+    /// `0..board_size` on both axes.
+    pub board_size: f32,
+    /// Smallest radius a piece may be placed with.
+    pub min_radius: f32,
+    /// Largest radius a piece may be placed with.
+    pub max_radius: f32,
+    /// Largest `force` a single shot may use.
+    pub max_force:  f32,
+    /// Shortest distance a shot must move its piece, so that a feeble shot
+    /// cannot be used to pass; `None` allows any shot.
+    pub min_shoot_distance: Option<f32>,
+    pub stalemate:  StalemateRule,
+    pub phase_mode: PhaseMode,
+    /// Pieces each player places before shooting starts in phased mode.
+    pub pieces_per_player: u32,
+    /// Most pieces a player may place over the whole game; `None` is unlimited.
+    pub max_pieces_per_player: Option<u32>,
+    /// Players seated in the game, numbered `0..players`.
+    pub players: u8,
+    /// Number of teams, or `None` for every player on their own.  Player `p`
+    /// plays for team `p % teams`, so turns alternate between teams.
+    pub teams: Option<u8>,
+    /// Pieces dealt to each player before the first turn, laid out by
+    /// [`Rules::starting_layout`].  They count towards `pieces_per_player`,
+    /// so dealing that many skips the placement phase, but not towards
+    /// `max_pieces_per_player`, which budgets only the pieces a player places.
+    pub starting_pieces: u32,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self {
+            board_size: 500.0,
+            min_radius: 1.0,
+            max_radius: 50.0,
+            max_force:  500.0,
+            min_shoot_distance: None,
+            stalemate:  StalemateRule::Loss,
+            phase_mode: PhaseMode::Open,
+            pieces_per_player: 3,
+            max_pieces_per_player: None,
+            players: 2,
+            teams: None,
+            starting_pieces: 0,
+        }
+    }
+}
+
+impl Rules {
+    /// Where the [`Rules::starting_pieces`] go: evenly spaced on a ring
+    /// around the centre of the board, each player's pieces side by side,
+    /// all of one size.  Turning the board by one player's share of the ring
+    /// turns every player's pieces into the next one's.  Fails if pieces big
+    /// enough for `min_radius` cannot fit.
+    pub fn starting_layout(&self) -> Result<Vec<Piece>, &'static str> {
+        let per_player = self.starting_pieces;
+        let count = self.players.max(2) as u32 * per_player;
+        if count == 0 {
+            return Ok(Vec::new());
+        }
+        let centre = self.board_size / 2.0;
+        let ring = self.board_size * 0.3;
+        let step = std::f32::consts::TAU / count as f32;
+        // Neighbours on the ring are 2·ring·sin(step/2) apart; leave a fifth
+        // of that free between them.
+        let radius = (0.8 * ring * (step / 2.0).sin()).min(self.max_radius).min(centre - ring);
+        if radius.is_nan() || radius < self.min_radius {
+            return Err("the starting pieces do not fit on the board");
+        }
+        Ok((0..count)
+            .map(|i| {
+                let angle = step * (i as f32 + 0.5);
+                Piece {
+                    owner: (i / per_player) as u8,
+                    x:     centre + ring * angle.cos(),
+                    y:     centre + ring * angle.sin(),
+                    radius,
+                }
+            })
+            .collect())
+    }
+}
+
+/// The limits announced to players in `RULES`.  Turns are not timed.
+impl From<&Rules> for RulesInfo {
+    fn from(rules: &Rules) -> Self {
+        Self {
+            board_size:   rules.board_size,
+            min_radius:   rules.min_radius,
+            max_radius:   rules.max_radius,
+            max_force:    rules.max_force,
+            piece_limit:  rules.max_pieces_per_player,
+            turn_timeout: None,
+        }
+    }
+}
+
+/// What each player is shown of enemy pieces.  The default is full
+/// visibility.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FogConfig {
+    /// Enemy pieces are only revealed within this distance of a friendly
+    /// piece's centre; `None` reveals every piece.
+    pub sight: Option<f32>,
+    /// Withhold the radius of enemy pieces even when they are revealed.
+    pub hide_radius: bool,
+}
+
+/// Why [`GameState`] refused a move or request.  Each carries the
+/// [`ErrorCode`] sent with it; its `Display` is the text for people.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Refusal {
+    NotYourTurn,
+    Eliminated,
+    PlacementOver,
+    StillPlacing,
+    PieceLimit,
+    QuotaMet,
+    BadCoordinates,
+    BadShotNumbers,
+    Overflow,
+    RadiusNotPositive,
+    RadiusTooSmall,
+    RadiusTooLarge,
+    OffBoard,
+    Overlap,
+    LandsOnPiece,
+    BadIndex,
+    NotYourPiece,
+    ForceNotPositive,
+    ForceTooLarge,
+    NoDirection,
+    NoVelocity,
+    TooWeak,
+    NoDrawToAccept,
+    NoDrawToDecline,
+    NoUndoToAccept,
+    NoUndoToDecline,
+    NothingToUndo,
+    DrawPending,
+    UndoPending,
+    DrawOffered,
+    DrawAccepted,
+    UndoAsked,
+    UndoAgreed,
+}
+
+impl Refusal {
+    /// The code sent with this refusal in an `ERROR` line.
+    pub fn code(self) -> ErrorCode {
+        match self {
+            Self::NotYourTurn       => ErrorCode::NotYourTurn,
+            Self::Eliminated        => ErrorCode::Eliminated,
+            Self::PlacementOver     => ErrorCode::WrongPhase,
+            Self::StillPlacing      => ErrorCode::WrongPhase,
+            Self::PieceLimit        => ErrorCode::PieceLimit,
+            Self::QuotaMet          => ErrorCode::PieceLimit,
+            Self::BadCoordinates    => ErrorCode::BadNumber,
+            Self::BadShotNumbers    => ErrorCode::BadNumber,
+            Self::Overflow          => ErrorCode::BadNumber,
+            Self::RadiusNotPositive => ErrorCode::BadRadius,
+            Self::RadiusTooSmall    => ErrorCode::BadRadius,
+            Self::RadiusTooLarge    => ErrorCode::BadRadius,
+            Self::OffBoard          => ErrorCode::OutOfBounds,
+            Self::Overlap           => ErrorCode::Overlap,
+            Self::LandsOnPiece      => ErrorCode::Overlap,
+            Self::BadIndex          => ErrorCode::BadIndex,
+            Self::NotYourPiece      => ErrorCode::NotYourPiece,
+            Self::ForceNotPositive  => ErrorCode::BadShot,
+            Self::ForceTooLarge     => ErrorCode::BadShot,
+            Self::NoDirection       => ErrorCode::BadShot,
+            Self::NoVelocity        => ErrorCode::BadShot,
+            Self::TooWeak           => ErrorCode::BadShot,
+            Self::NoDrawToAccept    => ErrorCode::NothingPending,
+            Self::NoDrawToDecline   => ErrorCode::NothingPending,
+            Self::NoUndoToAccept    => ErrorCode::NothingPending,
+            Self::NoUndoToDecline   => ErrorCode::NothingPending,
+            Self::NothingToUndo     => ErrorCode::NothingPending,
+            Self::DrawPending       => ErrorCode::Pending,
+            Self::UndoPending       => ErrorCode::Pending,
+            Self::DrawOffered       => ErrorCode::AlreadyDone,
+            Self::DrawAccepted      => ErrorCode::AlreadyDone,
+            Self::UndoAsked         => ErrorCode::AlreadyDone,
+            Self::UndoAgreed        => ErrorCode::AlreadyDone,
+        }
+    }
+
+    /// The human-readable reason that follows the code.
+    pub fn reason(self) -> &'static str {
+        match self {
+            Self::NotYourTurn       => "not your turn",
+            Self::Eliminated        => "you are out of the game",
+            Self::PlacementOver     => "the placement phase is over",
+            Self::StillPlacing      => "shooting is not allowed during the placement phase",
+            Self::PieceLimit        => "piece limit reached",
+            Self::QuotaMet          => "you have placed all your pieces for this phase",
+            Self::BadCoordinates    => "coordinates must be finite numbers",
+            Self::BadShotNumbers    => "direction and force must be finite numbers",
+            Self::Overflow          => "numeric overflow",
+            Self::RadiusNotPositive => "radius must be positive",
+            Self::RadiusTooSmall    => "radius is below the minimum",
+            Self::RadiusTooLarge    => "radius exceeds the maximum",
+            Self::OffBoard          => "piece must lie within the board",
+            Self::Overlap           => "overlaps an existing piece",
+            Self::LandsOnPiece      => "shot would land on another piece",
+            Self::BadIndex          => "piece index out of range",
+            Self::NotYourPiece      => "that piece does not belong to you",
+            Self::ForceNotPositive  => "force must be positive",
+            Self::ForceTooLarge     => "force exceeds the maximum",
+            Self::NoDirection       => "direction vector must be non-zero",
+            Self::NoVelocity        => "velocity must be non-zero",
+            Self::TooWeak           => "shot too weak",
+            Self::NoDrawToAccept    => "no draw offer to accept",
+            Self::NoDrawToDecline   => "no draw offer to decline",
+            Self::NoUndoToAccept    => "no undo request to accept",
+            Self::NoUndoToDecline   => "no undo request to decline",
+            Self::NothingToUndo     => "there is no move to undo",
+            Self::DrawPending       => "another player has offered a draw; accept or decline it",
+            Self::UndoPending       => "another player has asked to undo; accept or decline it",
+            Self::DrawOffered       => "you have already offered a draw",
+            Self::DrawAccepted      => "you have already accepted the draw",
+            Self::UndoAsked         => "you have already asked to undo",
+            Self::UndoAgreed        => "you have already agreed to the undo",
+        }
+    }
+}
+
+impl fmt::Display for Refusal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.reason())
+    }
+}
+
+/// Authoritative server-side game state.
+///
+/// Every rule check lives here so the live server and offline tools (such as
+/// the replay player) accept and reject exactly the same moves.
+///
+/// All randomness is drawn from an RNG seeded with [`GameState::seed`], so a
+/// game replayed from the same seed and moves is byte-identical.
+#[derive(Debug, Clone)]
+pub struct GameState {
+    pieces: Vec<Piece>,
+    turn:   u8,     // 0..rules.players
+    seq:    u64,    // bumped on every accepted move
+    tick:   u64,    // simulation steps run; never rewound
+    seed:   u64,
+    rng:    Rng,
+    rules:  Rules,
+    phase:  Phase,
+    placed: Vec<u32>,         // pieces placed so far, per owner
+    eliminated: Vec<u8>,      // players knocked out, in order
+    draw_offer: Option<u8>,   // player with a pending draw offer
+    draw_accepted: Vec<u8>,   // players who have accepted that offer
+    undo_request: Option<u8>, // player asking to take back the last move
+    undo_accepted: Vec<u8>,   // players who have agreed to it
+    history: Vec<Snapshot>,   // state before each accepted move, oldest first
+}
+
+/// Everything a move can change, saved so the move can be taken back.
+#[derive(Debug, Clone)]
+struct Snapshot {
+    pieces:     Vec<Piece>,
+    turn:       u8,
+    phase:      Phase,
+    placed:     Vec<u32>,
+    eliminated: Vec<u8>,
+}
+
+impl Default for GameState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GameState {
+    pub fn new() -> Self {
+        Self::with_seed(0)
+    }
+
+    /// Fresh game with default rules whose first player is chosen by `seed`.
+    pub fn with_seed(seed: u64) -> Self {
+        Self::with_rules(seed, Rules::default())
+    }
+
+    pub fn with_rules(seed: u64, rules: Rules) -> Self {
+        let mut rng = Rng::new(seed);
+        let players = rules.players.max(2);
+        let turn = rng.below(players as u64) as u8;
+        // Dealt pieces count towards the quota, so enough of them skip the
+        // placement phase altogether.
+        let phase = match rules.phase_mode {
+            PhaseMode::Open => Phase::Open,
+            PhaseMode::PlacementThenShoot if rules.starting_pieces >= rules.pieces_per_player => Phase::Shooting,
+            PhaseMode::PlacementThenShoot => Phase::Placement,
+        };
+        // The server refuses to start with a layout that does not fit.
+        let pieces = rules.starting_layout().unwrap_or_default();
+        Self {
+            pieces,
+            turn,
+            seq: 0,
+            tick: 0,
+            seed,
+            rng,
+            rules,
+            phase,
+            placed: vec![0; players as usize],
+            eliminated: Vec::new(),
+            draw_offer: None,
+            draw_accepted: Vec::new(),
+            undo_request: None,
+            undo_accepted: Vec::new(),
+            history: Vec::new(),
+        }
+    }
+
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+
+    pub fn phase(&self) -> Phase {
+        self.phase
+    }
+
+    /// Placements `player` has left under the piece budget, if there is one.
+    pub fn placements_left(&self, player: u8) -> Option<u32> {
+        let max = self.rules.max_pieces_per_player?;
+        Some(max.saturating_sub(self.placed[player as usize]))
+    }
+
+    /// Number of pieces `player` currently has on the board.
+    pub fn pieces_owned(&self, player: u8) -> usize {
+        self.pieces.iter().filter(|p| p.owner == player).count()
+    }
+
+    /// Whether `player` has placed their share for the placement phase.
+    fn quota_met(&self, player: u8) -> bool {
+        self.pieces_owned(player) >= self.rules.pieces_per_player as usize
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The game's seeded RNG — the only permitted source of randomness.
+    pub fn rng(&mut self) -> &mut Rng {
+        &mut self.rng
+    }
+
+    pub fn pieces(&self) -> &[Piece] {
+        &self.pieces
+    }
+
+    /// The player whose move is expected next.
+    pub fn turn(&self) -> u8 {
+        self.turn
+    }
+
+    /// Number of players seated in the game.
+    pub fn players(&self) -> u8 {
+        self.placed.len() as u8
+    }
+
+    /// Players knocked out for having no legal move, in the order they went.
+    pub fn eliminated(&self) -> &[u8] {
+        &self.eliminated
+    }
+
+    /// Whether `player` is still taking turns.
+    pub fn is_active(&self, player: u8) -> bool {
+        player < self.players() && !self.eliminated.contains(&player)
+    }
+
+    /// The team `player` plays for.  Without teams each player is their own.
+    pub fn team_of(&self, player: u8) -> u8 {
+        match self.rules.teams {
+            Some(teams) => player % teams.max(1),
+            None => player,
+        }
+    }
+
+    /// Every player seated on `team`.
+    pub fn team_members(&self, team: u8) -> Vec<u8> {
+        (0..self.players()).filter(|&p| self.team_of(p) == team).collect()
+    }
+
+    /// Teams with an active player other than `player`.
+    fn live_teams_besides(&self, player: u8) -> usize {
+        let mut teams: Vec<u8> = (0..self.players())
+            .filter(|&p| p != player && self.is_active(p))
+            .map(|p| self.team_of(p))
+            .collect();
+        teams.sort_unstable();
+        teams.dedup();
+        teams.len()
+    }
+
+    fn active_count(&self) -> usize {
+        self.players() as usize - self.eliminated.len()
+    }
+
+    /// The next player after `player` in seating order who is still active.
+    fn next_active(&self, player: u8) -> u8 {
+        let n = self.players();
+        let mut next = (player + 1) % n;
+        while !self.is_active(next) && next != player {
+            next = (next + 1) % n;
+        }
+        next
+    }
+
+    /// Number of moves accepted so far.
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    /// Simulation steps run so far.  Unlike [`GameState::seq`] an undo does
+    /// not take this back, so clients can order frames by it.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Full board serialised as a server message ready to write to a socket.
+    pub fn state_line(&self) -> String {
+        ServerMsg::State(BoardState::from(self)).to_wire()
+    }
+
+    /// The board as `player` may see it under `fog`, serialised like
+    /// [`GameState::state_line`].  Hidden enemy pieces keep their place in
+    /// the list, so piece indices are the same for every player.
+    pub fn state_line_for(&self, player: u8, fog: FogConfig) -> String {
+        let mut board = BoardState::from(self);
+        let team = self.team_of(player);
+        let friendly: Vec<(f32, f32)> = self
+            .pieces
+            .iter()
+            .filter(|p| self.team_of(p.owner) == team)
+            .map(|p| (p.x, p.y))
+            .collect();
+
+        for piece in board.pieces.iter_mut().filter(|p| self.team_of(p.owner) != team) {
+            let in_sight = fog.sight.is_none_or(|range| {
+                friendly
+                    .iter()
+                    .any(|&(x, y)| ((piece.x - x).powi(2) + (piece.y - y).powi(2)).sqrt() <= range)
+            });
+            if !in_sight {
+                piece.x = f32::NAN;
+                piece.y = f32::NAN;
+            }
+            if !in_sight || fog.hide_radius {
+                piece.radius = f32::NAN;
+            }
+        }
+        ServerMsg::State(board).to_wire()
+    }
+
+    /// Player whose draw offer is awaiting an answer, if any.
+    pub fn draw_offer(&self) -> Option<u8> {
+        self.draw_offer
+    }
+
+    /// Propose a draw.  Allowed at any time and does not use up a turn; the
+    /// offer lapses once the offering player makes their next move.
+    pub fn offer_draw(&mut self, player: u8) -> Result<(), Refusal> {
+        if !self.is_active(player) {
+            return Err(Refusal::Eliminated);
+        }
+        match self.draw_offer {
+            Some(p) if p == player => Err(Refusal::DrawOffered),
+            Some(_) => Err(Refusal::DrawPending),
+            None => {
+                self.draw_offer = Some(player);
+                Ok(())
+            }
+        }
+    }
+
+    /// Accept another player's pending offer.  `Ok(true)` means every other
+    /// active player has now accepted and the game is drawn; with two players
+    /// that is always the case.
+    pub fn accept_draw(&mut self, player: u8) -> Result<bool, Refusal> {
+        if !self.is_active(player) {
+            return Err(Refusal::Eliminated);
+        }
+        match self.draw_offer {
+            Some(p) if p != player => {
+                if self.draw_accepted.contains(&player) {
+                    return Err(Refusal::DrawAccepted);
+                }
+                self.draw_accepted.push(player);
+                if self.draw_accepted.len() + 1 < self.active_count() {
+                    return Ok(false);
+                }
+                self.clear_draw_offer();
+                Ok(true)
+            }
+            _ => Err(Refusal::NoDrawToAccept),
+        }
+    }
+
+    /// Refuse another player's pending offer.  One refusal withdraws it for
+    /// everyone.
+    pub fn decline_draw(&mut self, player: u8) -> Result<(), Refusal> {
+        if !self.is_active(player) {
+            return Err(Refusal::Eliminated);
+        }
+        match self.draw_offer {
+            Some(p) if p != player => {
+                self.clear_draw_offer();
+                Ok(())
+            }
+            _ => Err(Refusal::NoDrawToDecline),
+        }
+    }
+
+    fn clear_draw_offer(&mut self) {
+        self.draw_offer = None;
+        self.draw_accepted.clear();
+    }
+
+    /// Player whose undo request is awaiting an answer, if any.
+    pub fn undo_request(&self) -> Option<u8> {
+        self.undo_request
+    }
+
+    /// Ask to take back the last move.  Like a draw offer it is allowed at
+    /// any time, needs every other active player to agree, and lapses as
+    /// soon as anyone moves.
+    pub fn request_undo(&mut self, player: u8) -> Result<(), Refusal> {
+        if !self.is_active(player) {
+            return Err(Refusal::Eliminated);
+        }
+        if self.history.is_empty() {
+            return Err(Refusal::NothingToUndo);
+        }
+        match self.undo_request {
+            Some(p) if p == player => Err(Refusal::UndoAsked),
+            Some(_) => Err(Refusal::UndoPending),
+            None => {
+                self.undo_request = Some(player);
+                Ok(())
+            }
+        }
+    }
+
+    /// Agree to another player's undo request.  `Ok(true)` means every other
+    /// active player has now agreed and the last move has been taken back.
+    pub fn accept_undo(&mut self, player: u8) -> Result<bool, Refusal> {
+        if !self.is_active(player) {
+            return Err(Refusal::Eliminated);
+        }
+        match self.undo_request {
+            Some(p) if p != player => {
+                if self.undo_accepted.contains(&player) {
+                    return Err(Refusal::UndoAgreed);
+                }
+                self.undo_accepted.push(player);
+                if self.undo_accepted.len() + 1 < self.active_count() {
+                    return Ok(false);
+                }
+                self.undo()?;
+                Ok(true)
+            }
+            _ => Err(Refusal::NoUndoToAccept),
+        }
+    }
+
+    /// Refuse another player's undo request.
+    pub fn decline_undo(&mut self, player: u8) -> Result<(), Refusal> {
+        if !self.is_active(player) {
+            return Err(Refusal::Eliminated);
+        }
+        match self.undo_request {
+            Some(p) if p != player => {
+                self.clear_undo_request();
+                Ok(())
+            }
+            _ => Err(Refusal::NoUndoToDecline),
+        }
+    }
+
+    fn clear_undo_request(&mut self) {
+        self.undo_request = None;
+        self.undo_accepted.clear();
+    }
+
+    /// Take back the last accepted move, restoring the board and turn from
+    /// before it.  The sequence number still goes up, so clients treat the
+    /// restored board as new rather than stale.  Pending offers are dropped.
+    pub fn undo(&mut self) -> Result<(), Refusal> {
+        let snapshot = self.history.pop().ok_or(Refusal::NothingToUndo)?;
+        self.pieces     = snapshot.pieces;
+        self.turn       = snapshot.turn;
+        self.phase      = snapshot.phase;
+        self.placed     = snapshot.placed;
+        self.eliminated = snapshot.eliminated;
+        self.seq += 1;
+        self.clear_draw_offer();
+        self.clear_undo_request();
+        Ok(())
+    }
+
+    fn save_snapshot(&mut self) {
+        self.history.push(Snapshot {
+            pieces:     self.pieces.clone(),
+            turn:       self.turn,
+            phase:      self.phase,
+            placed:     self.placed.clone(),
+            eliminated: self.eliminated.clone(),
+        });
+    }
+
+    /// Whether `player` has any legal move: a piece of their own to shoot,
+    /// or room somewhere on the board for a minimum-size placement (see
+    /// [`GameState::room_to_place`]).
+    pub fn has_legal_move(&self, player: u8) -> bool {
+        if self.phase != Phase::Placement && self.pieces_owned(player) > 0 {
+            return true;
+        }
+        if self.phase == Phase::Shooting || self.placements_left(player) == Some(0) {
+            return false;
+        }
+        if self.phase == Phase::Placement && self.quota_met(player) {
+            return false;
+        }
+        self.room_to_place()
+    }
+
+    /// Whether a piece of the minimum radius `r` fits anywhere on the board.
+    ///
+    /// The centres it fits at are the square `r..=board_size - r` less an
+    /// open disc around each piece, of that piece's radius plus `r`.  If any
+    /// are left, so is a corner of what is left: a corner of the square, a
+    /// point where a disc's rim crosses a side, or one where two rims cross.
+    /// Only those are tried, the rims once as they are and once a hair wider
+    /// so rounding cannot lose a gap, and each try looks only at the pieces
+    /// in the coarse grid cells around it.  The first spot that fits ends
+    /// the search.
+    fn room_to_place(&self) -> bool {
+        let (r, size) = (self.rules.min_radius, self.rules.board_size);
+        let (lo, hi) = (r as f64, (size - r) as f64);
+        if lo > hi {
+            return false;
+        }
+        let rims: Vec<(f64, f64, f64)> =
+            self.pieces.iter().map(|p| (p.x as f64, p.y as f64, (p.radius + r) as f64)).collect();
+
+        // Rims that cross have centres less than two of the widest apart,
+        // and a disc holding a point has its centre within one of it, so
+        // with cells that wide both turn up in the 3×3 block around a cell.
+        // Never more than 32 cells a side, however small the pieces.
+        let widest = rims.iter().map(|&(_, _, rim)| rim).fold(lo, f64::max);
+        let cell = (2.0 * widest).max(size as f64 / 32.0);
+        let cols = (size as f64 / cell) as usize + 1;
+        let cell_of = |v: f64| ((v / cell).max(0.0) as usize).min(cols - 1);
+        let mut grid = vec![Vec::new(); cols * cols];
+        for (i, &(x, y, _)) in rims.iter().enumerate() {
+            grid[cell_of(y) * cols + cell_of(x)].push(i);
+        }
+        let grid = &grid;
+        let near = |x: f64, y: f64| {
+            let (col, row) = (cell_of(x), cell_of(y));
+            (row.saturating_sub(1)..=(row + 1).min(cols - 1)).flat_map(move |row| {
+                (col.saturating_sub(1)..=(col + 1).min(cols - 1))
+                    .flat_map(move |col| grid[row * cols + col].iter().copied())
+            })
+        };
+
+        // The same checks `place` makes, at the nearest `f32` centre.
+        let fits = |x: f64, y: f64| {
+            let (x, y) = (x as f32, y as f32);
+            x - r >= 0.0
+                && y - r >= 0.0
+                && x + r <= size
+                && y + r <= size
+                && near(x as f64, y as f64).all(|i| {
+                    let p = &self.pieces[i];
+                    ((p.x - x).powi(2) + (p.y - y).powi(2)).sqrt() >= p.radius + r
+                })
+        };
+
+        if [(lo, lo), (lo, hi), (hi, lo), (hi, hi)].into_iter().any(|(x, y)| fits(x, y)) {
+            return true;
+        }
+        for grow in [0.0, size as f64 * 1e-5] {
+            for (i, &(x, y, rim)) in rims.iter().enumerate() {
+                let rim = rim + grow;
+                for side in [lo, hi] {
+                    let h = rim * rim - (side - x).powi(2);
+                    if h >= 0.0 && (fits(side, y - h.sqrt()) || fits(side, y + h.sqrt())) {
+                        return true;
+                    }
+                    let h = rim * rim - (side - y).powi(2);
+                    if h >= 0.0 && (fits(x - h.sqrt(), side) || fits(x + h.sqrt(), side)) {
+                        return true;
+                    }
+                }
+                for j in near(x, y).filter(|&j| j > i) {
+                    let (ox, oy, other) = rims[j];
+                    let other = other + grow;
+                    let (dx, dy) = (ox - x, oy - y);
+                    let d = dx.hypot(dy);
+                    if d == 0.0 || d > rim + other || d < (rim - other).abs() {
+                        continue;
+                    }
+                    // Along the line between the centres to the chord the
+                    // rims share, then either way along the chord.
+                    let along = (rim * rim - other * other + d * d) / (2.0 * d);
+                    let off = (rim * rim - along * along).max(0.0).sqrt();
+                    let (mx, my) = (x + along * dx / d, y + along * dy / d);
+                    let (ux, uy) = (-dy / d * off, dx / d * off);
+                    if fits(mx + ux, my + uy) || fits(mx - ux, my - uy) {
+                        return true;
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    /// The result to declare if the player about to move is stuck.
+    ///
+    /// Under [`StalemateRule::Loss`] a stuck player is only knocked out while
+    /// two or more other teams play on (see [`GameState::eliminated`]), so by
+    /// the time this reports a win exactly one other team is left.
+    pub fn stalemate(&self) -> Option<GameResult> {
+        if self.has_legal_move(self.turn) {
+            return None;
+        }
+        let winner = self.next_active(self.turn);
+        Some(match (self.rules.stalemate, self.rules.teams) {
+            (StalemateRule::Loss, None)    => GameResult::Win(winner),
+            (StalemateRule::Loss, Some(_)) => GameResult::TeamWin(self.team_of(winner)),
+            (StalemateRule::Draw, _)       => GameResult::Draw,
+        })
+    }
+
+    fn overlaps_any(&self, x: f32, y: f32, radius: f32) -> bool {
+        self.pieces.iter().any(|p| {
+            let dist = ((p.x - x).powi(2) + (p.y - y).powi(2)).sqrt();
+            dist < p.radius + radius
+        })
+    }
+
+    /// Like [`GameState::overlaps_any`], ignoring piece `index` itself.
+    fn overlaps_other(&self, index: usize, x: f32, y: f32, radius: f32) -> bool {
+        self.pieces.iter().enumerate().any(|(i, p)| {
+            let dist = ((p.x - x).powi(2) + (p.y - y).powi(2)).sqrt();
+            i != index && dist < p.radius + radius
+        })
+    }
+
+    /// Verify the structural invariants every reachable state must satisfy:
+    /// a valid turn, known owners, finite coordinates and positive radii.
+    pub fn check_invariants(&self) -> Result<(), String> {
+        let n = self.players();
+        if !self.is_active(self.turn) {
+            return Err(format!("turn is {}, expected an active player below {n}", self.turn));
+        }
+        for (i, p) in self.pieces.iter().enumerate() {
+            if p.owner >= n {
+                return Err(format!("piece #{i} has unknown owner {}", p.owner));
+            }
+            if !(p.x.is_finite() && p.y.is_finite()) {
+                return Err(format!("piece #{i} has non-finite position ({}, {})", p.x, p.y));
+            }
+            if !(p.radius.is_finite() && p.radius > 0.0) {
+                return Err(format!("piece #{i} has invalid radius {}", p.radius));
+            }
+        }
+        Ok(())
+    }
+
+    /// First pair of pieces whose circles overlap, if any.
+    ///
+    /// Neither placing nor shooting creates an overlap, but replays recorded
+    /// before shots were checked may contain one, so this is a diagnostic
+    /// rather than an invariant.
+    pub fn overlapping_pair(&self) -> Option<(usize, usize)> {
+        for (i, a) in self.pieces.iter().enumerate() {
+            for (j, b) in self.pieces.iter().enumerate().skip(i + 1) {
+                let dist = ((a.x - b.x).powi(2) + (a.y - b.y).powi(2)).sqrt();
+                if dist < a.radius + b.radius {
+                    return Some((i, j));
+                }
+            }
+        }
+        None
+    }
+
+    pub fn place(&mut self, owner: u8, x: f32, y: f32, radius: f32) -> Result<(), Refusal> {
+        if owner != self.turn {
+            return Err(Refusal::NotYourTurn);
+        }
+        if self.phase == Phase::Shooting {
+            return Err(Refusal::PlacementOver);
+        }
+        if self.placements_left(owner) == Some(0) {
+            return Err(Refusal::PieceLimit);
+        }
+        if self.phase == Phase::Placement && self.quota_met(owner) {
+            return Err(Refusal::QuotaMet);
+        }
+        if !(x.is_finite() && y.is_finite() && radius.is_finite()) {
+            return Err(Refusal::BadCoordinates);
+        }
+        if radius <= 0.0 {
+            return Err(Refusal::RadiusNotPositive);
+        }
+        if radius < self.rules.min_radius {
+            return Err(Refusal::RadiusTooSmall);
+        }
+        if radius > self.rules.max_radius {
+            return Err(Refusal::RadiusTooLarge);
+        }
+        let size = self.rules.board_size;
+        if x - radius < 0.0 || y - radius < 0.0 || x + radius > size || y + radius > size {
+            return Err(Refusal::OffBoard);
+        }
+        if self.overlaps_any(x, y, radius) {
+            return Err(Refusal::Overlap);
+        }
+        self.save_snapshot();
+        self.pieces.push(Piece { owner, x, y, radius });
+        self.placed[owner as usize] += 1;
+        self.end_move(owner);
+        Ok(())
+    }
+
+    /// Move piece `index` by exactly `(vx, vy)`: a [`GameState::shoot`] whose
+    /// force is the velocity's length, so the same limits apply to it.
+    pub fn shoot_velocity(&mut self, owner: u8, index: usize, vx: f32, vy: f32) -> Result<(), Refusal> {
+        if vx == 0.0 && vy == 0.0 {
+            return Err(Refusal::NoVelocity);
+        }
+        self.shoot(owner, index, vx, vy, vx.hypot(vy))
+    }
+
+    /// Move piece `index` by `force` along `(dx, dy)`.
+    ///
+    /// A shot that would leave the piece overlapping another, including one
+    /// that lands exactly on it, or partly off the board, is rejected and
+    /// the turn is not used up.
+    /// Pieces are never pushed apart: the board only ever changes by the
+    /// move a player asked for.  A shot whose landing spot is too far out
+    /// to represent is rejected as a numeric overflow.
+    pub fn shoot(
+        &mut self,
+        owner: u8,
+        index: usize,
+        dx: f32,
+        dy: f32,
+        force: f32,
+    ) -> Result<(), Refusal> {
+        if owner != self.turn {
+            return Err(Refusal::NotYourTurn);
+        }
+        if self.phase == Phase::Placement {
+            return Err(Refusal::StillPlacing);
+        }
+        if !(dx.is_finite() && dy.is_finite() && force.is_finite()) {
+            return Err(Refusal::BadShotNumbers);
+        }
+        if force <= 0.0 {
+            return Err(Refusal::ForceNotPositive);
+        }
+        if force > self.rules.max_force {
+            return Err(Refusal::ForceTooLarge);
+        }
+        // `hypot` rather than squaring, which overflows for large vectors.
+        let len = dx.hypot(dy);
+        if len < f32::EPSILON {
+            return Err(Refusal::NoDirection);
+        }
+        let (step_x, step_y) = ((dx / len) * force, (dy / len) * force);
+        if let Some(min) = self.rules.min_shoot_distance
+            && (step_x * step_x + step_y * step_y).sqrt() < min
+        {
+            return Err(Refusal::TooWeak);
+        }
+        let piece = self.pieces.get(index).ok_or(Refusal::BadIndex)?;
+        if piece.owner != owner {
+            return Err(Refusal::NotYourPiece);
+        }
+        let x = piece.x + step_x;
+        let y = piece.y + step_y;
+        if !(x.is_finite() && y.is_finite()) {
+            return Err(Refusal::Overflow);
+        }
+        let (r, size) = (piece.radius, self.rules.board_size);
+        if x - r < 0.0 || y - r < 0.0 || x + r > size || y + r > size {
+            return Err(Refusal::OffBoard);
+        }
+        if self.overlaps_other(index, x, y, piece.radius) {
+            return Err(Refusal::LandsOnPiece);
+        }
+        self.save_snapshot();
+        let p = &mut self.pieces[index];
+        p.x = x;
+        p.y = y;
+        self.end_move(owner);
+        Ok(())
+    }
+
+    /// Bookkeeping shared by every accepted move.
+    fn end_move(&mut self, mover: u8) {
+        self.seq += 1;
+        // Moves resolve at once here, which counts as a single step.
+        self.tick += 1;
+        if self.draw_offer == Some(mover) {
+            self.clear_draw_offer();
+        }
+        self.clear_undo_request();
+        if self.phase == Phase::Placement
+            && (0..self.players())
+                .filter(|&p| self.is_active(p))
+                .all(|p| self.quota_met(p))
+        {
+            self.phase = Phase::Shooting;
+        }
+        self.turn = self.next_active(mover);
+
+        // A stuck player drops out while at least two other teams play on;
+        // otherwise the game is settled by `stalemate`.
+        while self.rules.stalemate == StalemateRule::Loss
+            && self.live_teams_besides(self.turn) > 1
+            && !self.has_legal_move(self.turn)
+        {
+            let out = self.turn;
+            self.eliminated.push(out);
+            if self.draw_offer == Some(out) {
+                self.clear_draw_offer();
+            }
+            self.draw_accepted.retain(|&p| p != out);
+            self.turn = self.next_active(out);
+        }
+    }
+}
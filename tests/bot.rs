@@ -0,0 +1,115 @@
+//! The bot binary against a real server, with the terminal client playing
+//! the human's side from a script.
+
+use clap::Parser;
+use seb_mul_game::server::{self, ServerArgs};
+use std::net::SocketAddr;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::process::Command;
+use tokio::time::{sleep, timeout};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+async fn start_server(flags: &[&str]) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let args = ServerArgs::parse_from(std::iter::once("server").chain(flags.iter().copied()));
+    tokio::spawn(server::serve(listener, args.config(), Arc::new(args.logger())));
+    addr
+}
+
+/// Seat `strategy` against a scripted human, who places up to four pieces
+/// and leaves.  Returns what the human's client and the bot printed.
+async fn play(strategy: &str, places: usize) -> (String, String) {
+    let addr = start_server(&["--seed", "7"]).await;
+    let bot = Command::new(env!("CARGO_BIN_EXE_bot"))
+        .arg(addr.to_string())
+        .args(["--strategy", strategy, "--seed", "11"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .unwrap();
+    // Seat the bot first, so the seats come out the same on every run.
+    sleep(Duration::from_millis(200)).await;
+
+    // Corners, far enough apart that the bot cannot crowd one by
+    // filling another.
+    let corners = ["place 30 30 10\n", "place 470 470 10\n", "place 30 470 10\n", "place 470 30 10\n"];
+    let commands = corners[..places].concat();
+    let path = std::env::temp_dir().join(format!("tilez-bot-{strategy}-{}.txt", std::process::id()));
+    std::fs::write(&path, commands).unwrap();
+    let human = Command::new(env!("CARGO_BIN_EXE_client"))
+        .arg(addr.to_string())
+        .arg("--script")
+        .arg(&path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .kill_on_drop(true)
+        .output();
+    let human = timeout(TIMEOUT, human).await.expect("the human's client hung").unwrap();
+    let bot = timeout(TIMEOUT, bot.wait_with_output()).await.expect("the bot hung").unwrap();
+    std::fs::remove_file(path).ok();
+
+    assert!(bot.status.success());
+    (String::from_utf8_lossy(&human.stdout).into_owned(), String::from_utf8_lossy(&bot.stdout).into_owned())
+}
+
+/// `(owner, x, y, radius)` for each piece on the last board the human saw.
+fn last_board(out: &str) -> Vec<(u8, f32, f32, f32)> {
+    let board = out.rsplit("Board:").next().unwrap();
+    board
+        .lines()
+        .filter_map(|l| {
+            let (_, rest) = l.trim().strip_prefix('#')?.split_once(" P")?;
+            let (owner, rest) = rest.split_once("pos=(")?;
+            let (pos, radius) = rest.split_once(")  radius=")?;
+            let (x, y) = pos.split_once(',')?;
+            Some((owner.trim().parse().ok()?, x.trim().parse().ok()?, y.trim().parse().ok()?, radius.trim().parse().ok()?))
+        })
+        .collect()
+}
+
+fn human_id(out: &str) -> u8 {
+    let rest = out.split("You are Player ").nth(1).expect(out);
+    rest[..1].parse().unwrap()
+}
+
+#[tokio::test]
+async fn random_bot_plays_until_the_human_leaves() {
+    let (human, bot) = play("random", 3).await;
+    // A board after each of the human's three moves and at least the two
+    // bot moves between them.
+    assert!(human.matches("Board:").count() >= 5, "{human}");
+    let me = human_id(&human);
+    let board = last_board(&human);
+    assert!(board.iter().any(|p| p.0 == me) && board.iter().any(|p| p.0 != me), "{human}");
+    assert!(bot.contains("Game on!"), "{bot}");
+    assert!(bot.contains("A player disconnected."), "{bot}");
+}
+
+#[tokio::test]
+async fn aggressive_bot_crowds_the_human() {
+    let (human, bot) = play("aggressive", 3).await;
+    let me = human_id(&human);
+    let board = last_board(&human);
+    let (mine, theirs): (Vec<_>, Vec<_>) = board.into_iter().partition(|p| p.0 == me);
+    assert_eq!(mine.len(), 3, "{human}");
+    assert!(theirs.len() >= 2, "{human}");
+    // With room to spare it places every piece right up against one of
+    // the human's, bar an opening piece in the middle if it moved first.
+    let apart: Vec<_> = theirs
+        .iter()
+        .filter(|b| {
+            !mine.iter().any(|h| {
+                let gap = (b.1 - h.1).hypot(b.2 - h.2) - b.3 - h.3;
+                (0.0..1.0).contains(&gap)
+            })
+        })
+        .collect();
+    assert!(apart.iter().all(|b| (b.1, b.2) == (250.0, 250.0)), "{apart:?} not against any of {mine:?}\n{human}");
+    assert!(bot.contains("A player disconnected."), "{bot}");
+}
@@ -0,0 +1,535 @@
+//! The client binary driven by a script against a scripted server: each test
+//! plays the server's side of the wire by hand and checks what the player
+//! would see.  One game pits two scripted clients against a real server.
+//! Connecting itself is tested on `GameClient` directly, typed
+//! shortcuts on `expand_alias`, and the local overlap check against
+//! `GameState`.
+
+use seb_mul_game::board::BoardState;
+use seb_mul_game::client::{expand_alias, GameClient};
+use seb_mul_game::protocol::ClientCmd;
+use seb_mul_game::logger::Logger;
+use seb_mul_game::rng::Rng;
+use seb_mul_game::server::{self, ServerConfig};
+use seb_mul_game::state::{GameState, PhaseMode, Refusal, Rules};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::process::{Child, Command};
+use tokio::time::timeout;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Write `commands` to a script file unique to this test.
+fn script(test: &str, commands: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("tilez-client-{test}-{}.txt", std::process::id()));
+    std::fs::write(&path, commands).unwrap();
+    path
+}
+
+/// Run the client on `script` against the server at `addr`.
+fn spawn_client(addr: SocketAddr, script: &PathBuf, flags: &[&str]) -> Child {
+    Command::new(env!("CARGO_BIN_EXE_client"))
+        .arg(addr.to_string())
+        .arg("--script")
+        .arg(script)
+        .args(flags)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .unwrap()
+}
+
+/// Start the client on `script` and accept its connection.
+async fn start_client(script: &PathBuf, flags: &[&str]) -> (Child, TcpStream) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let child = spawn_client(listener.local_addr().unwrap(), script, flags);
+    let (stream, _) = timeout(TIMEOUT, listener.accept()).await.unwrap().unwrap();
+    (child, stream)
+}
+
+/// Send `opening`, which starts with READY, and check the client acks it.
+async fn open(writer: &mut OwnedWriteHalf, lines: &mut Lines<BufReader<OwnedReadHalf>>, opening: &[u8]) {
+    writer.write_all(opening).await.unwrap();
+    let ack = timeout(TIMEOUT, lines.next_line()).await.unwrap().unwrap().unwrap();
+    assert_eq!(ack, "READY_ACK");
+}
+
+/// Everything the client printed, once it has exited.
+async fn output(mut child: Child) -> String {
+    let mut out = String::new();
+    child.stdout.take().unwrap().read_to_string(&mut out).await.unwrap();
+    timeout(TIMEOUT, child.wait()).await.unwrap().unwrap();
+    out
+}
+
+#[tokio::test]
+async fn move_is_reported_once_its_state_arrives() {
+    let path = script("state", "place 100 100 10\n");
+    let (child, stream) = start_client(&path, &[]).await;
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    open(&mut writer, &mut lines, b"READY 0 2\nYOUR_TURN\n").await;
+    let sent = timeout(TIMEOUT, lines.next_line()).await.unwrap().unwrap().unwrap();
+    assert_eq!(sent, "PLACE 100.000 100.000 10.000 #1");
+    writer.write_all(b"OK #1\nSTATE 1 1 1 0 100 100 10\nGAME_OVER WIN 0\n").await.unwrap();
+
+    let out = output(child).await;
+    let accepted = out.find("Accepted 'PLACE 100.000 100.000 10.000'.").expect(&out);
+    assert!(accepted < out.find("Board:").unwrap(), "{out}");
+    assert!(!out.contains("sent no board"), "{out}");
+    std::fs::remove_file(path).ok();
+}
+
+#[tokio::test]
+async fn winning_move_ends_the_session_without_a_prompt() {
+    let path = script("winning", "place 100 100 10\nplace 200 200 10\n");
+    let (child, stream) = start_client(&path, &[]).await;
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    open(&mut writer, &mut lines, b"READY 0 2\nYOUR_TURN\n").await;
+    let sent = timeout(TIMEOUT, lines.next_line()).await.unwrap().unwrap().unwrap();
+    assert_eq!(sent, "PLACE 100.000 100.000 10.000 #1");
+    writer.write_all(b"OK #1\nSTATE 1 1 1 0 100 100 10\nGAME_OVER WIN 0\n").await.unwrap();
+
+    let out = output(child).await;
+    let over = out.find("Game over — Player 0 wins.").expect(&out);
+    assert!(out.find("Board:").unwrap() < over, "{out}");
+    assert!(!out[over..].contains("P0>"), "{out}");
+    // The rest of the script is never sent.
+    assert_eq!(timeout(TIMEOUT, lines.next_line()).await.unwrap().unwrap(), None);
+    std::fs::remove_file(path).ok();
+}
+
+#[tokio::test]
+async fn an_unanswered_command_gives_the_prompt_back() {
+    let path = script("unanswered", "place 100 100 10\nplace 200 200 10\n");
+    let (child, stream) = start_client(&path, &["--reply-timeout", "1"]).await;
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    // The server swallows the first move and says something unreadable.
+    open(&mut writer, &mut lines, b"READY 0 2\nYOUR_TURN\n").await;
+    let first = timeout(TIMEOUT, lines.next_line()).await.unwrap().unwrap().unwrap();
+    assert_eq!(first, "PLACE 100.000 100.000 10.000 #1");
+    writer.write_all(b"BOGUS\n").await.unwrap();
+
+    // The client stops waiting and moves on to the next line of its script.
+    let second = timeout(TIMEOUT, lines.next_line()).await.unwrap().unwrap().unwrap();
+    assert_eq!(second, "PLACE 200.000 200.000 10.000 #2");
+    writer.write_all(b"GAME_OVER WIN 0\n").await.unwrap();
+
+    let out = output(child).await;
+    assert!(out.contains("The server has not answered; you can type again."), "{out}");
+    std::fs::remove_file(path).ok();
+}
+
+#[tokio::test]
+async fn rejections_are_explained_by_their_code() {
+    let path = script("codes", "place 100 100 10\nplace 200 200 10\n");
+    let (child, stream) = start_client(&path, &[]).await;
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    open(&mut writer, &mut lines, b"READY 0 2\nYOUR_TURN\n").await;
+    timeout(TIMEOUT, lines.next_line()).await.unwrap().unwrap().unwrap();
+    // The advice follows the code, whatever the reason says.
+    writer.write_all(b"ERROR #1 OVERLAP reworded for people\n").await.unwrap();
+    let sent = timeout(TIMEOUT, lines.next_line()).await.unwrap().unwrap().unwrap();
+    assert_eq!(sent, "PLACE 200.000 200.000 10.000 #2");
+    writer.write_all(b"OK #2\nSTATE 1 1 1 0 200 200 10\nGAME_OVER WIN 0\n").await.unwrap();
+
+    let out = output(child).await;
+    assert!(out.contains("Rejected 'PLACE 100.000 100.000 10.000': reworded for people"), "{out}");
+    assert!(out.contains("Pick a spot clear of the other pieces"), "{out}");
+    std::fs::remove_file(path).ok();
+}
+
+#[tokio::test]
+async fn missing_state_warns_and_asks_for_a_resync() {
+    let path = script("missing", "place 100 100 10\n");
+    let (child, stream) = start_client(&path, &[]).await;
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    open(&mut writer, &mut lines, b"READY 0 2\nYOUR_TURN\n").await;
+    timeout(TIMEOUT, lines.next_line()).await.unwrap().unwrap().unwrap();
+    writer.write_all(b"OK #1\n").await.unwrap();
+
+    // No STATE follows, so the client gives up waiting and resyncs.
+    let resync = timeout(TIMEOUT, lines.next_line()).await.unwrap().unwrap().unwrap();
+    assert_eq!(resync, "RESYNC");
+    writer.write_all(b"STATE 1 1 1 0 100 100 10\nGAME_OVER WIN 0\n").await.unwrap();
+
+    let out = output(child).await;
+    assert!(out.contains("The server accepted 'PLACE 100.000 100.000 10.000' but sent no board"), "{out}");
+    assert!(out.contains("Board:"), "{out}");
+    std::fs::remove_file(path).ok();
+}
+
+#[tokio::test]
+async fn stale_state_is_dropped_and_resynced() {
+    let path = script("stale", "place 100 100 10\n");
+    let (child, stream) = start_client(&path, &[]).await;
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    // Frame 2 arrives before frame 1; the late one must not be drawn.
+    open(&mut writer, &mut lines, b"READY 0 2\n").await;
+    writer.write_all(b"STATE 2 2 2 0 100 100 10 1 300 300 10\nSTATE 1 1 1 0 100 100 10\n").await.unwrap();
+    let resync = timeout(TIMEOUT, lines.next_line()).await.unwrap().unwrap().unwrap();
+    assert_eq!(resync, "RESYNC");
+    writer.write_all(b"STATE 2 2 2 0 100 100 10 1 300 300 10\nGAME_OVER WIN 0\n").await.unwrap();
+
+    let out = output(child).await;
+    // Only the two copies of frame 2 are drawn.
+    assert_eq!(out.matches("Board:").count(), 2, "{out}");
+    std::fs::remove_file(path).ok();
+}
+
+#[tokio::test]
+async fn name_flag_is_sent_on_connecting() {
+    let path = script("name", "place 100 100 10\n");
+    let (child, stream) = start_client(&path, &["--name", "bob"]).await;
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    // Sent before READY, while the game is still gathering players.
+    let sent = timeout(TIMEOUT, lines.next_line()).await.unwrap().unwrap().unwrap();
+    assert_eq!(sent, "NAME bob #1");
+    writer.write_all(b"OK #1\nGAME_OVER DRAW\n").await.unwrap();
+    output(child).await;
+    std::fs::remove_file(path).ok();
+}
+
+#[tokio::test]
+async fn bad_name_flag_is_refused_before_connecting() {
+    let status = Command::new(env!("CARGO_BIN_EXE_client"))
+        .args(["127.0.0.1:9", "--name", "no spaces allowed"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .await
+        .unwrap();
+    assert!(!status.success());
+}
+
+#[tokio::test]
+async fn slash_commands_are_routed_before_gameplay_commands() {
+    let path = script("slash", "/chat good luck\n/name alice\nchat hi\n/place 1 2 3\n/name\nplace 100 100 10\n");
+    let (child, stream) = start_client(&path, &[]).await;
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    open(&mut writer, &mut lines, b"READY 0 2\nYOUR_TURN\n").await;
+    let mut sent = Vec::new();
+    for _ in 0..3 {
+        sent.push(timeout(TIMEOUT, lines.next_line()).await.unwrap().unwrap().unwrap());
+    }
+    // Only the well-formed lines reach the server, each in its own form.
+    assert_eq!(sent, ["CHAT good luck #1", "NAME alice #2", "PLACE 100.000 100.000 10.000 #3"]);
+    writer.write_all(b"OK #1\nOK #2\nCHAT 1 you too\nOK #3\nSTATE 1 1 1 0 100 100 10\nGAME_OVER WIN 0\n").await.unwrap();
+
+    let out = output(child).await;
+    assert!(out.contains("unknown command 'CHAT'"), "{out}");
+    assert!(out.contains("unknown command '/place'"), "{out}");
+    assert!(out.contains("missing name"), "{out}");
+    assert!(out.contains("P1: you too"), "{out}");
+    std::fs::remove_file(path).ok();
+}
+
+#[tokio::test]
+async fn slash_commands_work_out_of_turn() {
+    let path = script("slash-turn", "place 100 100 10\n/chat hello\n");
+    let (child, stream) = start_client(&path, &[]).await;
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    // A script only runs while input is wanted, so open with a draw offer
+    // from the player on turn.
+    open(&mut writer, &mut lines, b"READY 1 2\nOPPONENT_TURN\nDRAW_OFFERED\n").await;
+    let sent = timeout(TIMEOUT, lines.next_line()).await.unwrap().unwrap().unwrap();
+    assert_eq!(sent, "CHAT hello #1");
+    writer.write_all(b"OK #1\nGAME_OVER DRAW\n").await.unwrap();
+
+    let out = output(child).await;
+    assert!(out.contains("not your turn"), "{out}");
+    std::fs::remove_file(path).ok();
+}
+
+#[tokio::test]
+async fn bad_piece_indices_are_refused_without_asking_the_server() {
+    let path = script(
+        "index",
+        "shoot -1 1 0 10\nshoot two 1 0 10\nshoot 18446744073709551615 1 0 10\naim 1 0 0 10\nshoot 0 1 0 10\n",
+    );
+    let (child, stream) = start_client(&path, &[]).await;
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    open(&mut writer, &mut lines, b"READY 0 2\nSTATE 1 1 1 0 100 100 10\nYOUR_TURN\n").await;
+    // Only the shot at a real piece goes out.
+    let sent = timeout(TIMEOUT, lines.next_line()).await.unwrap().unwrap().unwrap();
+    assert_eq!(sent, "SHOOT 0 1.000 0.000 10.000 #1");
+    writer.write_all(b"OK #1\nSTATE 2 2 1 0 110 100 10\nGAME_OVER WIN 0\n").await.unwrap();
+
+    let out = output(child).await;
+    assert!(out.contains("piece index cannot be negative"), "{out}");
+    assert!(out.contains("piece index must be a whole number"), "{out}");
+    assert!(out.contains("there is no piece #18446744073709551615; pieces are numbered 0 to 0"), "{out}");
+    assert!(out.contains("there is no piece #1;"), "{out}");
+    std::fs::remove_file(path).ok();
+}
+
+#[tokio::test]
+async fn shooting_before_any_piece_is_placed_is_refused() {
+    let path = script("no-pieces", "shoot 0 1 0 10\nplace 100 100 10\n");
+    let (child, stream) = start_client(&path, &[]).await;
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    open(&mut writer, &mut lines, b"READY 0 2\nYOUR_TURN\n").await;
+    let sent = timeout(TIMEOUT, lines.next_line()).await.unwrap().unwrap().unwrap();
+    assert_eq!(sent, "PLACE 100.000 100.000 10.000 #1");
+    writer.write_all(b"OK #1\nSTATE 1 1 1 0 100 100 10\nGAME_OVER WIN 0\n").await.unwrap();
+
+    let out = output(child).await;
+    assert!(out.contains("there are no pieces on the board yet"), "{out}");
+    std::fs::remove_file(path).ok();
+}
+
+#[tokio::test]
+async fn aim_shoots_from_the_piece_toward_the_target() {
+    let path = script("aim", "aim 0 100 100 10\naim 1 0 0 10\naim 0 40 180 50\naim 0 70 0 5\n");
+    let (child, stream) = start_client(&path, &[]).await;
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    open(&mut writer, &mut lines, b"READY 0 2\nSTATE 2 2 2 0 100 100 10 1 300 300 10\nYOUR_TURN\n").await;
+    let sent = timeout(TIMEOUT, lines.next_line()).await.unwrap().unwrap().unwrap();
+    assert_eq!(sent, "SHOOT 0 -60.000 80.000 50.000 #1");
+
+    // The next aim starts from wherever the last STATE left the piece.
+    writer.write_all(b"OK #1\nSTATE 3 3 2 0 70 140 10 1 300 300 10\nYOUR_TURN\n").await.unwrap();
+    let sent = timeout(TIMEOUT, lines.next_line()).await.unwrap().unwrap().unwrap();
+    assert_eq!(sent, "SHOOT 0 0.000 -140.000 5.000 #2");
+    writer.write_all(b"OK #2\nSTATE 4 4 2 0 70 135 10 1 300 300 10\nGAME_OVER WIN 0\n").await.unwrap();
+
+    let out = output(child).await;
+    assert!(out.contains("the target is the piece's own position"), "{out}");
+    assert!(out.contains("piece #1 is not yours"), "{out}");
+    std::fs::remove_file(path).ok();
+}
+
+#[tokio::test]
+async fn overlapping_and_off_board_placements_are_refused_locally() {
+    let path = script("overlap", "place 105 100 10\nplace 5 300 10\nplace 300 -1 0.5\nplace 120 100 10\n");
+    let (child, stream) = start_client(&path, &[]).await;
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    open(&mut writer, &mut lines, b"READY 0 2\nSTATE 1 1 1 1 100 100 10\nYOUR_TURN\n").await;
+    // Touching the piece is allowed; only that placement reaches the server.
+    let sent = timeout(TIMEOUT, lines.next_line()).await.unwrap().unwrap().unwrap();
+    assert_eq!(sent, "PLACE 120.000 100.000 10.000 #1");
+    writer.write_all(b"OK #1\nSTATE 2 2 2 1 100 100 10 0 120 100 10\nGAME_OVER WIN 0\n").await.unwrap();
+
+    let out = output(child).await;
+    assert!(out.contains("? overlaps an existing piece"), "{out}");
+    assert_eq!(out.matches("? piece must lie within the board").count(), 2, "{out}");
+    std::fs::remove_file(path).ok();
+}
+
+#[test]
+fn the_local_overlap_check_agrees_with_the_server() {
+    let mut state = GameState::with_seed(3);
+    let mut rng = Rng::new(3);
+    let mut coord = move || rng.next_f32() * 500.0;
+    for _ in 0..400 {
+        let (x, y, r) = (coord(), coord(), coord() / 10.0 + 1.0);
+        let local = BoardState::from(&state).overlaps(x, y, r);
+        let turn = state.turn();
+        match state.place(turn, x, y, r) {
+            Ok(()) => assert!(!local, "({x}, {y}) r {r}"),
+            Err(Refusal::Overlap) => assert!(local, "({x}, {y}) r {r}"),
+            // Refused for another reason first, e.g. off the board.
+            Err(_) => {}
+        }
+    }
+    assert!(state.pieces().len() > 20);
+}
+
+#[tokio::test]
+async fn moves_outside_the_announced_rules_are_refused() {
+    let path = script(
+        "rules",
+        "place 100 100 25\nplace 290 100 15\nplace 100 100 0.5\nplace 100 100 10\nshoot 0 1 0 150\nshoot 0 1 0 10\n",
+    );
+    let (child, stream) = start_client(&path, &[]).await;
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    open(&mut writer, &mut lines, b"READY 0 2\nRULES 300.000 1.000 20.000 100.000 - -\nYOUR_TURN\n").await;
+    let sent = timeout(TIMEOUT, lines.next_line()).await.unwrap().unwrap().unwrap();
+    assert_eq!(sent, "PLACE 100.000 100.000 10.000 #1");
+    writer.write_all(b"OK #1\nSTATE 1 1 1 0 100 100 10\nYOUR_TURN\n").await.unwrap();
+    let sent = timeout(TIMEOUT, lines.next_line()).await.unwrap().unwrap().unwrap();
+    assert_eq!(sent, "SHOOT 0 1.000 0.000 10.000 #2");
+    writer.write_all(b"OK #2\nSTATE 2 2 1 0 110 100 10\nGAME_OVER WIN 0\n").await.unwrap();
+
+    let out = output(child).await;
+    assert!(out.contains("Rules: 300×300 board, radius 1 to 20, force up to 100."), "{out}");
+    assert!(out.contains("radius may be at most 20"), "{out}");
+    assert!(out.contains("piece must lie within the 300×300 board"), "{out}");
+    assert!(out.contains("radius must be at least 1"), "{out}");
+    assert!(out.contains("force may be at most 100"), "{out}");
+    std::fs::remove_file(path).ok();
+}
+
+#[tokio::test]
+async fn two_scripted_clients_play_a_game_through_a_real_server() {
+    // Room for four pieces, and three each to place: whoever moves first is
+    // the one left stuck with the board full.
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let rules = Rules {
+        board_size: 40.0,
+        min_radius: 10.0,
+        max_radius: 10.0,
+        phase_mode: PhaseMode::PlacementThenShoot,
+        ..Rules::default()
+    };
+    tokio::spawn(server::serve(listener, ServerConfig { rules, ..ServerConfig::default() }, Arc::new(Logger::new(0))));
+
+    // The same corners for both; a corner already taken is refused locally
+    // and the script moves on to the next.
+    let path = script("two-clients", "place 10 10 10\nplace 30 10 10\nplace 10 30 10\nplace 30 30 10\n");
+    let (a, b) = (spawn_client(addr, &path, &[]), spawn_client(addr, &path, &[]));
+    let (a, b) = (output(a).await, output(b).await);
+
+    let over = |out: &str| out.lines().find(|l| l.starts_with("Game over")).map(str::to_string);
+    let result = over(&a).expect(&a);
+    assert_eq!(over(&b).as_deref(), Some(result.as_str()), "{b}");
+    for out in [&a, &b] {
+        assert_eq!(out.matches("\nAccepted 'PLACE").count(), 2, "{out}");
+    }
+    assert_eq!(a.matches("? overlaps an existing piece").count() + b.matches("? overlaps an existing piece").count(), 3);
+    // The second to move wins: they had the last free corner.
+    let second = if a.contains("Accepted 'PLACE 30.000 30.000") { &a } else { &b };
+    let id = second.split("You are Player ").nth(1).and_then(|s| s.split('.').next()).expect(second);
+    assert_eq!(result, format!("Game over — Player {id} wins."));
+    std::fs::remove_file(path).ok();
+}
+
+#[test]
+fn shortcuts_parse_like_the_full_words() {
+    let parse = |line: &str| expand_alias(line).and_then(|l| ClientCmd::parse_input(&l));
+    assert_eq!(parse("p 1 2 3"), ClientCmd::parse_input("place 1 2 3"));
+    assert_eq!(parse("P 1 2 3"), ClientCmd::parse_input("place 1 2 3"));
+    assert_eq!(parse("s 0 1 0 5"), ClientCmd::parse_input("shoot 0 1 0 5"));
+    assert_eq!(expand_alias("a 0 10 20 5").unwrap(), "aim 0 10 20 5");
+    // Full words, and mistakes in the arguments, are left to the parser.
+    assert_eq!(parse("place 1 2 3"), ClientCmd::parse_input("place 1 2 3"));
+    assert_eq!(parse("p 1 2"), ClientCmd::parse_input("place 1 2"));
+
+    assert_eq!(expand_alias("d"), Err("'d' could be draw or decline; type the command in full".into()));
+    assert_eq!(expand_alias("u"), Err("'u' is not a shortcut; type 'undo' in full".into()));
+    assert_eq!(
+        expand_alias("x 1 2"),
+        Err("unknown command 'x'; the shortcuts are p (place), s (shoot) and a (aim)".into())
+    );
+}
+
+#[tokio::test]
+async fn shortcuts_are_sent_as_the_full_command() {
+    let path = script("shortcuts", "x 1 2\np 100 100 10\n");
+    let (child, stream) = start_client(&path, &[]).await;
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    open(&mut writer, &mut lines, b"READY 0 2\nYOUR_TURN\n").await;
+    let sent = timeout(TIMEOUT, lines.next_line()).await.unwrap().unwrap().unwrap();
+    assert_eq!(sent, "PLACE 100.000 100.000 10.000 #1");
+    writer.write_all(b"OK #1\nSTATE 1 1 1 0 100 100 10\nGAME_OVER WIN 0\n").await.unwrap();
+
+    let out = output(child).await;
+    assert!(out.contains("unknown command 'x'"), "{out}");
+    std::fs::remove_file(path).ok();
+}
+
+#[tokio::test]
+async fn opponents_clock_counts_down_from_the_deadline() {
+    let path = script("deadline", "place 100 100 10\n");
+    let (child, mut stream) = start_client(&path, &[]).await;
+
+    stream.write_all(b"READY 1 2\nOPPONENT_TURN\nTURN_DEADLINE 2\n").await.unwrap();
+    tokio::time::sleep(Duration::from_millis(2500)).await;
+    stream.write_all(b"GAME_OVER WIN 0\n").await.unwrap();
+
+    let out = output(child).await;
+    let two = out.find("2s left").expect(&out);
+    let one = out.find("1s left").expect(&out);
+    let up = out.find("waiting for timeout…").expect(&out);
+    assert!(two < one && one < up, "{out}");
+    assert!(!out.contains("0s left"), "{out}");
+    std::fs::remove_file(path).ok();
+}
+
+#[tokio::test]
+async fn unreachable_addresses_fall_through_to_the_next() {
+    // A port nobody is listening on any more.
+    let refused = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let working = listener.local_addr().unwrap();
+
+    let (_client, addr) = GameClient::connect_any(&[refused, working], TIMEOUT).await.unwrap();
+    assert_eq!(addr, working);
+    timeout(TIMEOUT, listener.accept()).await.unwrap().unwrap();
+
+    let e = GameClient::connect_any(&[refused], TIMEOUT).await.err().unwrap();
+    assert!(e.to_string().contains(&refused.to_string()), "{e}");
+}
+
+#[tokio::test]
+async fn connecting_to_a_black_hole_times_out() {
+    // A listener with a full accept queue ignores further connection
+    // attempts, as an unreachable host would.
+    let socket = socket2::Socket::new(socket2::Domain::IPV4, socket2::Type::STREAM, None).unwrap();
+    socket.bind(&"127.0.0.1:0".parse::<std::net::SocketAddr>().unwrap().into()).unwrap();
+    socket.listen(0).unwrap();
+    let addr = socket.local_addr().unwrap().as_socket().unwrap();
+    let mut queued = Vec::new();
+    for _ in 0..2 {
+        if let Ok(Ok(stream)) = timeout(Duration::from_millis(200), TcpStream::connect(addr)).await {
+            queued.push(stream);
+        }
+    }
+
+    let started = std::time::Instant::now();
+    let out = timeout(
+        TIMEOUT,
+        Command::new(env!("CARGO_BIN_EXE_client"))
+            .arg(addr.to_string())
+            .args(["--connect-timeout", "1"])
+            .stdin(Stdio::null())
+            .kill_on_drop(true)
+            .output(),
+    )
+    .await
+    .expect("the client kept waiting")
+    .unwrap();
+
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(!out.status.success());
+    assert!(stderr.contains("connection timed out"), "{stderr}");
+    assert!(started.elapsed() >= Duration::from_secs(1));
+}
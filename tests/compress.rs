@@ -0,0 +1,74 @@
+//! The deflated server stream: lines survive the round trip however the
+//! bytes are split up, and large boards shrink.
+
+use seb_mul_game::board::{BoardState, Piece};
+use seb_mul_game::compress::{InflateReader, LineDeflater};
+use seb_mul_game::protocol::ServerMsg;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+/// A board of `n` pieces scattered the way a long game leaves them.
+fn scattered(n: usize) -> Vec<Piece> {
+    let mut seed = 0x9e37_79b9_7f4a_7c15_u64;
+    let mut next = move |scale: f32| {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        (seed >> 40) as f32 / (1u64 << 24) as f32 * scale
+    };
+    (0..n)
+        .map(|index| Piece {
+            index,
+            owner:  (index % 2) as u8,
+            x:      next(1000.0),
+            y:      next(700.0),
+            radius: 5.0 + next(15.0),
+        })
+        .collect()
+}
+
+fn state_line(seq: u64, pieces: &[Piece]) -> String {
+    ServerMsg::State(BoardState { seq, tick: seq * 40, pieces: pieces.to_vec() }).to_wire()
+}
+
+#[tokio::test]
+async fn lines_survive_any_split() {
+    let pieces = scattered(50);
+    let lines: Vec<String> = (1..=20).map(|seq| state_line(seq, &pieces)).collect();
+    let mut z = LineDeflater::new();
+    let bytes: Vec<u8> = lines.iter().flat_map(|line| z.compress(line.as_bytes())).collect();
+
+    // Some bytes were already buffered before the switch, and the rest
+    // trickles in a few at a time.
+    let (head, tail) = bytes.split_at(10);
+    let (mut tx, rx) = tokio::io::duplex(64);
+    let tail = tail.to_vec();
+    tokio::spawn(async move {
+        for chunk in tail.chunks(7) {
+            tx.write_all(chunk).await.unwrap();
+        }
+    });
+    let mut read = BufReader::new(InflateReader::new(rx, head.to_vec())).lines();
+    for line in &lines {
+        assert_eq!(read.next_line().await.unwrap().unwrap(), line.trim_end());
+    }
+    assert_eq!(read.next_line().await.unwrap(), None);
+}
+
+#[test]
+fn large_boards_shrink() {
+    let mut pieces = scattered(200);
+    let first = state_line(1, &pieces);
+    // A shot moves a handful of pieces; the rest of the board repeats.
+    for p in pieces.iter_mut().step_by(40) {
+        p.x += 12.5;
+        p.y -= 3.25;
+    }
+    let second = state_line(2, &pieces);
+
+    let mut z = LineDeflater::new();
+    let first_z = z.compress(first.as_bytes()).len();
+    let second_z = z.compress(second.as_bytes()).len();
+    // About 4.9 KB down to 2.4 KB, then down to about 0.1 KB.
+    assert!(first_z * 3 < first.len() * 2, "{first_z} of {}", first.len());
+    assert!(second_z * 10 < second.len(), "{second_z} of {}", second.len());
+}
@@ -0,0 +1,100 @@
+//! The server's `--config` file: command-line options win over the file, and
+//! the file wins over the defaults.
+
+use clap::{CommandFactory, FromArgMatches};
+use seb_mul_game::server::{ServerArgs, ServerConfig};
+use std::path::PathBuf;
+
+/// Write `contents` to a config file unique to this test.
+fn config_file(test: &str, contents: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("tilez-config-{test}-{}.toml", std::process::id()));
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+/// Parse a server command line the way the binary does.
+fn load(args: &[&str]) -> Result<ServerConfig, String> {
+    let matches = ServerArgs::command().get_matches_from(std::iter::once("server").chain(args.iter().copied()));
+    let args = ServerArgs::from_arg_matches(&matches).unwrap();
+    args.with_config_file(&matches).map(|args| args.config())
+}
+
+#[test]
+fn command_line_overrides_file_overrides_defaults() {
+    let path = config_file("precedence", "max_games = 4\nseed = 99\nelo_k = 16.0\n");
+    let config = load(&["--config", path.to_str().unwrap(), "--max-games", "8"]).unwrap();
+
+    assert_eq!(config.max_games, 8, "the command line wins");
+    assert_eq!(config.seed, 99, "the file fills what the command line left out");
+    assert_eq!(config.elo_k, 16.0);
+    assert_eq!(config.send_queue, ServerConfig::default().send_queue, "defaults fill the rest");
+    std::fs::remove_file(path).ok();
+}
+
+#[test]
+fn file_sets_rules_and_timeouts() {
+    let path = config_file(
+        "rules",
+        "players = 4\nteams = 2\nphase_mode = \"placement-then-shoot\"\nstalemate = \"draw\"\nflush_timeout = 1\n",
+    );
+    let config = load(&["--config", path.to_str().unwrap()]).unwrap();
+
+    assert_eq!((config.rules.players, config.rules.teams), (4, Some(2)));
+    assert_eq!(config.flush_timeout.as_secs(), 1);
+    std::fs::remove_file(path).ok();
+}
+
+#[test]
+fn malformed_file_is_reported() {
+    let path = config_file("malformed", "max_games = \n");
+    let err = load(&["--config", path.to_str().unwrap()]).unwrap_err();
+    assert!(err.contains("line 1"), "{err}");
+    std::fs::remove_file(path).ok();
+}
+
+#[test]
+fn unknown_keys_and_bad_types_are_reported() {
+    for (test, contents, expected) in [
+        ("unknown", "max_games = 4\nmax_game = 4\n", "unknown field `max_game`"),
+        ("type", "seed = 1\nmax_games = \"eight\"\n", "max_games: invalid type: string \"eight\""),
+        ("range", "players = 1\n", "players must be at least 2"),
+        ("board", "board_size = 0.0\n", "board_size must be a positive number"),
+        ("radius", "max_radius = -5.0\n", "max_radius must be a positive number"),
+        ("below", "max_radius = 0.5\n", "max_radius is below the smallest radius a piece may have"),
+        ("force", "max_force = -1.0\n", "max_force must be a positive number"),
+        ("conns", "max_conns_per_ip = 0\n", "max_conns_per_ip must be at least 1"),
+    ] {
+        let path = config_file(test, contents);
+        let err = load(&["--config", path.to_str().unwrap()]).unwrap_err();
+        assert!(err.starts_with(&format!("{}: ", path.display())), "{err}");
+        assert!(err.contains(expected), "{err}");
+        std::fs::remove_file(path).ok();
+    }
+}
+
+#[test]
+fn sizes_and_limits_must_be_positive_on_the_command_line() {
+    for arg in ["--board-size=0", "--max-radius=-5", "--max-force=-1", "--max-force=inf", "--max-force=nan"] {
+        assert!(ServerArgs::command().try_get_matches_from(["server", arg]).is_err(), "{arg}");
+    }
+    assert!(ServerArgs::command().try_get_matches_from(["server", "--max-force=0.5"]).is_ok());
+}
+
+#[test]
+fn the_radius_cap_cannot_fall_below_the_minimum_radius() {
+    assert!(ServerArgs::command().try_get_matches_from(["server", "--max-radius=0.5"]).is_err());
+    assert!(ServerArgs::command().try_get_matches_from(["server", "--max-radius=1"]).is_ok());
+}
+
+#[test]
+fn a_per_ip_cap_of_zero_is_refused() {
+    assert!(ServerArgs::command().try_get_matches_from(["server", "--max-conns-per-ip=0"]).is_err());
+    let config = load(&["--max-conns-per-ip", "1"]).unwrap();
+    assert_eq!(config.max_conns_per_ip, Some(1));
+}
+
+#[test]
+fn missing_file_is_reported() {
+    let err = load(&["--config", "/nonexistent/tilez.toml"]).unwrap_err();
+    assert!(err.starts_with("cannot read /nonexistent/tilez.toml"), "{err}");
+}
@@ -0,0 +1,1025 @@
+//! End-to-end protocol tests: a real server on an ephemeral port and raw TCP
+//! clients speaking the wire protocol line by line.
+
+use clap::Parser;
+use seb_mul_game::board::BoardState;
+use seb_mul_game::client::GameClient;
+use seb_mul_game::logger::{CaptureHandle, Level, Logger};
+use seb_mul_game::protocol::{ClientCmd, ServerMsg};
+use seb_mul_game::server::{self, ServerArgs, ServerConfig};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{TcpListener, TcpSocket, TcpStream};
+use tokio::time::timeout;
+
+/// How long to wait for any single server line before failing the test.
+const LINE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Start a server with the given extra flags and return its bound address.
+async fn start_server(flags: &[&str]) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let args = ServerArgs::parse_from(std::iter::once("server").chain(flags.iter().copied()));
+    tokio::spawn(server::serve(listener, args.config(), Arc::new(args.logger())));
+    addr
+}
+
+/// Embed a server with the default config and its log captured for
+/// inspection.
+async fn start_logged_server() -> (SocketAddr, CaptureHandle) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (log, capture) = Logger::capturing();
+    tokio::spawn(server::serve(listener, ServerConfig::default(), Arc::new(log)));
+    (addr, capture)
+}
+
+struct Player {
+    lines:  Lines<BufReader<OwnedReadHalf>>,
+    writer: OwnedWriteHalf,
+}
+
+impl Player {
+    async fn connect(addr: SocketAddr) -> Self {
+        let (reader, writer) = TcpStream::connect(addr).await.unwrap().into_split();
+        Self { lines: BufReader::new(reader).lines(), writer }
+    }
+
+    /// Connect with a receive buffer about as small as the OS allows, so
+    /// a player who stops reading backs up quickly.
+    async fn connect_slow(addr: SocketAddr) -> Self {
+        let socket = TcpSocket::new_v4().unwrap();
+        socket.set_recv_buffer_size(1024).unwrap();
+        let (reader, writer) = socket.connect(addr).await.unwrap().into_split();
+        Self { lines: BufReader::new(reader).lines(), writer }
+    }
+
+    async fn send(&mut self, line: &str) {
+        self.writer.write_all(format!("{line}\n").as_bytes()).await.unwrap();
+    }
+
+    async fn recv(&mut self) -> String {
+        timeout(LINE_TIMEOUT, self.lines.next_line())
+            .await
+            .expect("timed out waiting for the server")
+            .unwrap()
+            .expect("server closed the connection")
+    }
+
+    async fn expect(&mut self, want: &str) {
+        assert_eq!(self.recv().await, want);
+    }
+
+    /// The server has closed the connection without sending anything more.
+    async fn expect_closed(&mut self) {
+        let next = timeout(LINE_TIMEOUT, self.lines.next_line()).await.expect("timed out waiting for the server");
+        assert!(matches!(next, Ok(None)), "expected the connection to close, got {next:?}");
+    }
+
+    /// Nothing arrives for `wait`.
+    async fn expect_silence(&mut self, wait: Duration) {
+        if let Ok(line) = timeout(wait, self.lines.next_line()).await {
+            panic!("expected nothing yet, got {line:?}");
+        }
+    }
+
+    /// `READY` as given, then the `RULES` line that always follows it.
+    async fn expect_ready(&mut self, want: &str) -> String {
+        self.expect(want).await;
+        let rules = self.recv().await;
+        assert!(rules.starts_with("RULES "), "{rules}");
+        rules
+    }
+}
+
+/// Connect two players and consume the handshake.  Returns them as
+/// `(first, second)` in turn order, each with their player id.
+async fn start_game(addr: SocketAddr) -> ((Player, u8), (Player, u8)) {
+    let mut p0 = Player::connect(addr).await;
+    p0.expect("WAITING").await;
+    let mut p1 = Player::connect(addr).await;
+    p0.expect_ready("READY 0 2").await;
+    p1.expect_ready("READY 1 2").await;
+
+    match (p0.recv().await.as_str(), p1.recv().await.as_str()) {
+        ("YOUR_TURN", "OPPONENT_TURN") => ((p0, 0), (p1, 1)),
+        ("OPPONENT_TURN", "YOUR_TURN") => ((p1, 1), (p0, 0)),
+        other => panic!("unexpected turn announcement: {other:?}"),
+    }
+}
+
+/// Both players should see the same accepted-move broadcast.
+async fn expect_both(a: &mut Player, b: &mut Player, lines: &[&str]) {
+    for line in lines {
+        a.expect(line).await;
+        b.expect(line).await;
+    }
+}
+
+/// Seat `n` players in connection order, consuming the `WAITING` holds.
+async fn join_table(addr: SocketAddr, n: usize) -> Vec<Player> {
+    let mut players = Vec::new();
+    for id in 0..n {
+        let mut p = Player::connect(addr).await;
+        if id + 1 < n {
+            p.expect("WAITING").await;
+        }
+        players.push(p);
+    }
+    players
+}
+
+/// Wait until the server has logged `n` connections leaving the lobby.
+async fn left_lobby(capture: &CaptureHandle, n: usize) {
+    let deadline = tokio::time::Instant::now() + LINE_TIMEOUT;
+    while capture.events().iter().filter(|(_, e)| e.ends_with("left before the game started")).count() < n {
+        assert!(tokio::time::Instant::now() < deadline, "the server never noticed the hang-up");
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+}
+
+/// Consume the opening turn announcement and return who moves first.
+async fn first_turn(players: &mut [Player]) -> usize {
+    let mut first = None;
+    for (id, p) in players.iter_mut().enumerate() {
+        if p.recv().await == "YOUR_TURN" {
+            first = Some(id);
+        }
+    }
+    first.expect("nobody was given the first turn")
+}
+
+#[tokio::test]
+async fn place_shoot_and_draw() {
+    let addr = start_server(&[]).await;
+    let ((mut a, ia), (mut b, ib)) = start_game(addr).await;
+
+    // Moves out of turn are rejected without changing anything.
+    b.send("PLACE 100 100 10").await;
+    b.expect("ERROR NOT_YOUR_TURN not your turn").await;
+
+    a.send("PLACE 100 100 10").await;
+    expect_both(&mut a, &mut b, &["OK", &format!("STATE 1 1 1 {ia} 100.000 100.000 10.000")]).await;
+    a.expect("OPPONENT_TURN").await;
+    b.expect("YOUR_TURN").await;
+
+    // Overlaps and foreign pieces are refused; the turn stays with b.
+    b.send("PLACE 110 100 10").await;
+    b.expect("ERROR OVERLAP overlaps an existing piece").await;
+    b.send("SHOOT 0 1 0 50").await;
+    b.expect("ERROR NOT_YOUR_PIECE that piece does not belong to you").await;
+
+    b.send("PLACE 300 300 20").await;
+    expect_both(&mut a, &mut b, &[
+        "OK",
+        &format!("STATE 2 2 2 {ia} 100.000 100.000 10.000 {ib} 300.000 300.000 20.000"),
+    ]).await;
+    b.expect("OPPONENT_TURN").await;
+    a.expect("YOUR_TURN").await;
+
+    a.send("SHOOT 0 1 0 50").await;
+    expect_both(&mut a, &mut b, &[
+        "OK",
+        &format!("STATE 3 3 2 {ia} 150.000 100.000 10.000 {ib} 300.000 300.000 20.000"),
+    ]).await;
+    a.expect("OPPONENT_TURN").await;
+    b.expect("YOUR_TURN").await;
+
+    // Garbage is reported back to the sender only.
+    b.send("JUMP 1 2").await;
+    b.expect("ERROR BAD_COMMAND unrecognised command").await;
+
+    b.send("DRAW_OFFER").await;
+    a.expect("DRAW_OFFERED").await;
+    a.send("DRAW_ACCEPT").await;
+    expect_both(&mut a, &mut b, &["GAME_OVER DRAW"]).await;
+}
+
+/// Play the same three moves on a fresh server started with `--seed seed`
+/// and return everything the player who connected first was sent.
+async fn seeded_transcript(seed: &str) -> Vec<String> {
+    let addr = start_server(&["--seed", seed]).await;
+    let mut players = join_table(addr, 2).await;
+    let mut transcript = Vec::new();
+    for (id, p) in players.iter_mut().enumerate() {
+        transcript.push(p.expect_ready(&format!("READY {id} 2")).await);
+    }
+    let first = first_turn(&mut players).await;
+    for (mover, line) in [(first, "PLACE 100 100 10"), (1 - first, "PLACE 300 300 20"), (first, "SHOOT 0 1 0 50")] {
+        players[mover].send(line).await;
+        for _ in 0..3 {
+            transcript.push(players[0].recv().await);
+            players[1].recv().await;
+        }
+    }
+    transcript.push(format!("first {first}"));
+    transcript
+}
+
+#[tokio::test]
+async fn the_same_seed_plays_out_the_same_game() {
+    let mut firsts = Vec::new();
+    for seed in ["1", "2", "3", "4", "5", "6"] {
+        let once = seeded_transcript(seed).await;
+        assert_eq!(once, seeded_transcript(seed).await, "seed {seed}");
+        firsts.push(once.last().unwrap().clone());
+    }
+    // The seed is what decides the opening turn.
+    assert!(firsts.iter().any(|f| f != &firsts[0]), "{firsts:?}");
+}
+
+#[tokio::test]
+async fn shots_that_land_on_another_piece_are_refused() {
+    let addr = start_server(&[]).await;
+    let ((mut a, ia), (mut b, ib)) = start_game(addr).await;
+
+    a.send("PLACE 100 100 10").await;
+    expect_both(&mut a, &mut b, &["OK", &format!("STATE 1 1 1 {ia} 100.000 100.000 10.000")]).await;
+    a.expect("OPPONENT_TURN").await;
+    b.expect("YOUR_TURN").await;
+    b.send("PLACE 200 100 10").await;
+    expect_both(&mut a, &mut b, &[
+        "OK",
+        &format!("STATE 2 2 2 {ia} 100.000 100.000 10.000 {ib} 200.000 100.000 10.000"),
+    ]).await;
+    b.expect("OPPONENT_TURN").await;
+    a.expect("YOUR_TURN").await;
+
+    // Exactly on top of the other piece, and just short of touching it:
+    // both are refused and the turn stays with a.
+    a.send("SHOOT 0 1 0 100").await;
+    a.expect("ERROR OVERLAP shot would land on another piece").await;
+    a.send("SHOOT 0 1 0 80.5").await;
+    a.expect("ERROR OVERLAP shot would land on another piece").await;
+
+    // Stopping with the circles just touching is allowed.
+    a.send("SHOOT 0 1 0 80").await;
+    expect_both(&mut a, &mut b, &[
+        "OK",
+        &format!("STATE 3 3 2 {ia} 180.000 100.000 10.000 {ib} 200.000 100.000 10.000"),
+    ]).await;
+}
+
+#[tokio::test]
+async fn shots_shorter_than_the_minimum_are_refused() {
+    let addr = start_server(&["--min-shoot-distance", "5"]).await;
+    let ((mut a, ia), (mut b, ib)) = start_game(addr).await;
+
+    a.send("PLACE 100 100 10").await;
+    expect_both(&mut a, &mut b, &["OK", &format!("STATE 1 1 1 {ia} 100.000 100.000 10.000")]).await;
+    a.expect("OPPONENT_TURN").await;
+    b.expect("YOUR_TURN").await;
+    b.send("PLACE 300 300 10").await;
+    expect_both(&mut a, &mut b, &[
+        "OK",
+        &format!("STATE 2 2 2 {ia} 100.000 100.000 10.000 {ib} 300.000 300.000 10.000"),
+    ]).await;
+    b.expect("OPPONENT_TURN").await;
+    a.expect("YOUR_TURN").await;
+
+    // Below the minimum the turn is not used up.
+    a.send("SHOOT 0 3 4 4.9").await;
+    a.expect("ERROR BAD_SHOT shot too weak").await;
+
+    // At it, and above it.
+    a.send("SHOOT 0 3 4 5").await;
+    expect_both(&mut a, &mut b, &[
+        "OK",
+        &format!("STATE 3 3 2 {ia} 103.000 104.000 10.000 {ib} 300.000 300.000 10.000"),
+    ]).await;
+    a.expect("OPPONENT_TURN").await;
+    b.expect("YOUR_TURN").await;
+    b.send("SHOOT 1 1 0 20").await;
+    expect_both(&mut a, &mut b, &[
+        "OK",
+        &format!("STATE 4 4 2 {ia} 103.000 104.000 10.000 {ib} 320.000 300.000 10.000"),
+    ]).await;
+}
+
+#[tokio::test]
+async fn shoot_vel_moves_the_piece_by_the_velocity() {
+    let addr = start_server(&["--max-force", "50"]).await;
+    let ((mut a, ia), (mut b, ib)) = start_game(addr).await;
+
+    a.send("PLACE 100 100 10").await;
+    expect_both(&mut a, &mut b, &["OK", &format!("STATE 1 1 1 {ia} 100.000 100.000 10.000")]).await;
+    a.expect("OPPONENT_TURN").await;
+    b.expect("YOUR_TURN").await;
+    b.send("PLACE 300 300 10").await;
+    expect_both(&mut a, &mut b, &[
+        "OK",
+        &format!("STATE 2 2 2 {ia} 100.000 100.000 10.000 {ib} 300.000 300.000 10.000"),
+    ]).await;
+    b.expect("OPPONENT_TURN").await;
+    a.expect("YOUR_TURN").await;
+
+    // A speed over --max-force, or none at all, does not use up the turn.
+    a.send("SHOOT_VEL 0 30 40.5").await;
+    a.expect("ERROR BAD_SHOT force exceeds the maximum").await;
+    a.send("SHOOT_VEL 0 0 0").await;
+    a.expect("ERROR BAD_SHOT velocity must be non-zero").await;
+
+    // The piece moves by exactly the velocity, with no normalising.
+    a.send("SHOOT_VEL 0 30 40").await;
+    expect_both(&mut a, &mut b, &[
+        "OK",
+        &format!("STATE 3 3 2 {ia} 130.000 140.000 10.000 {ib} 300.000 300.000 10.000"),
+    ]).await;
+    a.expect("OPPONENT_TURN").await;
+    b.expect("YOUR_TURN").await;
+    b.send("SHOOT_VEL 1 -0.25 0").await;
+    expect_both(&mut a, &mut b, &[
+        "OK",
+        &format!("STATE 4 4 2 {ia} 130.000 140.000 10.000 {ib} 299.750 300.000 10.000"),
+    ]).await;
+}
+
+#[tokio::test]
+async fn shots_that_leave_the_board_are_refused() {
+    let max = f32::MAX.to_string();
+    let addr = start_server(&["--max-force", &max]).await;
+    let ((mut a, ia), (mut b, ib)) = start_game(addr).await;
+
+    a.send("PLACE 100 100 10").await;
+    expect_both(&mut a, &mut b, &["OK", &format!("STATE 1 1 1 {ia} 100.000 100.000 10.000")]).await;
+    a.expect("OPPONENT_TURN").await;
+    b.expect("YOUR_TURN").await;
+    b.send("PLACE 300 300 10").await;
+    expect_both(&mut a, &mut b, &[
+        "OK",
+        &format!("STATE 2 2 2 {ia} 100.000 100.000 10.000 {ib} 300.000 300.000 10.000"),
+    ]).await;
+    b.expect("OPPONENT_TURN").await;
+    a.expect("YOUR_TURN").await;
+
+    // As far as a float reaches is far off the board, and a negative force
+    // is no way back; neither uses up the turn.
+    a.send(&format!("SHOOT 0 1 0 {max}")).await;
+    a.expect("ERROR OUT_OF_BOUNDS piece must lie within the board").await;
+    a.send("SHOOT 0 1 0 -1e30").await;
+    a.expect("ERROR BAD_SHOT force must be positive").await;
+    a.send("SHOOT 0 -1 0 1").await;
+    expect_both(&mut a, &mut b, &[
+        "OK",
+        &format!("STATE 3 3 2 {ia} 99.000 100.000 10.000 {ib} 300.000 300.000 10.000"),
+    ]).await;
+    a.expect("OPPONENT_TURN").await;
+    b.expect("YOUR_TURN").await;
+
+    // A direction too long to square is still a direction.
+    b.send("SHOOT 1 3e19 4e19 20").await;
+    b.expect("OK").await;
+    a.recv().await;
+    let state = b.recv().await;
+    assert!(state.ends_with(&format!("{ib} 312.000 316.000 10.000")), "{state}");
+}
+
+#[tokio::test]
+async fn rules_are_announced_after_ready() {
+    let addr = start_server(&["--board-size", "300", "--max-pieces-per-player", "4"]).await;
+    let mut a = Player::connect(addr).await;
+    a.expect("WAITING").await;
+    let mut b = Player::connect(addr).await;
+    assert_eq!(a.expect_ready("READY 0 2").await, "RULES 300.000 1.000 50.000 500.000 4 -");
+    assert_eq!(b.expect_ready("READY 1 2").await, "RULES 300.000 1.000 50.000 500.000 4 -");
+    a.expect("PIECE_LIMIT 4").await;
+    b.expect("PIECE_LIMIT 4").await;
+}
+
+#[tokio::test]
+async fn shooting_needs_a_piece_and_placing_ends_with_its_phase() {
+    // In an open game there is nothing to shoot until a piece is placed.
+    let addr = start_server(&[]).await;
+    let ((mut a, _), (_b, _)) = start_game(addr).await;
+    a.send("SHOOT 0 1 0 50").await;
+    a.expect("ERROR BAD_INDEX piece index out of range").await;
+
+    let addr = start_server(&["--phase-mode", "placement-then-shoot", "--pieces-per-player", "1"]).await;
+    let mut players = join_table(addr, 2).await;
+    for (id, p) in players.iter_mut().enumerate() {
+        p.expect_ready(&format!("READY {id} 2")).await;
+        p.expect("PHASE placement").await;
+    }
+    let first = first_turn(&mut players).await;
+    let (ia, ib) = (first as u8, 1 - first as u8);
+    let [a, b] = &mut players[..] else { unreachable!() };
+    let (a, b) = if first == 0 { (a, b) } else { (b, a) };
+
+    a.send("SHOOT 0 1 0 50").await;
+    a.expect("ERROR WRONG_PHASE shooting is not allowed during the placement phase").await;
+    a.send("PLACE 100 100 10").await;
+    expect_both(a, b, &["OK", &format!("STATE 1 1 1 {ia} 100.000 100.000 10.000")]).await;
+    a.expect("OPPONENT_TURN").await;
+    b.expect("YOUR_TURN").await;
+    b.send("PLACE 300 300 10").await;
+    expect_both(a, b, &[
+        "OK",
+        &format!("STATE 2 2 2 {ia} 100.000 100.000 10.000 {ib} 300.000 300.000 10.000"),
+        "PHASE shooting",
+    ]).await;
+    b.expect("OPPONENT_TURN").await;
+    a.expect("YOUR_TURN").await;
+
+    a.send("PLACE 200 200 10").await;
+    a.expect("ERROR WRONG_PHASE the placement phase is over").await;
+    a.send("SHOOT 0 1 0 50").await;
+    expect_both(a, b, &[
+        "OK",
+        &format!("STATE 3 3 2 {ia} 150.000 100.000 10.000 {ib} 300.000 300.000 10.000"),
+    ]).await;
+}
+
+#[tokio::test]
+async fn starting_pieces_are_dealt_before_the_first_turn() {
+    let addr = start_server(&["--starting-pieces", "3"]).await;
+    let mut p0 = Player::connect(addr).await;
+    p0.expect("WAITING").await;
+    let mut p1 = Player::connect(addr).await;
+    p0.expect_ready("READY 0 2").await;
+    p1.expect_ready("READY 1 2").await;
+
+    let state = p0.recv().await;
+    p1.expect(&state).await;
+    let board = BoardState::parse(state.strip_prefix("STATE ").unwrap()).unwrap();
+    assert_eq!((board.seq, board.pieces.len()), (0, 6), "{state}");
+    for owner in 0..2 {
+        assert_eq!(board.pieces.iter().filter(|p| p.owner == owner).count(), 3, "{state}");
+    }
+    for (i, a) in board.pieces.iter().enumerate() {
+        assert!(a.x - a.radius >= 0.0 && a.x + a.radius <= 500.0, "{state}");
+        assert!(a.y - a.radius >= 0.0 && a.y + a.radius <= 500.0, "{state}");
+        for b in &board.pieces[i + 1..] {
+            assert!((a.x - b.x).hypot(a.y - b.y) >= a.radius + b.radius, "{state}");
+        }
+    }
+
+    // Then play starts as usual.
+    let turns = [p0.recv().await, p1.recv().await];
+    assert!(turns.contains(&"YOUR_TURN".to_string()) && turns.contains(&"OPPONENT_TURN".to_string()));
+}
+
+#[tokio::test]
+async fn the_first_turn_waits_for_every_ready_ack() {
+    let addr = start_server(&["--ready-ack-timeout", "5"]).await;
+    let mut p0 = Player::connect(addr).await;
+    p0.expect("WAITING").await;
+    let mut p1 = Player::connect(addr).await;
+    p0.expect_ready("READY 0 2").await;
+    p1.expect_ready("READY 1 2").await;
+
+    // One ack is not enough ...
+    p0.send("READY_ACK").await;
+    p0.expect_silence(Duration::from_millis(300)).await;
+    p1.expect_silence(Duration::from_millis(300)).await;
+
+    // ... but once everyone has acked, the turn is announced.
+    p1.send("READY_ACK").await;
+    match (p0.recv().await.as_str(), p1.recv().await.as_str()) {
+        ("YOUR_TURN", "OPPONENT_TURN") | ("OPPONENT_TURN", "YOUR_TURN") => {}
+        other => panic!("unexpected turn announcement: {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn missing_ready_acks_only_delay_the_first_turn() {
+    let addr = start_server(&["--ready-ack-timeout", "1"]).await;
+    let mut p0 = Player::connect(addr).await;
+    p0.expect("WAITING").await;
+    let mut p1 = Player::connect(addr).await;
+    p0.expect_ready("READY 0 2").await;
+    p1.expect_ready("READY 1 2").await;
+
+    // Nobody acks, so the game starts when the timeout runs out.
+    p0.expect_silence(Duration::from_millis(300)).await;
+    match (p0.recv().await.as_str(), p1.recv().await.as_str()) {
+        ("YOUR_TURN", "OPPONENT_TURN") | ("OPPONENT_TURN", "YOUR_TURN") => {}
+        other => panic!("unexpected turn announcement: {other:?}"),
+    }
+
+    // A late ack is not answered.
+    p0.send("READY_ACK").await;
+    p0.send("NAME late #n").await;
+    p0.expect("OK #n").await;
+}
+
+#[tokio::test]
+async fn tagged_commands_get_tagged_replies() {
+    let addr = start_server(&[]).await;
+    let ((mut a, ia), (mut b, ib)) = start_game(addr).await;
+
+    b.send("PLACE 100 100 10 #b1").await;
+    b.expect("ERROR #b1 NOT_YOUR_TURN not your turn").await;
+
+    // Only the sender's OK carries the tag.
+    a.send("PLACE 100 100 10 #7").await;
+    a.expect("OK #7").await;
+    b.expect("OK").await;
+    expect_both(&mut a, &mut b, &[&format!("STATE 1 1 1 {ia} 100.000 100.000 10.000")]).await;
+    a.expect("OPPONENT_TURN").await;
+    b.expect("YOUR_TURN").await;
+
+    // A retry after an error is told apart from the first attempt.
+    b.send("PLACE 110 100 10 #try-1").await;
+    b.send("NAME bob #name").await;
+    b.expect("ERROR #try-1 OVERLAP overlaps an existing piece").await;
+    b.expect("OK #name").await;
+    b.send("PLACE 300 300 20 #try-2").await;
+    b.expect("OK #try-2").await;
+    a.expect("OK").await;
+    expect_both(&mut a, &mut b, &[
+        &format!("STATE 2 2 2 {ia} 100.000 100.000 10.000 {ib} 300.000 300.000 20.000"),
+    ]).await;
+
+    // Untagged commands still get plain replies.
+    a.send("SHOOT 1 1 0 5").await;
+    a.expect("YOUR_TURN").await;
+    a.expect("ERROR NOT_YOUR_PIECE that piece does not belong to you").await;
+}
+
+#[tokio::test]
+async fn only_the_handshake_is_accepted_before_ready() {
+    let addr = start_server(&[]).await;
+    let mut p0 = Player::connect(addr).await;
+    p0.expect("WAITING").await;
+
+    p0.send("PLACE 100 100 10").await;
+    p0.expect("ERROR NOT_STARTED not started").await;
+    p0.send("RESYNC #r").await;
+    p0.expect("ERROR #r NOT_STARTED not started").await;
+    p0.send("HELLO").await;
+    p0.expect("OK").await;
+    p0.send("NAME bob #n").await;
+    p0.expect("OK #n").await;
+
+    // Nothing sent in the lobby reaches the game, but the name carries over.
+    let mut p1 = Player::connect(addr).await;
+    p0.expect_ready("READY 0 2").await;
+    p1.expect_ready("READY 1 2").await;
+    p0.recv().await;
+    p1.recv().await;
+    p0.send("RATING").await;
+    p0.expect("RATING bob 1200").await;
+    p0.send("RESYNC").await;
+    p0.expect("STATE 0 0 0 ").await;
+}
+
+#[tokio::test]
+async fn two_players_cannot_share_a_name() {
+    let addr = start_server(&["--players", "3"]).await;
+    let mut p0 = Player::connect(addr).await;
+    p0.expect("WAITING").await;
+    p0.send("NAME Bob").await;
+    p0.expect("OK").await;
+
+    // Taken in the lobby, whatever the case; the player picks another.
+    let mut p1 = Player::connect(addr).await;
+    p1.expect("WAITING").await;
+    p1.send("NAME bob #n").await;
+    p1.expect("ERROR #n NAME_TAKEN name taken").await;
+    p1.send("NAME Rob").await;
+    p1.expect("OK").await;
+
+    let mut p2 = Player::connect(addr).await;
+    for (id, p) in [&mut p0, &mut p1, &mut p2].into_iter().enumerate() {
+        p.expect_ready(&format!("READY {id} 3")).await;
+        p.recv().await;
+    }
+
+    // And taken once the game is on.
+    p2.send("NAME ROB").await;
+    p2.expect("ERROR NAME_TAKEN name taken").await;
+    p2.send("RATING").await;
+    p2.expect("ERROR NO_NAME set a name first").await;
+
+    // A player may still change the case of their own name.
+    p0.send("NAME bob").await;
+    p0.expect("OK").await;
+    p0.send("RATING").await;
+    p0.expect("RATING bob 1200").await;
+}
+
+#[tokio::test]
+async fn skins_are_passed_on_to_every_player() {
+    let addr = start_server(&[]).await;
+    let mut p0 = Player::connect(addr).await;
+    p0.expect("WAITING").await;
+    p0.send("SKIN teal #s").await;
+    p0.expect("OK #s").await;
+    p0.send("SKIN mauve").await;
+    p0.expect("ERROR NOT_STARTED not started").await;
+
+    // A skin chosen in the lobby is announced when the game starts ...
+    let mut p1 = Player::connect(addr).await;
+    p0.expect_ready("READY 0 2").await;
+    p1.expect_ready("READY 1 2").await;
+    p0.expect("SKIN 0 teal").await;
+    p1.expect("SKIN 0 teal").await;
+    p0.recv().await;
+    p1.recv().await;
+
+    // ... and one picked during it, whoever's turn it is.
+    p1.send("SKIN blue").await;
+    p1.expect("OK").await;
+    p1.expect("SKIN 1 blue").await;
+    p0.expect("SKIN 1 blue").await;
+}
+
+#[tokio::test]
+async fn broadcast_errors_tells_the_others_about_rejected_moves() {
+    let addr = start_server(&["--broadcast-errors"]).await;
+    let ((mut a, _), (mut b, _)) = start_game(addr).await;
+
+    // The others learn why, but not what was tried.
+    a.send("PLACE 9999 100 10").await;
+    a.expect("ERROR OUT_OF_BOUNDS piece must lie within the board").await;
+    b.expect("OPPONENT_ERROR piece must lie within the board").await;
+
+    // Out-of-turn attempts are not a move and stay private.
+    b.send("PLACE 100 100 10").await;
+    b.expect("ERROR NOT_YOUR_TURN not your turn").await;
+    a.send("PLACE 100 100 10").await;
+    expect_both(&mut a, &mut b, &["OK"]).await;
+}
+
+#[tokio::test]
+async fn undo_restores_the_previous_board_and_turn() {
+    let addr = start_server(&[]).await;
+    let ((mut a, ia), (mut b, ib)) = start_game(addr).await;
+
+    // Nothing has been played yet.
+    a.send("UNDO").await;
+    a.expect("ERROR NOTHING_PENDING there is no move to undo").await;
+
+    a.send("PLACE 100 100 10").await;
+    expect_both(&mut a, &mut b, &["OK", &format!("STATE 1 1 1 {ia} 100.000 100.000 10.000")]).await;
+    a.expect("OPPONENT_TURN").await;
+    b.expect("YOUR_TURN").await;
+
+    b.send("PLACE 300 300 20").await;
+    expect_both(&mut a, &mut b, &[
+        "OK",
+        &format!("STATE 2 2 2 {ia} 100.000 100.000 10.000 {ib} 300.000 300.000 20.000"),
+    ]).await;
+    b.expect("OPPONENT_TURN").await;
+    a.expect("YOUR_TURN").await;
+
+    // b takes their move back out of turn; a refuses, then agrees.
+    b.send("UNDO").await;
+    a.expect("UNDO_REQUESTED").await;
+    a.send("UNDO_DECLINE").await;
+    b.expect("UNDO_DECLINED").await;
+    b.send("UNDO").await;
+    a.expect("UNDO_REQUESTED").await;
+    a.send("UNDO_ACCEPT #u1").await;
+    a.expect("OK #u1").await;
+
+    // The board is as it was before b's move, under a new sequence number
+    // but the same tick, and it is b's turn again.
+    expect_both(&mut a, &mut b, &[&format!("STATE 3 2 1 {ia} 100.000 100.000 10.000")]).await;
+    a.expect("OPPONENT_TURN").await;
+    b.expect("YOUR_TURN").await;
+
+    // Undoing the first move leaves the history empty again.
+    a.send("UNDO").await;
+    b.expect("UNDO_REQUESTED").await;
+    b.send("UNDO_ACCEPT").await;
+    b.expect("OK").await;
+    expect_both(&mut a, &mut b, &["STATE 4 2 0 "]).await;
+    a.expect("YOUR_TURN").await;
+    b.expect("OPPONENT_TURN").await;
+    b.send("UNDO").await;
+    b.expect("ERROR NOTHING_PENDING there is no move to undo").await;
+}
+
+#[tokio::test]
+async fn stalemate_wins_the_game() {
+    // On a 4×4 board one radius-2 piece leaves no room for any other.
+    let addr = start_server(&["--board-size", "4"]).await;
+    let ((mut a, ia), (mut b, _)) = start_game(addr).await;
+
+    // The winning move is answered like any other, then the result takes
+    // the place of the turn announcement and nothing comes after it.
+    a.send("PLACE 2 2 2").await;
+    expect_both(&mut a, &mut b, &[
+        "OK",
+        &format!("STATE 1 1 1 {ia} 2.000 2.000 2.000"),
+        &format!("GAME_OVER WIN {ia}"),
+    ]).await;
+    a.expect_closed().await;
+    b.expect_closed().await;
+}
+
+#[tokio::test]
+async fn fog_reveals_only_enemies_in_sight() {
+    let addr = start_server(&["--fog-sight", "50"]).await;
+    let ((mut a, ia), (mut b, ib)) = start_game(addr).await;
+
+    a.send("PLACE 100 100 10").await;
+    a.expect("OK").await;
+    b.expect("OK").await;
+    a.expect(&format!("STATE 1 1 1 {ia} 100.000 100.000 10.000")).await;
+    b.expect(&format!("STATE 1 1 1 {ia} ? ? ?")).await;
+    a.expect("OPPONENT_TURN").await;
+    b.expect("YOUR_TURN").await;
+
+    // Within 50 of a's piece, so both sides see each other's.
+    b.send("PLACE 140 100 10").await;
+    a.expect("OK").await;
+    b.expect("OK").await;
+    let both = format!("STATE 2 2 2 {ia} 100.000 100.000 10.000 {ib} 140.000 100.000 10.000");
+    a.expect(&both).await;
+    b.expect(&both).await;
+    b.expect("OPPONENT_TURN").await;
+    a.expect("YOUR_TURN").await;
+
+    // Shooting a's piece away takes b's out of a's sight and a's out of b's.
+    a.send("SHOOT 0 0 1 200").await;
+    a.expect("OK").await;
+    b.expect("OK").await;
+    a.expect(&format!("STATE 3 3 2 {ia} 100.000 300.000 10.000 {ib} ? ? ?")).await;
+    b.expect(&format!("STATE 3 3 2 {ia} ? ? ? {ib} 140.000 100.000 10.000")).await;
+    a.expect("OPPONENT_TURN").await;
+    b.expect("YOUR_TURN").await;
+
+    // A resync gets the same filtered view.
+    b.send("RESYNC").await;
+    b.expect(&format!("STATE 3 3 2 {ia} ? ? ? {ib} 140.000 100.000 10.000")).await;
+}
+
+#[tokio::test]
+async fn disconnect_ends_the_game() {
+    let addr = start_server(&[]).await;
+    let ((a, _), (mut b, _)) = start_game(addr).await;
+
+    drop(a);
+    b.expect("DISCONNECTED").await;
+}
+
+#[tokio::test]
+async fn reset_connection_ends_the_game_promptly() {
+    let addr = start_server(&[]).await;
+    let mut a = Player::connect(addr).await;
+    a.expect("WAITING").await;
+    let stream = TcpStream::connect(addr).await.unwrap();
+    a.expect_ready("READY 0 2").await;
+    a.recv().await;
+
+    // An abortive close fails the server's reads and writes alike; either
+    // one ends the game straight away.
+    stream.set_zero_linger().unwrap();
+    drop(stream);
+    let started = std::time::Instant::now();
+    a.expect("DISCONNECTED").await;
+    assert!(started.elapsed() < Duration::from_secs(1));
+}
+
+#[tokio::test]
+async fn a_burst_of_players_is_paired_in_arrival_order() {
+    let addr = start_server(&[]).await;
+    let mut players = Vec::new();
+    for _ in 0..3 {
+        players.push(Player::connect(addr).await);
+    }
+
+    // The first two start a game; the third holds for the next one.
+    players[0].expect("WAITING").await;
+    players[0].expect_ready("READY 0 2").await;
+    players[1].expect_ready("READY 1 2").await;
+    players[2].expect("WAITING").await;
+    let mut fourth = Player::connect(addr).await;
+    players[2].expect_ready("READY 0 2").await;
+    fourth.expect_ready("READY 1 2").await;
+}
+
+#[tokio::test]
+async fn connections_that_drop_in_the_lobby_are_not_dealt_in() {
+    let (addr, capture) = start_logged_server().await;
+
+    // A scanner that hangs up at once, then a player who gives up waiting;
+    // the next two to arrive are paired with each other, not with either.
+    drop(TcpStream::connect(addr).await.unwrap());
+    left_lobby(&capture, 1).await;
+    let mut quitter = Player::connect(addr).await;
+    quitter.expect("WAITING").await;
+    drop(quitter);
+    left_lobby(&capture, 2).await;
+
+    let mut a = Player::connect(addr).await;
+    a.expect("WAITING").await;
+    let mut b = Player::connect(addr).await;
+    a.expect_ready("READY 0 2").await;
+    b.expect_ready("READY 1 2").await;
+}
+
+#[tokio::test]
+async fn server_events_can_be_captured() {
+    let (addr, capture) = start_logged_server().await;
+    let ((a, ia), (mut b, _)) = start_game(addr).await;
+
+    drop(a);
+    b.expect("DISCONNECTED").await;
+    assert!(capture.contains(Level::Info, "Game started"));
+    assert!(capture.contains(Level::Info, &format!("Player {ia} disconnected")));
+    // Capturing ignores -v: verbose lines are kept too.
+    assert!(capture.events().iter().any(|(level, _)| *level == Level::Verbose));
+}
+
+#[tokio::test]
+async fn a_player_who_stops_reading_is_dropped_without_stalling_the_game() {
+    let addr = start_server(&["--broadcast-errors"]).await;
+    let mut fast = Player::connect(addr).await;
+    fast.expect("WAITING").await;
+    let mut slow = Player::connect_slow(addr).await;
+    fast.expect_ready("READY 0 2").await;
+    slow.expect_ready("READY 1 2").await;
+
+    // Hand the turn to the fast player if need be; the slow one reads
+    // nothing from here on.
+    if slow.recv().await == "YOUR_TURN" {
+        fast.expect("OPPONENT_TURN").await;
+        slow.send("PLACE 100 100 10").await;
+        fast.expect("OK").await;
+        fast.recv().await;
+    }
+    fast.expect("YOUR_TURN").await;
+
+    // Every rejected move queues an OPPONENT_ERROR for the slow player.
+    // The fast one keeps getting answers until the slow one is cut off.
+    let flood = "PLACE 9999 100 10\n".repeat(200_000);
+    let (mut lines, mut writer) = (fast.lines, fast.writer);
+    let spam = tokio::spawn(async move { writer.write_all(flood.as_bytes()).await });
+    let mut errors = 0;
+    loop {
+        let line = timeout(LINE_TIMEOUT, lines.next_line()).await.unwrap().unwrap().unwrap();
+        if line == "DISCONNECTED" {
+            break;
+        }
+        assert_eq!(line, "ERROR OUT_OF_BOUNDS piece must lie within the board");
+        errors += 1;
+    }
+    spam.abort();
+    assert!(errors > 256, "cut off after only {errors} errors");
+}
+
+#[tokio::test]
+async fn game_client_drives_a_game() {
+    async fn next(client: &mut GameClient) -> ServerMsg {
+        timeout(LINE_TIMEOUT, client.recv()).await.unwrap().unwrap().expect("connection closed")
+    }
+
+    let addr = start_server(&[]).await;
+    let mut a = GameClient::connect(addr).await.unwrap();
+    assert!(matches!(next(&mut a).await, ServerMsg::Waiting));
+    let mut b = GameClient::connect(addr).await.unwrap();
+    for (client, id) in [(&mut a, 0), (&mut b, 1)] {
+        let msg = next(client).await;
+        assert!(matches!(msg, ServerMsg::Ready { player_id, players: 2, team: None } if player_id == id));
+        assert!(matches!(next(client).await, ServerMsg::Rules(_)));
+    }
+    let a_moves = matches!(next(&mut a).await, ServerMsg::YourTurn);
+    next(&mut b).await;
+    let (mover, waiter) = if a_moves { (&mut a, &mut b) } else { (&mut b, &mut a) };
+
+    mover.send_tagged(&ClientCmd::Place { x: 100.0, y: 100.0, radius: 10.0 }, "m1").await.unwrap();
+    assert!(matches!(next(mover).await, ServerMsg::Ok { tag: Some(t) } if t == "m1"));
+    assert!(matches!(next(waiter).await, ServerMsg::Ok { tag: None }));
+    for client in [&mut *mover, &mut *waiter] {
+        match next(client).await {
+            ServerMsg::State(board) => assert_eq!((board.seq, board.pieces.len()), (1, 1)),
+            _ => panic!("expected STATE"),
+        }
+    }
+    assert!(matches!(next(waiter).await, ServerMsg::YourTurn));
+    assert!(matches!(next(mover).await, ServerMsg::OpponentTurn));
+}
+
+#[tokio::test]
+async fn compressed_clients_see_the_same_game() {
+    async fn next(client: &mut GameClient) -> ServerMsg {
+        timeout(LINE_TIMEOUT, client.recv()).await.unwrap().unwrap().expect("connection closed")
+    }
+
+    // One player asks while waiting for the table, the other once the game
+    // is under way.
+    let addr = start_server(&[]).await;
+    let mut a = GameClient::connect(addr).await.unwrap();
+    assert!(matches!(next(&mut a).await, ServerMsg::Waiting));
+    a.send_tagged(&ClientCmd::Hello { deflate: true }, "h").await.unwrap();
+    assert!(matches!(next(&mut a).await, ServerMsg::Ok { tag: Some(t) } if t == "h"));
+    assert!(matches!(next(&mut a).await, ServerMsg::Compress));
+    let mut b = GameClient::connect(addr).await.unwrap();
+    for client in [&mut a, &mut b] {
+        assert!(matches!(next(client).await, ServerMsg::Ready { .. }));
+        assert!(matches!(next(client).await, ServerMsg::Rules(_)));
+    }
+    let a_moves = matches!(next(&mut a).await, ServerMsg::YourTurn);
+    next(&mut b).await;
+    b.send(&ClientCmd::Hello { deflate: true }).await.unwrap();
+    assert!(matches!(next(&mut b).await, ServerMsg::Ok { tag: None }));
+    assert!(matches!(next(&mut b).await, ServerMsg::Compress));
+    // Asking again changes nothing.
+    a.send_tagged(&ClientCmd::Hello { deflate: true }, "h2").await.unwrap();
+    assert!(matches!(next(&mut a).await, ServerMsg::Ok { tag: Some(t) } if t == "h2"));
+
+    let (mover, waiter) = if a_moves { (&mut a, &mut b) } else { (&mut b, &mut a) };
+    mover.send_tagged(&ClientCmd::Place { x: 100.0, y: 100.0, radius: 10.0 }, "m1").await.unwrap();
+    assert!(matches!(next(mover).await, ServerMsg::Ok { tag: Some(t) } if t == "m1"));
+    assert!(matches!(next(waiter).await, ServerMsg::Ok { tag: None }));
+    for client in [&mut *mover, &mut *waiter] {
+        match next(client).await {
+            ServerMsg::State(board) => assert_eq!((board.seq, board.pieces.len()), (1, 1)),
+            _ => panic!("expected STATE"),
+        }
+    }
+    assert!(matches!(next(waiter).await, ServerMsg::YourTurn));
+    assert!(matches!(next(mover).await, ServerMsg::OpponentTurn));
+}
+
+#[tokio::test]
+async fn chat_reaches_the_other_players_at_any_time() {
+    let addr = start_server(&["--players", "3"]).await;
+    let mut players = join_table(addr, 3).await;
+    for (id, p) in players.iter_mut().enumerate() {
+        p.expect_ready(&format!("READY {id} 3")).await;
+    }
+    let first = first_turn(&mut players).await;
+
+    let talker = (first + 1) % 3;
+    players[talker].send("CHAT good luck all #c1").await;
+    players[talker].expect("OK #c1").await;
+    for (id, p) in players.iter_mut().enumerate() {
+        if id != talker {
+            p.expect(&format!("CHAT {talker} good luck all")).await;
+        }
+    }
+}
+
+#[tokio::test]
+async fn three_players_play_down_to_the_last() {
+    let addr = start_server(&["--players", "3", "--board-size", "4"]).await;
+    let mut players = join_table(addr, 3).await;
+    for (id, p) in players.iter_mut().enumerate() {
+        p.expect_ready(&format!("READY {id} 3")).await;
+    }
+    let first = first_turn(&mut players).await;
+
+    // The board is now full: the next player is knocked out, and the one
+    // after that is stuck with only the first player left.
+    players[first].send("PLACE 2 2 2").await;
+    for p in &mut players {
+        p.expect("OK").await;
+        p.expect(&format!("STATE 1 1 1 {first} 2.000 2.000 2.000")).await;
+        p.expect(&format!("ELIMINATED {}", (first + 1) % 3)).await;
+        p.expect(&format!("GAME_OVER WIN {first}")).await;
+    }
+}
+
+#[tokio::test]
+async fn two_vs_two_plays_on_after_a_teammate_is_out() {
+    let addr = start_server(&["--players", "4", "--teams", "2", "--board-size", "4"]).await;
+    let mut players = join_table(addr, 4).await;
+    for (id, p) in players.iter_mut().enumerate() {
+        p.expect_ready(&format!("READY {id} 4 {}", id % 2)).await;
+    }
+    let first = first_turn(&mut players).await;
+    let seat = |k: usize| (first + k) % 4;
+
+    // Three pieces fill the board, leaving the fourth player with no piece
+    // and no room.
+    let mut pieces = Vec::new();
+    for (k, (x, y)) in [(1, 1), (3, 2), (1, 3)].into_iter().enumerate() {
+        players[seat(k)].send(&format!("PLACE {x} {y} 1")).await;
+        pieces.push(format!("{} {x}.000 {y}.000 1.000", seat(k)));
+        let state = format!("STATE {} {} {} {}", k + 1, k + 1, k + 1, pieces.join(" "));
+        for p in &mut players {
+            p.expect("OK").await;
+            p.expect(&state).await;
+        }
+        if k < 2 {
+            for (id, p) in players.iter_mut().enumerate() {
+                p.expect(if id == seat(k + 1) { "YOUR_TURN" } else { "OPPONENT_TURN" }).await;
+            }
+        }
+    }
+
+    // Their teammate still has a piece, so the game goes on and the turn
+    // skips straight back to the first player.
+    let out = seat(3);
+    for (id, p) in players.iter_mut().enumerate() {
+        p.expect(&format!("ELIMINATED {out}")).await;
+        p.expect(if id == first { "YOUR_TURN" } else { "OPPONENT_TURN" }).await;
+    }
+    players[out].send("DRAW_OFFER").await;
+    players[out].expect("ERROR ELIMINATED you are out of the game").await;
+
+    players[first].send("SHOOT 0 1 0 0.001").await;
+    pieces[0] = format!("{} 1.001 1.000 1.000", seat(0));
+    for p in &mut players {
+        p.expect("OK").await;
+        p.expect(&format!("STATE 4 4 3 {}", pieces.join(" "))).await;
+    }
+    players[seat(1)].expect("YOUR_TURN").await;
+}
@@ -0,0 +1,145 @@
+//! The read-only HTTP API: a dashboard sees the games the server is running.
+
+use seb_mul_game::board::{BoardState, Piece, SVG_SIZE};
+use seb_mul_game::client::GameClient;
+use seb_mul_game::http::serve_http;
+use seb_mul_game::logger::Logger;
+use seb_mul_game::protocol::{ClientCmd, ServerMsg};
+use seb_mul_game::registry::GameRegistry;
+use seb_mul_game::server::{serve_with_registry, ServerConfig};
+use serde_json::Value;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time::{sleep, timeout};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Start a game server and its HTTP API; returns their addresses.
+async fn start() -> (SocketAddr, SocketAddr) {
+    let games = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let http = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addrs = (games.local_addr().unwrap(), http.local_addr().unwrap());
+    let registry = Arc::new(GameRegistry::new());
+    let log = Arc::new(Logger::new(0));
+    tokio::spawn(serve_http(http, Arc::clone(&registry), Arc::clone(&log)));
+    tokio::spawn(serve_with_registry(games, ServerConfig::default(), registry, log));
+    addrs
+}
+
+/// Send a raw request and return the status code and body.
+async fn request(addr: SocketAddr, request: &str) -> (u16, String) {
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.write_all(request.as_bytes()).await.unwrap();
+    let mut response = String::new();
+    timeout(TIMEOUT, stream.read_to_string(&mut response)).await.unwrap().unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").expect(&response);
+    let status = head.split_whitespace().nth(1).unwrap().parse().unwrap();
+    (status, body.to_string())
+}
+
+async fn get(addr: SocketAddr, path: &str) -> (u16, Value) {
+    let (status, body) = request(addr, &format!("GET {path} HTTP/1.1\r\nHost: test\r\n\r\n")).await;
+    (status, serde_json::from_str(&body).unwrap())
+}
+
+async fn next(client: &mut GameClient) -> ServerMsg {
+    timeout(TIMEOUT, client.recv()).await.unwrap().unwrap().expect("connection closed")
+}
+
+#[tokio::test]
+async fn games_and_their_boards_are_listed() {
+    let (games, http) = start().await;
+    assert_eq!(get(http, "/games").await, (200, Value::Array(vec![])));
+
+    let mut a = GameClient::connect(games).await.unwrap();
+    a.send(&ClientCmd::Name("alice".into())).await.unwrap();
+    next(&mut a).await;
+    next(&mut a).await;
+    let mut b = GameClient::connect(games).await.unwrap();
+    for client in [&mut a, &mut b] {
+        assert!(matches!(next(client).await, ServerMsg::Ready { .. }));
+        assert!(matches!(next(client).await, ServerMsg::Rules(_)));
+    }
+    let a_moves = matches!(next(&mut a).await, ServerMsg::YourTurn);
+    next(&mut b).await;
+    let mover = if a_moves { &mut a } else { &mut b };
+    mover.send(&ClientCmd::Place { x: 100.0, y: 120.0, radius: 10.0 }).await.unwrap();
+    next(mover).await;
+    next(mover).await;
+
+    // The game publishes once it has dealt with the move.
+    let mut list = get(http, "/games").await.1;
+    for _ in 0..50 {
+        if list[0]["pieces"] == 1 {
+            break;
+        }
+        sleep(Duration::from_millis(20)).await;
+        list = get(http, "/games").await.1;
+    }
+    let turn = if a_moves { 1 } else { 0 };
+    assert_eq!(list, serde_json::json!([{ "id": 0, "players": ["alice", null], "pieces": 1, "turn": turn }]));
+
+    let (status, game) = get(http, "/games/0").await;
+    assert_eq!(status, 200);
+    assert_eq!(game["board"]["seq"], 1);
+    assert_eq!(
+        game["board"]["pieces"],
+        serde_json::json!([{ "owner": 1 - turn, "x": 100.0, "y": 120.0, "radius": 10.0 }])
+    );
+
+    let (status, svg) = request(http, "GET /games/0/board.svg HTTP/1.1\r\n\r\n").await;
+    assert_eq!(status, 200);
+    assert!(svg.starts_with("<svg") && svg.trim_end().ends_with("</svg>"), "{svg}");
+    assert_eq!(svg.matches("<circle").count(), 1, "{svg}");
+
+    // Once the game is over it is gone.
+    drop(a);
+    drop(b);
+    for _ in 0..50 {
+        if get(http, "/games").await.1 == Value::Array(vec![]) {
+            return;
+        }
+        sleep(Duration::from_millis(20)).await;
+    }
+    panic!("the finished game is still listed");
+}
+
+#[test]
+fn svg_draws_one_circle_per_piece() {
+    let piece = |index, owner, x, y| Piece { index, owner, x, y, radius: 10.0 };
+    let board = BoardState {
+        seq:    3,
+        tick:   3,
+        pieces: vec![piece(0, 0, 50.0, 50.0), piece(1, 1, 250.0, 400.0), piece(2, 0, 490.0, 10.0)],
+    };
+    let svg = board.to_svg(500.0);
+
+    assert_eq!(svg.matches("<circle").count(), 3, "{svg}");
+    for label in [">0</text>", ">1</text>", ">2</text>"] {
+        assert!(svg.contains(label), "{svg}");
+    }
+    // World coordinates are scaled to the canvas.
+    let scale = SVG_SIZE / 500.0;
+    assert!(svg.contains(&format!("cx=\"{:.2}\" cy=\"{:.2}\"", 250.0 * scale, 400.0 * scale)), "{svg}");
+    assert!(svg.contains(&format!("r=\"{:.2}\"", 10.0 * scale)), "{svg}");
+
+    // A piece out of sight is not drawn.
+    let mut fogged = board.clone();
+    fogged.pieces[1].x = f32::NAN;
+    fogged.pieces[1].y = f32::NAN;
+    assert_eq!(fogged.to_svg(500.0).matches("<circle").count(), 2);
+}
+
+#[tokio::test]
+async fn anything_else_is_refused() {
+    let (_, http) = start().await;
+    assert_eq!(get(http, "/games/7").await.0, 404);
+    assert_eq!(get(http, "/games/seven").await.0, 404);
+    assert_eq!(get(http, "/games/7/board.svg").await.0, 404);
+    assert_eq!(get(http, "/players").await.0, 404);
+    assert_eq!(request(http, "POST /games HTTP/1.1\r\n\r\n").await.0, 405);
+    assert_eq!(request(http, "nonsense\r\n\r\n").await.0, 400);
+}
@@ -0,0 +1,76 @@
+//! Client-side smoothing: blending positions between `STATE` frames and
+//! dropping predicted inputs once the server confirms them.
+
+use bevy::prelude::{Entity, Vec2};
+use seb_mul_game::board::BoardState;
+use seb_mul_game::game::{GameCommand, PredictedInputs, SnapshotBuffer};
+use std::time::Duration;
+
+fn frame(payload: &str) -> BoardState {
+    BoardState::parse(payload).unwrap()
+}
+
+fn ms(n: u64) -> Duration {
+    Duration::from_millis(n)
+}
+
+#[test]
+fn midway_between_two_frames_is_the_average() {
+    let mut buffer = SnapshotBuffer::new(Duration::ZERO);
+    buffer.push(ms(1000), frame("1 1 2 0 0 0 5 1 100 100 5"));
+    buffer.push(ms(1100), frame("2 2 2 0 10 20 5 1 100 50 5"));
+
+    let positions = buffer.interpolated_positions(ms(1050));
+    assert_eq!(positions, [(0, Vec2::new(5.0, 10.0)), (1, Vec2::new(100.0, 75.0))]);
+}
+
+#[test]
+fn render_delay_and_clamping() {
+    let mut buffer = SnapshotBuffer::new(ms(100));
+    buffer.push(ms(1000), frame("1 1 1 0 0 0 5"));
+    buffer.push(ms(1100), frame("2 2 1 0 40 0 5"));
+
+    // Rendering 100 ms behind puts 1125 a quarter of the way along.
+    assert_eq!(buffer.interpolated_positions(ms(1125)), [(0, Vec2::new(10.0, 0.0))]);
+    // Before the older frame and long after the newer one, nothing is extrapolated.
+    assert_eq!(buffer.interpolated_positions(ms(500)), [(0, Vec2::new(0.0, 0.0))]);
+    assert_eq!(buffer.interpolated_positions(ms(5000)), [(0, Vec2::new(40.0, 0.0))]);
+}
+
+#[test]
+fn new_hidden_and_stale_pieces() {
+    let mut buffer = SnapshotBuffer::new(Duration::ZERO);
+    assert!(buffer.interpolated_positions(ms(0)).is_empty());
+
+    buffer.push(ms(0), frame("1 1 1 0 0 0 5"));
+    assert_eq!(buffer.interpolated_positions(ms(0)), [(0, Vec2::new(0.0, 0.0))]);
+
+    // A piece placed in the newer frame appears where it is; one hidden by
+    // fog is left out.
+    buffer.push(ms(100), frame("2 2 3 0 20 0 5 1 50 50 5 1 ? ? ?"));
+    // A frame older than the latest changes nothing.
+    buffer.push(ms(150), frame("1 1 1 0 90 90 5"));
+    assert_eq!(
+        buffer.interpolated_positions(ms(50)),
+        [(0, Vec2::new(10.0, 0.0)), (1, Vec2::new(50.0, 50.0))],
+    );
+}
+
+#[test]
+fn confirmed_ticks_drop_predicted_inputs() {
+    let shot = |force| GameCommand::Shoot { entity: Entity::PLACEHOLDER, direction: Vec2::X, force };
+    let mut inputs = PredictedInputs::default();
+    inputs.push(3, shot(1.0));
+    inputs.push(5, shot(2.0));
+    inputs.push(9, shot(3.0));
+
+    inputs.confirm(5);
+    assert_eq!(inputs.confirmed(), 5);
+    let left: Vec<u64> = inputs.pending().map(|(tick, _)| *tick).collect();
+    assert_eq!(left, [9]);
+
+    // A late frame for an older tick confirms nothing new.
+    inputs.confirm(4);
+    assert_eq!(inputs.confirmed(), 5);
+    assert_eq!(inputs.pending().count(), 1);
+}
@@ -0,0 +1,181 @@
+//! `Logger` behaviour checked through a capturing sink.
+
+use seb_mul_game::logger::{parse_category_level, Category, Level, Logger, Sink};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn lines(events: Vec<(Level, String)>) -> Vec<String> {
+    events.into_iter().map(|(level, msg)| format!("[{level}] {msg}")).collect()
+}
+
+#[test]
+fn dedup_collapses_identical_consecutive_messages() {
+    let (log, capture) = Logger::capturing();
+    let log = log.with_dedup(Duration::from_secs(3600));
+
+    log.warn("slow client");
+    log.warn("slow client");
+    log.warn("slow client");
+    log.info("slow client"); // same text, different level: not a repeat
+    log.info("done");
+    log.info("done");
+    log.flush();
+
+    assert_eq!(lines(capture.events()), [
+        "[WARN] slow client",
+        "[WARN] … (last message repeated 2 times)",
+        "[INFO] slow client",
+        "[INFO] done",
+        "[INFO] … (last message repeated 1 time)",
+    ]);
+}
+
+#[test]
+fn dedup_reports_long_runs_every_interval() {
+    let (log, capture) = Logger::capturing();
+    let log = log.with_dedup(Duration::ZERO);
+
+    log.trace("tick");
+    log.trace("tick");
+    log.trace("tick");
+
+    assert_eq!(lines(capture.events()), [
+        "[TRCE] tick",
+        "[TRCE] … (last message repeated 1 time)",
+        "[TRCE] … (last message repeated 1 time)",
+    ]);
+}
+
+#[test]
+fn split_routes_urgent_and_detail_levels_apart() {
+    let (urgent, stderr) = Sink::capture();
+    let (detail, stdout) = Sink::capture();
+    let log = Logger::new(3).split(Level::Warn, urgent, detail);
+
+    log.warn("w");
+    log.info("i");
+    log.verbose("v");
+    log.debug("d");
+    log.trace("t");
+
+    assert_eq!(lines(stderr.events()), ["[WARN] w"]);
+    assert_eq!(lines(stdout.events()), ["[INFO] i", "[VERB] v", "[DEBG] d", "[TRCE] t"]);
+}
+
+#[test]
+fn split_through_info_keeps_info_urgent() {
+    let (urgent, stderr) = Sink::capture();
+    let (detail, stdout) = Sink::capture();
+    let log = Logger::new(1).split(Level::Info, urgent, detail);
+
+    log.warn("w");
+    log.info("i");
+    log.verbose("v");
+    log.debug("hidden at -v");
+
+    assert_eq!(lines(stderr.events()), ["[WARN] w", "[INFO] i"]);
+    assert_eq!(lines(stdout.events()), ["[VERB] v"]);
+}
+
+#[test]
+fn without_dedup_every_message_is_kept() {
+    let (log, capture) = Logger::capturing();
+    for _ in 0..3 {
+        log.warn("again");
+    }
+    assert_eq!(capture.events().len(), 3);
+}
+
+#[test]
+fn scoped_lines_carry_increasing_sequence_numbers() {
+    let (sink, capture) = Sink::capture();
+    let log = Arc::new(Logger::new(0).split(Level::Trace, sink, Sink::Stderr));
+    let game0 = log.scoped("game 0");
+    let game1 = log.scoped("game 1");
+
+    game0.info("started");
+    game1.info("started");
+    game0.verbose("hidden without -v, so not counted");
+    game0.warn("player 1 is slow");
+    log.info("unscoped");
+    game1.info("over");
+
+    assert_eq!(lines(capture.events()), [
+        "[INFO] [game 0 #1] started",
+        "[INFO] [game 1 #1] started",
+        "[WARN] [game 0 #2] player 1 is slow",
+        "[INFO] unscoped",
+        "[INFO] [game 1 #2] over",
+    ]);
+}
+
+#[test]
+fn dedup_collapses_repeats_within_a_scope() {
+    let (log, capture) = Logger::capturing();
+    let log = Arc::new(log.with_dedup(Duration::from_secs(3600)));
+    let game0 = log.scoped("game 0");
+    let game1 = log.scoped("game 1");
+
+    game0.warn("slow client");
+    game0.warn("slow client");
+    game0.warn("slow client");
+    game1.warn("slow client"); // another scope: not a repeat
+    game0.warn("slow client");
+    game0.info("over");
+    log.flush();
+
+    // Collapsed repeats take no number, so each scope's count stays unbroken.
+    assert_eq!(lines(capture.events()), [
+        "[WARN] [game 0 #1] slow client",
+        "[WARN] … (last message repeated 2 times)",
+        "[WARN] [game 1 #1] slow client",
+        "[WARN] [game 0 #2] slow client",
+        "[INFO] [game 0 #3] over",
+    ]);
+}
+
+#[test]
+fn categories_override_the_verbosity_for_their_own_messages() {
+    let (sink, capture) = Sink::capture();
+    let log = Logger::new(1)
+        .with_category(Category::Board, Level::Trace.verbosity())
+        .with_category(Category::Connection, Level::Info.verbosity())
+        .split(Level::Trace, sink, Sink::Stderr);
+
+    log.verbose("uncategorised verbose");
+    log.debug("uncategorised debug");
+    log.of(Category::Board).trace("STATE 1 1 1 0 10 10 2");
+    log.of(Category::Connection).verbose("hidden: connections stay at info");
+    log.of(Category::Connection).info("player connected");
+    log.of(Category::Protocol).verbose("no override, so -v applies");
+    log.of(Category::Protocol).debug("hidden at -v");
+
+    let log = Arc::new(log);
+    let game = log.scoped("game 0");
+    game.trace("hidden, so not counted");
+    game.of(Category::Board).trace("STATE 2 2 1 0 10 10 2");
+
+    assert_eq!(lines(capture.events()), [
+        "[VERB] uncategorised verbose",
+        "[TRCE] STATE 1 1 1 0 10 10 2",
+        "[INFO] player connected",
+        "[VERB] no override, so -v applies",
+        "[TRCE] [game 0 #1] STATE 2 2 1 0 10 10 2",
+    ]);
+}
+
+#[test]
+fn category_levels_parse_from_the_command_line() {
+    assert_eq!(parse_category_level("board=trace"), Ok((Category::Board, 3)));
+    assert_eq!(parse_category_level("Connection = INFO"), Ok((Category::Connection, 0)));
+    assert_eq!(parse_category_level("http=verbose"), Ok((Category::Http, 1)));
+    assert!(parse_category_level("board").unwrap_err().starts_with("expected <category>=<level>"));
+    assert_eq!(
+        parse_category_level("physics=debug"),
+        Err("unknown category 'physics'; expected one of connection, protocol, board, http".into())
+    );
+    assert_eq!(
+        parse_category_level("board=loud"),
+        Err("unknown level 'loud'; expected info, verbose, debug or trace".into())
+    );
+}
@@ -0,0 +1,152 @@
+//! Addresses from the command line, the listening socket and the per-IP
+//! connection limit.
+
+use clap::Parser;
+use seb_mul_game::client::ClientArgs;
+use seb_mul_game::net::{
+    bind_listener, canonical, compose_addr, compose_candidates, parse_addr, parse_candidates, Candidates,
+    ConnLimiter,
+};
+use seb_mul_game::server::ServerArgs;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+
+fn addr(s: &str) -> SocketAddr {
+    s.parse().unwrap()
+}
+
+// ── HOST, PORT AND BIND ──────────────────────────────────────────────────────
+
+#[test]
+fn a_full_address_overrides_host_and_port() {
+    assert_eq!(compose_addr(Some(addr("127.0.0.1:9000")), "::1", 1), Ok(addr("127.0.0.1:9000")));
+    assert_eq!(compose_addr(None, "127.0.0.1", 9000), Ok(addr("127.0.0.1:9000")));
+    assert_eq!(compose_addr(None, "::1", 9000), Ok(addr("[::1]:9000")));
+    assert_eq!(compose_addr(None, "localhost", 9000), Ok(addr("127.0.0.1:9000")));
+
+    let given = Candidates(vec![addr("10.0.0.1:1")]);
+    assert_eq!(compose_candidates(Some(given), "127.0.0.1", 9000), Ok(vec![addr("10.0.0.1:1")]));
+    assert_eq!(compose_candidates(None, "127.0.0.1", 9000), Ok(vec![addr("127.0.0.1:9000")]));
+}
+
+#[test]
+fn a_host_that_is_not_an_address_is_reported() {
+    let err = compose_addr(None, "not an address", 9000).unwrap_err();
+    assert!(err.starts_with("could not resolve 'not an address'"), "{err}");
+    let err = compose_candidates(None, "", 9000).unwrap_err();
+    assert!(err.contains("''"), "{err}");
+}
+
+#[test]
+fn bad_host_port_and_bind_flags_are_refused_while_parsing() {
+    for args in [
+        &["server", "--port", "70000"][..],
+        &["server", "--port", "-1"],
+        &["server", "--bind", "127.0.0.1"],
+        &["server", "--bind", "127.0.0.1:port"],
+    ] {
+        assert!(ServerArgs::try_parse_from(args).is_err(), "{args:?}");
+    }
+    assert!(ServerArgs::try_parse_from(["server", "--bind", "[::1]:0", "--port", "1"]).is_ok());
+    assert!(ClientArgs::try_parse_from(["client", "127.0.0.1:70000"]).is_err());
+    assert!(ClientArgs::try_parse_from(["client", "--host", "::1", "--port", "1"]).is_ok());
+}
+
+// ── PARSING ADDRESSES ────────────────────────────────────────────────────────
+
+#[test]
+fn addresses_and_hostnames_that_resolve_are_accepted() {
+    assert_eq!(parse_addr("127.0.0.1:7878"), Ok(addr("127.0.0.1:7878")));
+    assert_eq!(parse_addr("[::]:7878"), Ok(addr("[::]:7878")));
+    assert_eq!(parse_addr("localhost:80"), Ok(addr("127.0.0.1:80")));
+    let Candidates(all) = parse_candidates("localhost:80").unwrap();
+    assert!(all.contains(&addr("127.0.0.1:80")), "{all:?}");
+}
+
+#[test]
+fn malformed_addresses_say_what_is_expected() {
+    for bad in ["127.0.0.1", ":7878", "127.0.0.1:", "127.0.0.1:port", "127.0.0.1:70000", ""] {
+        assert_eq!(
+            parse_addr(bad),
+            Err(format!("invalid address '{bad}': expected <host>:<port>")),
+        );
+        assert!(parse_candidates(bad).is_err(), "{bad}");
+    }
+}
+
+#[test]
+fn unresolvable_hosts_fail_at_once() {
+    for bad in ["no-such-host.invalid:80", "999.1.1.1:80"] {
+        let err = parse_addr(bad).unwrap_err();
+        assert!(err.starts_with(&format!("could not resolve '{bad}'")), "{err}");
+        assert!(parse_candidates(bad).is_err(), "{bad}");
+    }
+}
+
+#[test]
+fn candidates_alternate_families_starting_with_ipv6() {
+    let mixed = Candidates(["10.0.0.1:1", "10.0.0.2:1", "[::1]:1", "10.0.0.3:1"].map(addr).to_vec());
+    assert_eq!(
+        compose_candidates(Some(mixed), "", 0),
+        Ok(["[::1]:1", "10.0.0.1:1", "10.0.0.2:1", "10.0.0.3:1"].map(addr).to_vec()),
+    );
+}
+
+// ── DUAL STACK ───────────────────────────────────────────────────────────────
+
+#[tokio::test]
+async fn a_dual_stack_listener_accepts_both_families() {
+    let listener = bind_listener(addr("0.0.0.0:0"), true).unwrap();
+    let port = listener.local_addr().unwrap().port();
+    assert!(listener.local_addr().unwrap().is_ipv6(), "the wildcard is widened to [::]");
+
+    for client in [format!("[::1]:{port}"), format!("127.0.0.1:{port}")] {
+        let stream = timeout(Duration::from_secs(5), TcpStream::connect(&client)).await.unwrap().unwrap();
+        let (_, peer) = listener.accept().await.unwrap();
+        assert_eq!(canonical(peer).ip(), stream.local_addr().unwrap().ip(), "{client}");
+    }
+}
+
+#[tokio::test]
+async fn a_v6_only_listener_refuses_ipv4() {
+    let listener = bind_listener(addr("[::]:0"), false).unwrap();
+    let port = listener.local_addr().unwrap().port();
+    TcpStream::connect(format!("[::1]:{port}")).await.unwrap();
+    assert!(TcpStream::connect(format!("127.0.0.1:{port}")).await.is_err());
+}
+
+#[tokio::test]
+async fn dual_stack_needs_an_ipv6_or_wildcard_address() {
+    let err = bind_listener(addr("127.0.0.1:0"), true).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+// ── PER-IP LIMIT ─────────────────────────────────────────────────────────────
+
+#[test]
+fn connections_beyond_the_per_ip_cap_are_refused() {
+    let limiter = ConnLimiter::new(2);
+    let (busy, other): (IpAddr, IpAddr) = ("10.0.0.1".parse().unwrap(), "10.0.0.2".parse().unwrap());
+
+    // A stand-in accept source: peers in the order they connect.
+    let arrivals = [busy, busy, busy, other, busy];
+    let mut accepted: Vec<_> = arrivals.iter().map(|&ip| limiter.try_acquire(ip)).collect();
+    let admitted: Vec<bool> = accepted.iter().map(Option::is_some).collect();
+    assert_eq!(admitted, [true, true, false, true, false]);
+
+    // Hanging up frees the slot for the next connection from that IP.
+    accepted[0] = None;
+    let _again = limiter.try_acquire(busy).unwrap();
+    assert!(limiter.try_acquire(busy).is_none());
+}
+
+#[test]
+fn ipv4_mapped_peers_count_against_their_ipv4_address() {
+    let limiter = ConnLimiter::new(1);
+    let mapped = canonical(addr("[::ffff:10.0.0.1]:5000"));
+    assert_eq!(mapped, addr("10.0.0.1:5000"));
+    let _held = limiter.try_acquire(mapped.ip()).unwrap();
+    assert!(limiter.try_acquire("10.0.0.1".parse().unwrap()).is_none());
+}
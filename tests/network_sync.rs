@@ -0,0 +1,108 @@
+//! `NetworkSyncPlugin`: a server's `STATE` frames and our own predicted
+//! moves, turned into pieces in a bevy world.
+
+use bevy::prelude::*;
+use seb_mul_game::board::{BoardState, Piece};
+use seb_mul_game::game::{
+    GamePlugin, NetworkPieces, NetworkSyncPlugin, Owner, PieceRemoved, PlayerId, Position, SentMoves, ServerInbox,
+};
+use seb_mul_game::protocol::{ClientCmd, ServerMsg};
+use std::sync::mpsc::{channel, Sender};
+use std::sync::Mutex;
+
+/// An app synced from the returned channels, told it is player 0.
+fn synced_app() -> (App, Sender<ServerMsg>, Sender<ClientCmd>) {
+    let (server, inbox) = channel();
+    let (moves, sent) = channel();
+    let mut app = App::new();
+    app.init_resource::<Time<Fixed>>()
+        .add_plugins((GamePlugin, NetworkSyncPlugin))
+        .insert_resource(ServerInbox(Mutex::new(inbox)))
+        .insert_resource(SentMoves(Mutex::new(sent)));
+    server.send(ServerMsg::Ready { player_id: 0, players: 2, team: None }).unwrap();
+    (app, server, moves)
+}
+
+fn state(seq: u64, pieces: &[(usize, u8, f32, f32)]) -> ServerMsg {
+    let pieces = pieces.iter().map(|&(index, owner, x, y)| Piece { index, owner, x, y, radius: 10.0 }).collect();
+    ServerMsg::State(BoardState { seq, tick: seq, pieces })
+}
+
+/// Where the piece with server index `index` is drawn.
+fn position_of(app: &App, index: usize) -> Vec2 {
+    let entity = app.world().resource::<NetworkPieces>().entities[&index];
+    app.world().get::<Position>(entity).unwrap().0
+}
+
+fn piece_count(app: &mut App) -> usize {
+    app.world_mut().query::<&Owner>().iter(app.world()).count()
+}
+
+#[test]
+fn pieces_follow_the_newest_frame_by_index() {
+    let (mut app, server, _) = synced_app();
+
+    // Indices need not be contiguous.
+    server.send(state(1, &[(0, 0, 100.0, 100.0), (3, 1, 300.0, 300.0)])).unwrap();
+    app.update();
+    assert_eq!(piece_count(&mut app), 2);
+    assert_eq!(position_of(&app, 0), Vec2::new(100.0, 100.0));
+    assert_eq!(position_of(&app, 3), Vec2::new(300.0, 300.0));
+    let owner = app.world().get::<Owner>(app.world().resource::<NetworkPieces>().entities[&3]).unwrap();
+    assert_eq!(owner.0, PlayerId(1));
+
+    // A frame no newer than the last is out of date.
+    server.send(state(1, &[(0, 0, 150.0, 100.0), (3, 1, 300.0, 300.0)])).unwrap();
+    app.update();
+    assert_eq!(position_of(&app, 0), Vec2::new(100.0, 100.0));
+
+    // Of two frames in one batch, the newer wins whatever the arrival order.
+    server.send(state(3, &[(0, 0, 130.0, 100.0), (3, 1, 300.0, 300.0)])).unwrap();
+    server.send(state(2, &[(0, 0, 120.0, 100.0), (3, 1, 300.0, 300.0)])).unwrap();
+    app.update();
+    assert_eq!(position_of(&app, 0), Vec2::new(130.0, 100.0));
+}
+
+#[test]
+fn a_piece_out_of_sight_keeps_its_last_known_place() {
+    let (mut app, server, _) = synced_app();
+    server.send(state(1, &[(0, 0, 100.0, 100.0), (1, 1, 300.0, 300.0)])).unwrap();
+    app.update();
+
+    server.send(state(2, &[(0, 0, 110.0, 100.0), (1, 1, f32::NAN, f32::NAN)])).unwrap();
+    app.update();
+    assert_eq!(piece_count(&mut app), 2);
+    assert_eq!(position_of(&app, 0), Vec2::new(110.0, 100.0));
+    assert_eq!(position_of(&app, 1), Vec2::new(300.0, 300.0));
+}
+
+#[test]
+fn predictions_show_until_the_server_answers() {
+    let (mut app, server, moves) = synced_app();
+    server.send(state(1, &[(0, 0, 100.0, 100.0)])).unwrap();
+    app.update();
+
+    moves.send(ClientCmd::Place { x: 200.0, y: 200.0, radius: 10.0 }).unwrap();
+    app.update();
+    assert_eq!(piece_count(&mut app), 2, "the placement shows before the server confirms it");
+
+    // The server's board replaces the prediction with its own piece.
+    server.send(state(2, &[(0, 0, 100.0, 100.0), (1, 0, 201.0, 200.0)])).unwrap();
+    app.update();
+    assert_eq!(piece_count(&mut app), 2);
+    assert_eq!(position_of(&app, 1), Vec2::new(201.0, 200.0));
+}
+
+#[test]
+fn pieces_the_server_drops_are_removed() {
+    let (mut app, server, _) = synced_app();
+    server.send(state(1, &[(0, 0, 100.0, 100.0), (1, 1, 300.0, 300.0)])).unwrap();
+    app.update();
+
+    server.send(state(2, &[(1, 1, 300.0, 300.0)])).unwrap();
+    app.update();
+    assert_eq!(piece_count(&mut app), 1);
+    let removed: Vec<_> = app.world_mut().resource_mut::<Messages<PieceRemoved>>().drain().map(|r| r.owner).collect();
+    assert_eq!(removed, [PlayerId(0)]);
+    assert!(!app.world().resource::<NetworkPieces>().entities.contains_key(&0));
+}
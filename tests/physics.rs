@@ -0,0 +1,511 @@
+//! Single-step physics checks on a bare `World`, no `App` or clock.
+
+use bevy::prelude::*;
+use seb_mul_game::game::{
+    step, step_board, step_commands, step_outcome, Board, CollisionEvent, FrictionModel, GameCommand, GameEnded,
+    Mass, Owner, PhysicsConfig, PieceRemoved, PlayerId, Position, Radius, SimControl, SimTick, TurnState, Velocity,
+    FIXED_TIMESTEP,
+};
+use seb_mul_game::rng::Rng;
+
+/// A world with one piece moving right at `speed` under `friction`.
+fn sliding_piece(friction: FrictionModel, speed: f32) -> (World, Entity) {
+    let mut world = World::new();
+    world.insert_resource(PhysicsConfig { friction, ..PhysicsConfig::default() });
+    let piece = world
+        .spawn((
+            Position(Vec2::new(50.0, 250.0)),
+            Velocity(Vec2::new(speed, 0.0)),
+            Mass(1.0),
+            Radius(5.0),
+            Owner(PlayerId(0)),
+        ))
+        .id();
+    (world, piece)
+}
+
+fn velocity(world: &World, piece: Entity) -> Vec2 {
+    world.get::<Velocity>(piece).unwrap().0
+}
+
+#[test]
+fn linear_friction_stops_a_piece_dead() {
+    // 100 u/s at 200 u/s² needs half a second: 60 steps, give or take rounding.
+    let (mut world, piece) = sliding_piece(FrictionModel::Linear(200.0), 100.0);
+    let mut steps = 0;
+    while velocity(&world, piece) != Vec2::ZERO {
+        step(&mut world, FIXED_TIMESTEP);
+        steps += 1;
+        assert!(steps <= 61, "still moving after {steps} steps");
+    }
+    assert!(steps >= 60, "stopped after only {steps} steps");
+
+    // Once stopped it stays put, with no drift backwards.
+    let at_rest = world.get::<Position>(piece).unwrap().0;
+    step(&mut world, FIXED_TIMESTEP);
+    assert_eq!(velocity(&world, piece), Vec2::ZERO);
+    assert_eq!(world.get::<Position>(piece).unwrap().0, at_rest);
+}
+
+#[test]
+fn exponential_friction_slows_but_never_stops() {
+    let (mut world, piece) = sliding_piece(FrictionModel::Exponential(0.99), 100.0);
+    for _ in 0..120 {
+        step(&mut world, FIXED_TIMESTEP);
+    }
+    let v = velocity(&world, piece).x;
+    assert!((v - 100.0 * 0.99_f32.powi(120)).abs() < 1e-3, "v = {v}");
+}
+
+#[test]
+fn no_friction_keeps_full_speed() {
+    let (mut world, piece) = sliding_piece(FrictionModel::None, 100.0);
+    for _ in 0..120 {
+        step(&mut world, FIXED_TIMESTEP);
+    }
+    assert_eq!(velocity(&world, piece), Vec2::new(100.0, 0.0));
+}
+
+#[test]
+fn a_lone_piece_moves_by_its_velocity_each_step() {
+    let (mut world, piece) = sliding_piece(FrictionModel::None, 120.0);
+    world.get_mut::<Velocity>(piece).unwrap().0.y = -60.0;
+    let position = |world: &World| world.get::<Position>(piece).unwrap().0;
+
+    step(&mut world, FIXED_TIMESTEP);
+    assert_eq!(position(&world), Vec2::new(50.0 + 120.0 * FIXED_TIMESTEP, 250.0 - 60.0 * FIXED_TIMESTEP));
+    for _ in 1..60 {
+        step(&mut world, FIXED_TIMESTEP);
+    }
+    // Half a second at (120, -60) u/s.
+    assert!(position(&world).distance(Vec2::new(110.0, 220.0)) < 1e-3, "{}", position(&world));
+    assert_eq!(world.resource::<SimTick>().0, 60);
+}
+
+#[test]
+fn tick_counts_every_step_of_a_settle() {
+    let (mut world, piece) = sliding_piece(FrictionModel::Linear(200.0), 100.0);
+    let mut last = 0;
+    while velocity(&world, piece) != Vec2::ZERO {
+        step(&mut world, FIXED_TIMESTEP);
+        let tick = world.resource::<SimTick>().0;
+        assert_eq!(tick, last + 1);
+        last = tick;
+    }
+    assert!(last >= 60, "settled after only {last} ticks");
+
+    // A paused world stands still, and so does its clock.
+    world.insert_resource(SimControl { paused: true, step_requested: false });
+    step(&mut world, FIXED_TIMESTEP);
+    assert_eq!(world.resource::<SimTick>().0, last);
+    world.resource_mut::<SimControl>().step();
+    step(&mut world, FIXED_TIMESTEP);
+    assert_eq!(world.resource::<SimTick>().0, last + 1);
+}
+
+#[test]
+fn a_collision_fires_one_event_with_a_positive_impulse() {
+    // Equal masses, one at rest just out of reach of the other.
+    let (mut world, shooter) = sliding_piece(FrictionModel::None, 100.0);
+    let target = world
+        .spawn((
+            Position(Vec2::new(60.5, 250.0)),
+            Velocity(Vec2::ZERO),
+            Mass(1.0),
+            Radius(5.0),
+            Owner(PlayerId(1)),
+        ))
+        .id();
+    for _ in 0..60 {
+        step(&mut world, FIXED_TIMESTEP);
+    }
+    let hits: Vec<CollisionEvent> = world.resource_mut::<Messages<CollisionEvent>>().drain().collect();
+    assert_eq!(hits.len(), 1, "the pieces separate after one hit");
+    let hit = &hits[0];
+    let mut pair = [hit.a, hit.b];
+    pair.sort();
+    let mut expected = [shooter, target];
+    expected.sort();
+    assert_eq!(pair, expected);
+    // (1 + 0.9) · 100 / 2 with equal unit masses.
+    assert!((hit.impulse - 95.0).abs() < 1e-3, "impulse = {}", hit.impulse);
+    assert!(hit.point.x > 55.0 && hit.point.x < 56.0 && hit.point.y == 250.0, "{:?}", hit.point);
+}
+
+/// A tight cluster of overlapping pieces spread over several archetypes, with
+/// one shot into it, run for a second.  Returns every final position in
+/// spawn order.
+fn break_shot(parallel_threshold: usize) -> Vec<Vec2> {
+    let mut world = World::new();
+    world.insert_resource(PhysicsConfig { parallel_threshold, ..PhysicsConfig::default() });
+    let mut spawned = Vec::new();
+    for i in 0..16 {
+        let at = Vec2::new(200.0 + (i % 4) as f32 * 9.0, 200.0 + (i / 4) as f32 * 9.0);
+        let mut piece = world.spawn((Position(at), Velocity(Vec2::ZERO), Mass(1.0 + i as f32 * 0.1), Radius(5.0)));
+        if i % 3 == 0 {
+            piece.insert(Owner(PlayerId(i % 2)));
+        }
+        spawned.push(piece.id());
+    }
+    world.get_mut::<Velocity>(spawned[0]).unwrap().0 = Vec2::new(900.0, 700.0);
+    for _ in 0..120 {
+        step(&mut world, FIXED_TIMESTEP);
+    }
+    spawned.iter().map(|&e| world.get::<Position>(e).unwrap().0).collect()
+}
+
+#[test]
+fn the_same_scene_always_ends_the_same_way() {
+    for threshold in [1000, 0] {
+        let first = break_shot(threshold);
+        for _ in 0..4 {
+            assert_eq!(break_shot(threshold), first, "parallel_threshold {threshold}");
+        }
+    }
+}
+
+#[test]
+fn serial_and_parallel_contacts_end_in_the_same_place() {
+    let serial = break_shot(usize::MAX);
+    let parallel = break_shot(0);
+    for (i, (s, p)) in serial.iter().zip(&parallel).enumerate() {
+        assert!(s.distance(*p) < 1e-4, "piece {i}: serial {s}, parallel {p}");
+    }
+    // The shot did break the cluster up, so there was something to compare.
+    assert!(serial[0].distance(Vec2::new(200.0, 200.0)) > 10.0, "{}", serial[0]);
+}
+
+/// Shoot a piece at `speed` into a lighter one at rest under a shatter
+/// threshold of 100 and let it play out.  Returns the world, the target and
+/// the owners of every piece removed.
+fn hit_at(speed: f32) -> (World, Entity, Vec<PlayerId>) {
+    let (mut world, _) = sliding_piece(FrictionModel::None, speed);
+    world.resource_mut::<PhysicsConfig>().destroy_impulse = 100.0;
+    let target = world
+        .spawn((
+            Position(Vec2::new(70.0, 250.0)),
+            Velocity(Vec2::ZERO),
+            Mass(0.5),
+            Radius(5.0),
+            Owner(PlayerId(1)),
+        ))
+        .id();
+    for _ in 0..60 {
+        step(&mut world, FIXED_TIMESTEP);
+    }
+    let removed = world.resource_mut::<Messages<PieceRemoved>>().drain().map(|r| r.owner).collect();
+    (world, target, removed)
+}
+
+#[test]
+fn a_hard_hit_shatters_the_lighter_piece() {
+    // Impulse 1.9·1000/3 ≈ 633: well over the threshold.
+    let (world, target, removed) = hit_at(1000.0);
+    assert!(world.get_entity(target).is_err(), "the target survived");
+    assert_eq!(removed, [PlayerId(1)]);
+}
+
+#[test]
+fn a_soft_hit_only_pushes() {
+    // Impulse 1.9·50/3 ≈ 32: under the threshold.
+    let (world, target, removed) = hit_at(50.0);
+    assert!(velocity(&world, target).x > 0.0, "the target was never hit");
+    assert!(removed.is_empty());
+}
+
+/// Queue `commands` and apply them.
+fn command(world: &mut World, commands: impl IntoIterator<Item = GameCommand>) {
+    world.init_resource::<Messages<GameCommand>>();
+    for cmd in commands {
+        world.resource_mut::<Messages<GameCommand>>().write(cmd);
+    }
+    step_commands(world);
+}
+
+fn place(at: Vec2, owner: u32) -> GameCommand {
+    GameCommand::PlacePiece { position: at, radius: 5.0, owner: PlayerId(owner) }
+}
+
+fn pieces(world: &mut World, owner: u32) -> Vec<Entity> {
+    let mut query = world.query::<(Entity, &Owner)>();
+    query.iter(world).filter(|(_, o)| o.0 == PlayerId(owner)).map(|(e, _)| e).collect()
+}
+
+#[test]
+fn losing_every_piece_ends_the_game() {
+    let mut world = World::new();
+    let opening = [place(Vec2::new(50.0, 50.0), 0), place(Vec2::new(100.0, 50.0), 1), place(Vec2::new(150.0, 50.0), 1)];
+    command(&mut world, opening);
+    step_outcome(&mut world);
+    assert_eq!(world.resource::<TurnState>().outcome, None, "both players still have pieces");
+
+    let mut doomed = pieces(&mut world, 1);
+    let last = doomed.pop().unwrap();
+    command(&mut world, doomed.into_iter().map(|entity| GameCommand::RemovePiece { entity }));
+    step_outcome(&mut world);
+    assert_eq!(world.resource::<TurnState>().outcome, None, "player 1 has a piece left");
+
+    command(&mut world, [GameCommand::RemovePiece { entity: last }]);
+    step_outcome(&mut world);
+    let outcome = world.resource::<TurnState>().outcome.expect("the game is over");
+    assert_eq!(outcome.winner, Some(PlayerId(0)));
+    let ended: Vec<_> = world.resource_mut::<Messages<GameEnded>>().drain().map(|e| e.winner).collect();
+    assert_eq!(ended, [Some(PlayerId(0))]);
+
+    // The result is only announced once.
+    step_outcome(&mut world);
+    assert_eq!(world.resource_mut::<Messages<GameEnded>>().drain().count(), 0);
+}
+
+#[test]
+fn an_enormous_shot_is_capped_at_max_speed() {
+    let mut world = World::new();
+    world.insert_resource(PhysicsConfig { max_speed: 300.0, ..PhysicsConfig::default() });
+    command(&mut world, [place(Vec2::new(250.0, 250.0), 0)]);
+    let piece = pieces(&mut world, 0)[0];
+
+    for force in [1e30, f32::MAX] {
+        command(&mut world, [GameCommand::Shoot { entity: piece, direction: Vec2::new(3.0, 4.0), force }]);
+        let v = velocity(&world, piece);
+        assert!((v - Vec2::new(180.0, 240.0)).length() < 1e-3, "force {force}: v = {v}");
+    }
+    // A shot within the cap is left alone.
+    command(&mut world, [GameCommand::Shoot { entity: piece, direction: Vec2::X, force: 100.0 }]);
+    assert_eq!(velocity(&world, piece), Vec2::new(100.0, 0.0));
+
+    // One step cannot carry the piece further than the cap allows.
+    command(&mut world, [GameCommand::Shoot { entity: piece, direction: Vec2::Y, force: 1e30 }]);
+    step(&mut world, FIXED_TIMESTEP);
+    let moved = world.get::<Position>(piece).unwrap().0 - Vec2::new(250.0, 250.0);
+    assert!(moved.length() <= 300.0 * FIXED_TIMESTEP + 1e-3, "moved {moved}");
+}
+
+#[test]
+fn area_follows_the_square_of_the_radius() {
+    let mut world = World::new();
+    for (owner, at, radius) in [(0, 100.0, 10.0), (1, 300.0, 20.0), (1, 400.0, 10.0)] {
+        world.spawn((Position(Vec2::splat(at)), Velocity(Vec2::ZERO), Mass(1.0), Radius(radius), Owner(PlayerId(owner))));
+    }
+    step_board(&mut world);
+    let area = world.resource::<Board>().area_by_owner();
+
+    // Whole cells within a radius-10 disc, and within radius-10 plus radius-20.
+    assert_eq!(area[&PlayerId(0)], 317);
+    assert_eq!(area[&PlayerId(1)], 317 + 1257);
+    let ratio = area[&PlayerId(1)] as f32 / area[&PlayerId(0)] as f32;
+    assert!((ratio - 5.0).abs() < 0.05, "ratio {ratio}, (10² + 20²) / 10² = 5");
+}
+
+#[test]
+fn each_cell_holds_at_most_one_owner_and_entity() {
+    let mut world = World::new();
+    let (a, b) = (world.spawn_empty().id(), world.spawn_empty().id());
+    let mut board = Board::new();
+    assert!(!board.occupied(10, 10));
+
+    board.set(10, 10, Some((a, PlayerId(0))));
+    assert_eq!((board.get(10, 10), board.owner_at(10, 10)), (Some(a), Some(PlayerId(0))));
+    assert!(board.occupied(10, 10) && !board.occupied(11, 10));
+
+    // A later piece takes the cell over completely.
+    board.set(10, 10, Some((b, PlayerId(1))));
+    assert_eq!((board.get(10, 10), board.owner_at(10, 10)), (Some(b), Some(PlayerId(1))));
+    assert_eq!(board.area_by_owner().get(&PlayerId(0)), None);
+
+    board.set(10, 10, None);
+    assert!(!board.occupied(10, 10));
+    assert_eq!(board.get(10, 10), None);
+
+    board.set(0, 0, Some((a, PlayerId(0))));
+    board.set(499, 499, Some((b, PlayerId(1))));
+    board.clear();
+    assert!(!board.occupied(0, 0) && !board.occupied(499, 499));
+    assert!(board.area_by_owner().is_empty());
+}
+
+#[test]
+fn a_narrow_board_gives_every_cell_its_own_slot() {
+    let mut world = World::new();
+    let mut board = Board::with_size(30, 10);
+    let cells: Vec<(i32, i32, Entity)> =
+        (0..10).flat_map(|y| (0..30).map(move |x| (x, y))).map(|(x, y)| (x, y, world.spawn_empty().id())).collect();
+    for &(x, y, e) in &cells {
+        board.set(x, y, Some((e, PlayerId(0))));
+    }
+    for &(x, y, e) in &cells {
+        assert_eq!(board.get(x, y), Some(e), "({x}, {y})");
+    }
+    assert_eq!(board.area_by_owner()[&PlayerId(0)], 300);
+}
+
+#[test]
+fn the_board_agrees_with_one_slot_per_cell() {
+    let mut world = World::new();
+    let entities: Vec<Entity> = (0..8).map(|_| world.spawn_empty().id()).collect();
+    let (width, height) = (70, 45);
+    let mut board = Board::centered(width, height);
+    let origin = board.origin();
+    let mut model: Vec<Option<(Entity, PlayerId)>> = vec![None; (width * height) as usize];
+    let slot = |x: i32, y: i32| ((y - origin.y) * width + (x - origin.x)) as usize;
+
+    let mut rng = Rng::new(838);
+    for round in 0..300 {
+        let e = entities[rng.below(8) as usize];
+        let owner = PlayerId(rng.below(3) as u32);
+        let at = Vec2::new(rng.next_f32() * 90.0 - 45.0, rng.next_f32() * 60.0 - 30.0);
+        match rng.below(10) {
+            // Pieces overlapping each other and the board's edges.
+            0..=5 => {
+                let radius = rng.next_f32() * 12.0;
+                board.stamp(e, owner, at, radius);
+                for y in origin.y..origin.y + height {
+                    for x in origin.x..origin.x + width {
+                        if (Vec2::new(x as f32, y as f32) - at).length_squared() <= radius * radius {
+                            model[slot(x, y)] = Some((e, owner));
+                        }
+                    }
+                }
+            }
+            6..=8 => {
+                let (x, y) = (at.x as i32, at.y as i32);
+                let occupant = (rng.below(2) == 0).then_some((e, owner));
+                board.set(x, y, occupant);
+                if board.grid_cell(x, y).is_some() {
+                    model[slot(x, y)] = occupant;
+                }
+            }
+            _ => {
+                board.clear();
+                model.fill(None);
+            }
+        }
+
+        for y in origin.y..origin.y + height {
+            for x in origin.x..origin.x + width {
+                let want = model[slot(x, y)];
+                assert_eq!(board.get(x, y), want.map(|o| o.0), "round {round}, ({x}, {y})");
+                assert_eq!(board.owner_at(x, y), want.map(|o| o.1), "round {round}, ({x}, {y})");
+            }
+        }
+        let area = board.area_by_owner();
+        for (owner, cells) in &area {
+            assert_eq!(model.iter().filter(|o| o.is_some_and(|o| o.1 == *owner)).count(), *cells, "round {round}");
+        }
+        assert_eq!(area.values().sum::<usize>(), model.iter().flatten().count(), "round {round}");
+    }
+}
+
+#[test]
+fn cells_off_the_board_are_ignored_rather_than_wrapped() {
+    let mut world = World::new();
+    let piece = world.spawn_empty().id();
+    let mut board = Board::with_size(30, 10);
+    for (x, y) in [(30, 0), (-1, 5), (0, 10), (0, -1), (i32::MAX, i32::MAX)] {
+        board.set(x, y, Some((piece, PlayerId(0))));
+        assert_eq!(board.get(x, y), None, "({x}, {y})");
+        assert!(!board.occupied(x, y));
+    }
+    // (30, 0) would land on (0, 1) if rows were not bounds-checked.
+    assert!(board.area_by_owner().is_empty());
+
+    board.set(29, 9, Some((piece, PlayerId(0))));
+    board.resize(5, 5);
+    assert_eq!((board.width(), board.height()), (5, 5));
+    assert_eq!(board.get(29, 9), None);
+    assert!(board.area_by_owner().is_empty(), "resizing clears the board");
+}
+
+/// A 500 × 500 board with a radius-5 piece at (100, 100) and another at
+/// (300, 100).
+fn two_targets() -> (World, Entity, Entity) {
+    let mut world = World::new();
+    let mut spawn = |x| {
+        let at = Position(Vec2::new(x, 100.0));
+        world.spawn((at, Velocity(Vec2::ZERO), Mass(1.0), Radius(5.0), Owner(PlayerId(0)))).id()
+    };
+    let (near, far) = (spawn(100.0), spawn(300.0));
+    step_board(&mut world);
+    (world, near, far)
+}
+
+#[test]
+fn a_ray_reports_the_first_piece_it_enters() {
+    let (world, near, far) = two_targets();
+    let board = world.resource::<Board>();
+
+    let (hit, point) = board.raycast(Vec2::new(10.0, 100.0), Vec2::X).unwrap();
+    assert_eq!(hit, near);
+    // The cell centred on x = 95 starts half a cell earlier.
+    assert!(point.distance(Vec2::new(94.5, 100.0)) < 1e-3, "{point}");
+
+    // From the near piece's own centre the shot passes out of it to the far one.
+    assert_eq!(board.raycast(Vec2::new(100.0, 100.0), Vec2::X).map(|h| h.0), Some(far));
+    assert_eq!(board.raycast(Vec2::new(400.0, 100.0), Vec2::NEG_X).map(|h| h.0), Some(far));
+}
+
+#[test]
+fn a_clear_line_or_a_near_miss_hits_nothing() {
+    let (world, near, _) = two_targets();
+    let board = world.resource::<Board>();
+    assert_eq!(board.raycast(Vec2::new(10.0, 300.0), Vec2::X), None);
+    assert_eq!(board.raycast(Vec2::new(10.0, 100.0), Vec2::NEG_X), None);
+    assert_eq!(board.raycast(Vec2::new(10.0, 100.0), Vec2::ZERO), None);
+
+    // The piece's top cell is at y = 105; the row above it is clear.
+    assert_eq!(board.raycast(Vec2::new(10.0, 105.0), Vec2::X).map(|h| h.0), Some(near));
+    assert_eq!(board.raycast(Vec2::new(10.0, 106.0), Vec2::X), None);
+}
+
+#[test]
+fn a_ray_from_far_off_the_board_is_clipped_to_it() {
+    let (world, near, _) = two_targets();
+    let board = world.resource::<Board>();
+
+    let (hit, point) = board.raycast(Vec2::new(-1e8, 100.0), Vec2::X).unwrap();
+    assert_eq!(hit, near);
+    assert!(point.distance(Vec2::new(94.5, 100.0)) < 1e-3, "{point}");
+    // Along a diagonal too: the ray enters at (0, 0) and runs to (100, 100).
+    assert_eq!(board.raycast(Vec2::splat(-1e8), Vec2::ONE).map(|h| h.0), Some(near));
+
+    // Rays that miss the board, or only run along beside it.
+    assert_eq!(board.raycast(Vec2::new(-1e8, 100.0), Vec2::NEG_X), None);
+    assert_eq!(board.raycast(Vec2::new(-1e8, -1e8), Vec2::X), None);
+    assert_eq!(board.raycast(Vec2::new(-10.0, 1e8), Vec2::NEG_Y), None);
+    assert_eq!(board.raycast(Vec2::splat(f32::INFINITY), Vec2::NEG_ONE), None);
+}
+
+/// A `Board::centered(500, 500)` world with one piece of radius 3 at `at`.
+fn stamped_at(at: Vec2) -> (World, Entity) {
+    let mut world = World::new();
+    world.insert_resource(Board::centered(500, 500));
+    let piece = world
+        .spawn((Position(at), Velocity(Vec2::ZERO), Mass(1.0), Radius(3.0), Owner(PlayerId(0))))
+        .id();
+    step_board(&mut world);
+    (world, piece)
+}
+
+#[test]
+fn a_negative_position_is_stamped_in_its_grid_cell() {
+    let (world, piece) = stamped_at(Vec2::new(-100.0, -240.0));
+    let board = world.resource::<Board>();
+    assert_eq!(board.grid_cell(-100, -240), Some(IVec2::new(150, 10)));
+    assert_eq!(board.get(-100, -240), Some(piece));
+    assert_eq!(board.get(-103, -240), Some(piece));
+    assert_eq!(board.get(-104, -240), None);
+    // A radius-3 disc covers 29 cells, none of them dropped.
+    assert_eq!(board.area_by_owner()[&PlayerId(0)], 29);
+}
+
+#[test]
+fn a_centred_board_ends_where_its_grid_does() {
+    let (world, piece) = stamped_at(Vec2::new(-250.0, 248.0));
+    let board = world.resource::<Board>();
+    assert_eq!(board.grid_cell(-250, 249), Some(IVec2::new(0, 499)));
+    assert_eq!(board.grid_cell(-251, 0), None);
+    assert_eq!(board.grid_cell(0, 250), None);
+    // Only the part of the piece inside -250..250 is stamped.
+    assert_eq!(board.get(-250, 249), Some(piece));
+    assert_eq!(board.get(-251, 248), None);
+    assert_eq!(board.area_by_owner()[&PlayerId(0)], 14);
+}
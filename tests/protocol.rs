@@ -0,0 +1,183 @@
+//! Single protocol lines: what they parse to and how they are written back.
+
+use seb_mul_game::board::BoardState;
+use seb_mul_game::protocol::{ClientCmd, ErrorCode, RulesInfo, ServerMsg};
+use seb_mul_game::state::{GameState, PhaseMode, Refusal, Rules};
+
+fn parse_rules(line: &str) -> Option<RulesInfo> {
+    match ServerMsg::parse(line) {
+        ServerMsg::Rules(rules) => Some(rules),
+        _ => None,
+    }
+}
+
+#[test]
+fn rules_line_parses_into_the_limits() {
+    let rules = RulesInfo {
+        board_size:   500.0,
+        min_radius:   1.0,
+        max_radius:   50.0,
+        max_force:    500.0,
+        piece_limit:  None,
+        turn_timeout: None,
+    };
+    assert_eq!(parse_rules("RULES 500 1 50 500 - -"), Some(rules));
+    assert_eq!(ServerMsg::Rules(rules).to_wire(), "RULES 500.000 1.000 50.000 500.000 - -\n");
+
+    let limited = RulesInfo {
+        board_size:   300.0,
+        min_radius:   0.5,
+        max_force:    1234.25,
+        piece_limit:  Some(4),
+        turn_timeout: Some(30),
+        ..rules
+    };
+    let wire = ServerMsg::Rules(limited).to_wire();
+    assert_eq!(wire, "RULES 300.000 0.500 50.000 1234.250 4 30\n");
+    assert_eq!(parse_rules(wire.trim_end()), Some(limited));
+    assert_eq!(
+        ServerMsg::Rules(limited).to_string(),
+        "Rules: 300×300 board, radius 0.5 to 50, force up to 1234.25, at most 4 piece(s) each, 30s per turn."
+    );
+}
+
+#[test]
+fn malformed_rules_lines_are_not_rules() {
+    for line in [
+        "RULES",
+        "RULES 500 1 50 500 -",
+        "RULES 500 1 50 500 - - -",
+        "RULES 500 1 50 - - -",
+        "RULES nan 1 50 500 - -",
+        "RULES 500 1 inf 500 - -",
+        "RULES 500 1 50 500 -1 -",
+        "RULES 500 1 50 500 4 30s",
+    ] {
+        assert_eq!(parse_rules(line), None, "{line}");
+    }
+}
+
+#[test]
+fn coordinates_survive_the_round_trip_both_ways() {
+    // A client's PLACE, however precise, goes out with three decimals ...
+    let cmd = ClientCmd::Place { x: 100.0 / 3.0, y: 250.125, radius: 7.0 };
+    let wire = cmd.to_wire();
+    assert_eq!(wire, "PLACE 33.333 250.125 7.000\n");
+
+    // ... the server stores what it read and writes it back the same way ...
+    let Some(ClientCmd::Place { x, y, radius }) = ClientCmd::parse(&wire) else { panic!("{wire:?}") };
+    assert_eq!(ClientCmd::Place { x, y, radius }.to_wire(), wire);
+    let mut state = GameState::with_rules(0, Rules::default());
+    state.place(state.turn(), x, y, radius).unwrap();
+    let line = state.state_line();
+    assert!(line.ends_with(" 33.333 250.125 7.000\n"), "{line:?}");
+
+    // ... and the client reads back exactly the values the server holds.
+    let board = BoardState::parse(line.trim_end().strip_prefix("STATE ").unwrap()).unwrap();
+    let piece = &board.pieces[0];
+    assert_eq!((piece.x, piece.y, piece.radius), (x, y, radius));
+    assert_eq!(board.wire_payload(), line.trim_end().strip_prefix("STATE ").unwrap());
+}
+
+#[test]
+fn skins_round_trip_and_stay_on_the_list() {
+    assert_eq!(ClientCmd::parse("SKIN teal"), Some(ClientCmd::Skin("teal".into())));
+    assert_eq!(ClientCmd::Skin("teal".into()).to_wire(), "SKIN teal\n");
+    for line in ["SKIN", "SKIN mauve", "SKIN Teal"] {
+        assert_eq!(ClientCmd::parse(line), None, "{line}");
+    }
+
+    let ServerMsg::Skin { player, skin } = ServerMsg::parse("SKIN 1 blue") else { panic!() };
+    assert_eq!((player, skin.as_str()), (1, "blue"));
+    assert_eq!(ServerMsg::Skin { player, skin }.to_wire(), "SKIN 1 blue\n");
+    for line in ["SKIN 1 mauve", "SKIN blue", "SKIN 1 blue green"] {
+        assert!(matches!(ServerMsg::parse(line), ServerMsg::Unknown(_)), "{line}");
+    }
+}
+
+#[test]
+fn error_lines_carry_a_code_before_the_reason() {
+    let msg = ServerMsg::Error { tag: Some("t1".into()), code: ErrorCode::Overlap, reason: "overlaps an existing piece".into() };
+    let wire = msg.to_wire();
+    assert_eq!(wire, "ERROR #t1 OVERLAP overlaps an existing piece\n");
+    let ServerMsg::Error { tag, code, reason } = ServerMsg::parse(wire.trim_end()) else { panic!("{wire:?}") };
+    assert_eq!((tag.as_deref(), code, reason.as_str()), (Some("t1"), ErrorCode::Overlap, "overlaps an existing piece"));
+
+    // Without a code this end knows, the whole text is the reason.
+    for (line, want) in [("ERROR not your turn", "not your turn"), ("ERROR SHINY_NEW_CODE why", "SHINY_NEW_CODE why")] {
+        let ServerMsg::Error { tag: None, code, reason } = ServerMsg::parse(line) else { panic!("{line}") };
+        assert_eq!((code, reason.as_str()), (ErrorCode::Other, want), "{line}");
+    }
+}
+
+/// The code of the refusal in `result`.
+fn code_of<T: std::fmt::Debug>(result: Result<T, Refusal>) -> ErrorCode {
+    result.unwrap_err().code()
+}
+
+#[test]
+fn every_game_state_refusal_has_its_code() {
+    use ErrorCode::*;
+
+    // Placing and shooting.
+    let rules = Rules {
+        board_size: 300.0,
+        min_radius: 2.0,
+        max_radius: 20.0,
+        max_force: 200.0,
+        min_shoot_distance: Some(5.0),
+        max_pieces_per_player: Some(1),
+        ..Rules::default()
+    };
+    let mut g = GameState::with_rules(0, rules);
+    let (p, q) = (g.turn(), 1 - g.turn());
+    assert_eq!(code_of(g.place(q, 100.0, 100.0, 10.0)), NotYourTurn);
+    assert_eq!(code_of(g.place(p, f32::NAN, 100.0, 10.0)), BadNumber);
+    assert_eq!(code_of(g.place(p, 100.0, 100.0, 0.0)), BadRadius);
+    assert_eq!(code_of(g.place(p, 100.0, 100.0, 1.0)), BadRadius);
+    assert_eq!(code_of(g.place(p, 100.0, 100.0, 30.0)), BadRadius);
+    assert_eq!(code_of(g.place(p, 295.0, 100.0, 10.0)), OutOfBounds);
+    g.place(p, 100.0, 100.0, 10.0).unwrap();
+    assert_eq!(code_of(g.place(q, 105.0, 100.0, 10.0)), Overlap);
+    g.place(q, 200.0, 200.0, 10.0).unwrap();
+    assert_eq!(code_of(g.place(p, 50.0, 250.0, 10.0)), PieceLimit);
+    assert_eq!(code_of(g.shoot(p, 1, 1.0, 0.0, 10.0)), NotYourPiece);
+    assert_eq!(code_of(g.shoot(p, 9, 1.0, 0.0, 10.0)), BadIndex);
+    assert_eq!(code_of(g.shoot(p, 0, f32::INFINITY, 0.0, 10.0)), BadNumber);
+    assert_eq!(code_of(g.shoot(p, 0, 1.0, 0.0, 1000.0)), BadShot);
+    assert_eq!(code_of(g.shoot(p, 0, 0.0, 0.0, 10.0)), BadShot);
+    assert_eq!(code_of(g.shoot(p, 0, 1.0, 0.0, 1.0)), BadShot);
+    assert_eq!(code_of(g.shoot_velocity(p, 0, 0.0, 0.0)), BadShot);
+    assert_eq!(code_of(g.shoot(p, 0, 1.0, 1.0, 100.0 * 2f32.sqrt())), Overlap);
+
+    // Phases.
+    let phased = Rules { phase_mode: PhaseMode::PlacementThenShoot, pieces_per_player: 1, ..Rules::default() };
+    let mut g = GameState::with_rules(0, phased);
+    let (p, q) = (g.turn(), 1 - g.turn());
+    g.place(p, 100.0, 100.0, 10.0).unwrap();
+    assert_eq!(code_of(g.shoot(q, 0, 1.0, 0.0, 10.0)), WrongPhase);
+    g.place(q, 300.0, 300.0, 10.0).unwrap();
+    assert_eq!(code_of(g.place(p, 200.0, 200.0, 10.0)), WrongPhase);
+
+    // Draws and undos, with a third player so a first answer is not the last.
+    let mut g = GameState::with_rules(0, Rules { players: 3, ..Rules::default() });
+    let (a, b) = (g.turn(), (g.turn() + 1) % 3);
+    assert_eq!(code_of(g.undo()), NothingPending);
+    assert_eq!(code_of(g.request_undo(a)), NothingPending);
+    assert_eq!(code_of(g.accept_draw(a)), NothingPending);
+    assert_eq!(code_of(g.decline_draw(a)), NothingPending);
+    g.offer_draw(a).unwrap();
+    assert_eq!(code_of(g.offer_draw(a)), AlreadyDone);
+    assert_eq!(code_of(g.offer_draw(b)), Pending);
+    g.accept_draw(b).unwrap();
+    assert_eq!(code_of(g.accept_draw(b)), AlreadyDone);
+
+    g.place(a, 100.0, 100.0, 10.0).unwrap();
+    assert_eq!(code_of(g.accept_undo(a)), NothingPending);
+    assert_eq!(code_of(g.decline_undo(a)), NothingPending);
+    g.request_undo(a).unwrap();
+    assert_eq!(code_of(g.request_undo(a)), AlreadyDone);
+    assert_eq!(code_of(g.request_undo(b)), Pending);
+    g.accept_undo(b).unwrap();
+    assert_eq!(code_of(g.accept_undo(b)), AlreadyDone);
+}
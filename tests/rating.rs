@@ -0,0 +1,36 @@
+//! Elo arithmetic and the shared rating table.
+
+use seb_mul_game::rating::{elo_update, expected_score, Ratings, DEFAULT_RATING};
+
+fn close(a: f64, b: f64) -> bool {
+    (a - b).abs() < 1e-6
+}
+
+#[test]
+fn equal_ratings_move_by_half_of_k() {
+    assert!(close(expected_score(1200.0, 1200.0), 0.5));
+    let (a, b) = elo_update(1200.0, 1200.0, 1.0, 32.0);
+    assert!(close(a, 1216.0) && close(b, 1184.0), "{a} {b}");
+    let (a, b) = elo_update(1200.0, 1200.0, 0.5, 32.0);
+    assert!(close(a, 1200.0) && close(b, 1200.0), "{a} {b}");
+}
+
+#[test]
+fn an_upset_moves_ratings_further_than_an_expected_win() {
+    // 400 points apart the favourite is expected to score 10/11.
+    assert!(close(expected_score(1600.0, 1200.0), 10.0 / 11.0));
+    assert!(close(expected_score(1200.0, 1600.0), 1.0 / 11.0));
+    let (fav, dog) = elo_update(1600.0, 1200.0, 1.0, 32.0);
+    assert!(close(fav, 1600.0 + 32.0 / 11.0) && close(dog, 1200.0 - 32.0 / 11.0), "{fav} {dog}");
+    let (fav, dog) = elo_update(1600.0, 1200.0, 0.0, 32.0);
+    assert!(close(fav, 1600.0 - 320.0 / 11.0) && close(dog, 1200.0 + 320.0 / 11.0), "{fav} {dog}");
+}
+
+#[test]
+fn ratings_start_at_the_initial_rating_and_follow_wins() {
+    let ratings = Ratings::new(32.0);
+    assert_eq!(ratings.get("ada"), DEFAULT_RATING);
+    let (winner, loser) = ratings.record_win("ada", "bob");
+    assert!(close(winner, DEFAULT_RATING + 16.0) && close(loser, DEFAULT_RATING - 16.0));
+    assert_eq!((ratings.get("ada"), ratings.get("bob")), (winner, loser));
+}
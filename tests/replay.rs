@@ -0,0 +1,98 @@
+//! `replay::verify`: re-running a game's moves from scratch, as the server
+//! does at the end of every game under `--verify-replay`; and reading boards
+//! back out of a server log.
+
+use seb_mul_game::protocol::ClientCmd;
+use seb_mul_game::replay::{logged_state, verify, ReplayCmd, ReplayRecord};
+use seb_mul_game::state::{GameState, Rules};
+
+const SEED: u64 = 5;
+
+/// Apply `cmd` for the player on turn and record it.
+fn play(state: &mut GameState, records: &mut Vec<ReplayRecord>, cmd: ReplayCmd) {
+    let player = state.turn();
+    cmd.apply(state, player).unwrap();
+    records.push(ReplayRecord::Move { player, at_ms: 0, cmd });
+}
+
+/// Play a short game of placements, a shot and an undo on a live board and
+/// return it with the records of every move.
+fn played() -> (GameState, Vec<ReplayRecord>) {
+    let mut state = GameState::with_rules(SEED, Rules::default());
+    let mut records = Vec::new();
+    play(&mut state, &mut records, ReplayCmd::Place { x: 100.0, y: 100.0, radius: 10.0 });
+    play(&mut state, &mut records, ReplayCmd::Place { x: 400.0, y: 400.0, radius: 20.0 });
+    play(&mut state, &mut records, ReplayCmd::Shoot { index: 0, dx: 1.0, dy: 0.5, force: 120.0 });
+    play(&mut state, &mut records, ReplayCmd::Place { x: 250.0, y: 60.0, radius: 15.0 });
+    state.undo().unwrap();
+    records.push(ReplayRecord::Undo { at_ms: 0 });
+    play(&mut state, &mut records, ReplayCmd::Place { x: 60.0, y: 250.0, radius: 15.0 });
+    (state, records)
+}
+
+#[test]
+fn replaying_the_moves_reproduces_the_live_board() {
+    let (live, records) = played();
+    assert_eq!(verify(SEED, &Rules::default(), &records, &live), Ok(()));
+}
+
+#[test]
+fn a_perturbed_replay_is_caught() {
+    let (live, records) = played();
+
+    // A move that lands somewhere slightly different.
+    let mut moved = records.clone();
+    let ReplayRecord::Move { cmd: ReplayCmd::Shoot { force, .. }, .. } = &mut moved[2] else { unreachable!() };
+    *force += 0.5;
+    let err = verify(SEED, &Rules::default(), &moved, &live).unwrap_err();
+    assert!(err.starts_with("piece #0 is P"), "{err}");
+
+    // A lost undo.
+    let mut skipped = records.clone();
+    skipped.retain(|r| !matches!(r, ReplayRecord::Undo { .. }));
+    let err = verify(SEED, &Rules::default(), &skipped, &live).unwrap_err();
+    assert_eq!(err, "record 5 was refused on replay: not your turn");
+
+    // A move the rules refuse when run again.
+    let mut refused = records.clone();
+    refused[1] = ReplayRecord::Move { player: 0, at_ms: 0, cmd: ReplayCmd::Place { x: 100.0, y: 100.0, radius: 10.0 } };
+    let err = verify(SEED, &Rules::default(), &refused, &live).unwrap_err();
+    assert!(err.starts_with("record 2 was refused on replay: "), "{err}");
+}
+
+#[test]
+fn moves_print_in_the_wire_syntax() {
+    let place = ReplayCmd::Place { x: 100.0, y: 120.5, radius: 10.0 };
+    let shoot = ReplayCmd::Shoot { index: 0, dx: 1.0, dy: -0.25, force: 50.0 };
+    assert_eq!(place.to_string(), "PLACE 100.000 120.500 10.000");
+    assert_eq!(shoot.to_string(), "SHOOT 0 1.000 -0.250 50.000");
+
+    // The same text the client sends for the move.
+    let sent = ClientCmd::Shoot { index: 0, dx: 1.0, dy: -0.25, force: 50.0 };
+    assert_eq!(sent.to_wire().trim_end(), shoot.to_string());
+}
+
+#[test]
+fn boards_are_read_back_from_a_trace_log() {
+    let log = "\
+[INFO] [game 0 #1] Game started (seed 5)
+[INFO] [game 1 #1] Game started (seed 6)
+[VERB] [game 0 #2] P0: PLACE 100 100 10
+[TRCE] [game 0 #3] STATE 1 1 1 0 100.000 100.000 10.000
+[TRCE] [game 1 #2] STATE 1 1 1 1 50.000 60.000 5.000
+[TRCE] [game 0 #4] STATE 2 2 2 0 100.000 100.000 10.000 1 300.000 300.000 20.000
+[DEBG] [game 0 #5] STATE 9 9 0
+[TRCE] [game 0 #6] STATE garbled
+[TRCE] STATE 3 3 0
+";
+    let game_0: Vec<_> = log.lines().filter_map(logged_state).filter(|(game, _)| *game == 0).collect();
+    assert_eq!(game_0.len(), 2);
+    assert_eq!(game_0[0].1.seq, 1);
+    assert_eq!(game_0[1].1.seq, 2);
+    let last = &game_0[1].1.pieces[1];
+    assert_eq!((last.owner, last.x, last.y, last.radius), (1, 300.0, 300.0, 20.0));
+
+    let game_1: Vec<_> = log.lines().filter_map(logged_state).filter(|(game, _)| *game == 1).collect();
+    assert_eq!(game_1.len(), 1);
+    assert_eq!((game_1[0].1.pieces[0].x, game_1[0].1.pieces[0].y), (50.0, 60.0));
+}
@@ -0,0 +1,151 @@
+//! `Session` driven over in-memory duplex pipes instead of sockets.
+
+use seb_mul_game::session::{GameLogic, Session};
+use std::time::Duration;
+use tokio::io::{duplex, AsyncReadExt, AsyncWriteExt, DuplexStream};
+use tokio::task::JoinHandle;
+use tokio::time::timeout;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Answers every message with itself.
+struct Echo;
+
+impl GameLogic for Echo {
+    type Message = Vec<u8>;
+
+    fn on_message(&mut self, mut msg: Vec<u8>) -> Option<Vec<u8>> {
+        msg.push(b'\n');
+        Some(msg)
+    }
+}
+
+/// Remembers every message and acknowledges with a running count; stays
+/// silent for `quiet`.
+#[derive(Default)]
+struct Tally {
+    seen: Vec<String>,
+}
+
+impl GameLogic for Tally {
+    type Message = Vec<u8>;
+
+    fn on_message(&mut self, msg: Vec<u8>) -> Option<Vec<u8>> {
+        self.seen.push(String::from_utf8(msg).unwrap());
+        if self.seen.last().unwrap() == "quiet" {
+            return None;
+        }
+        Some(format!("ack {}\n", self.seen.len()).into_bytes())
+    }
+}
+
+/// Start a session on one end of a pipe and hand back the other end.
+fn start<L>(logic: L, capacity: usize) -> (DuplexStream, JoinHandle<std::io::Result<L>>)
+where
+    L: GameLogic<Message = Vec<u8>> + Send + 'static,
+{
+    let (client, server) = duplex(capacity);
+    (client, tokio::spawn(Session::new(server, logic).run()))
+}
+
+async fn read_exact_str(client: &mut DuplexStream, len: usize) -> String {
+    let mut buf = vec![0u8; len];
+    timeout(TIMEOUT, client.read_exact(&mut buf)).await.unwrap().unwrap();
+    String::from_utf8(buf).unwrap()
+}
+
+/// Close our side and collect the session's final logic.
+async fn finish<L>(mut client: DuplexStream, session: JoinHandle<std::io::Result<L>>) -> L {
+    client.shutdown().await.unwrap();
+    timeout(TIMEOUT, session).await.unwrap().unwrap().unwrap()
+}
+
+#[tokio::test]
+async fn echoes_each_message() {
+    let (mut client, session) = start(Echo, 1024);
+
+    client.write_all(b"hello\n").await.unwrap();
+    assert_eq!(read_exact_str(&mut client, 6).await, "hello\n");
+    client.write_all(b"world\n").await.unwrap();
+    assert_eq!(read_exact_str(&mut client, 6).await, "world\n");
+
+    finish(client, session).await;
+}
+
+#[tokio::test]
+async fn message_split_across_reads_is_delivered_once() {
+    let (mut client, session) = start(Tally::default(), 1024);
+
+    client.write_all(b"PLACE 1").await.unwrap();
+    tokio::task::yield_now().await;
+    client.write_all(b"0 20 5\n").await.unwrap();
+    assert_eq!(read_exact_str(&mut client, 6).await, "ack 1\n");
+
+    let tally = finish(client, session).await;
+    assert_eq!(tally.seen, ["PLACE 10 20 5"]);
+}
+
+#[tokio::test]
+async fn tiny_pipe_forces_partial_reads() {
+    // A 3-byte pipe can never carry a whole message in one read.
+    let (mut client, session) = start(Echo, 3);
+    let (mut rx, mut tx) = tokio::io::split(&mut client);
+
+    let writer = async {
+        tx.write_all(b"SHOOT 0 1 0 50\nRESYNC\n").await.unwrap();
+    };
+    let reader = async {
+        let mut buf = vec![0u8; 22];
+        rx.read_exact(&mut buf).await.unwrap();
+        buf
+    };
+    let ((), echoed) = timeout(TIMEOUT, async { tokio::join!(writer, reader) }).await.unwrap();
+    assert_eq!(echoed, b"SHOOT 0 1 0 50\nRESYNC\n");
+
+    finish(client, session).await;
+}
+
+#[tokio::test]
+async fn coalesced_messages_are_split() {
+    let (mut client, session) = start(Tally::default(), 1024);
+
+    client.write_all(b"one\ntwo\nquiet\nthree\n").await.unwrap();
+    assert_eq!(read_exact_str(&mut client, 18).await, "ack 1\nack 2\nack 4\n");
+
+    let tally = finish(client, session).await;
+    assert_eq!(tally.seen, ["one", "two", "quiet", "three"]);
+}
+
+#[tokio::test]
+async fn stats_count_a_scripted_exchange() {
+    let (mut client, server) = duplex(1024);
+    let session = Session::new(server, Tally::default());
+    let stats = session.stats();
+    let session = tokio::spawn(session.run());
+
+    // "quiet" is read but gets no reply.
+    client.write_all(b"one\ntwo\nquiet\nlast\n").await.unwrap();
+    assert_eq!(read_exact_str(&mut client, 18).await, "ack 1\nack 2\nack 4\n");
+    finish(client, session).await;
+
+    assert_eq!(stats.bytes_read(), 19);
+    assert_eq!(stats.messages_in(), 4);
+    assert_eq!(stats.bytes_written(), 18);
+    assert_eq!(stats.messages_out(), 3);
+}
+
+#[tokio::test]
+async fn close_delivers_unterminated_tail_and_ends_cleanly() {
+    let (mut client, session) = start(Tally::default(), 1024);
+
+    client.write_all(b"first\nlast").await.unwrap();
+    let tally = finish(client, session).await;
+    assert_eq!(tally.seen, ["first", "last"]);
+}
+
+#[tokio::test]
+async fn close_without_data_ends_cleanly() {
+    let (client, session) = start(Tally::default(), 1024);
+    let tally = finish(client, session).await;
+    assert!(tally.seen.is_empty());
+}
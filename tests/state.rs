@@ -0,0 +1,252 @@
+//! `GameState` on its own: the rules every move is checked against.
+
+use seb_mul_game::protocol::{GameResult, Phase};
+use seb_mul_game::rng::Rng;
+use seb_mul_game::state::{GameState, PhaseMode, Refusal, Rules, StalemateRule};
+
+/// A two-player game under `rules` with player 0 on turn.
+fn game(rules: Rules) -> GameState {
+    let mut seed = 0;
+    loop {
+        let state = GameState::with_rules(seed, rules.clone());
+        if state.turn() == 0 {
+            return state;
+        }
+        seed += 1;
+    }
+}
+
+#[test]
+fn radius_is_capped_at_max_radius() {
+    let rules = Rules { max_radius: 20.0, ..Rules::default() };
+    assert_eq!(game(rules.clone()).place(0, 100.0, 100.0, 19.999), Ok(()));
+    assert_eq!(game(rules.clone()).place(0, 100.0, 100.0, 20.0), Ok(()));
+    assert_eq!(game(rules).place(0, 100.0, 100.0, 20.001), Err(Refusal::RadiusTooLarge));
+}
+
+#[test]
+fn force_is_capped_at_max_force() {
+    let rules = Rules { max_force: 100.0, ..Rules::default() };
+    let shot = |force: f32| {
+        let mut state = game(rules.clone());
+        state.place(0, 100.0, 100.0, 10.0).unwrap();
+        state.place(1, 400.0, 400.0, 10.0).unwrap();
+        state.shoot(0, 0, 1.0, 0.0, force)
+    };
+    assert_eq!(shot(99.999), Ok(()));
+    assert_eq!(shot(100.0), Ok(()));
+    assert_eq!(shot(100.001), Err(Refusal::ForceTooLarge));
+}
+
+#[test]
+fn force_must_be_positive() {
+    let mut state = game(Rules::default());
+    state.place(0, 100.0, 100.0, 10.0).unwrap();
+    state.place(1, 400.0, 400.0, 10.0).unwrap();
+    assert_eq!(state.shoot(0, 0, 1.0, 0.0, 0.0), Err(Refusal::ForceNotPositive));
+    assert_eq!(state.shoot(0, 0, 1.0, 0.0, -1e30), Err(Refusal::ForceNotPositive));
+    assert_eq!(state.pieces()[0].x, 100.0);
+}
+
+#[test]
+fn shots_must_land_on_the_board() {
+    let mut state = game(Rules::default());
+    state.place(0, 100.0, 100.0, 10.0).unwrap();
+    state.place(1, 400.0, 400.0, 10.0).unwrap();
+    // 500 wide: the piece may end touching the edge, but not past it.
+    assert_eq!(state.shoot(0, 0, 1.0, 0.0, 390.001), Err(Refusal::OffBoard));
+    assert_eq!(state.shoot(0, 0, -1.0, 0.0, 90.001), Err(Refusal::OffBoard));
+    assert_eq!(state.shoot(0, 0, 1.0, 0.0, 390.0), Ok(()));
+    assert_eq!(state.pieces()[0].x, 490.0);
+}
+
+/// Player 0 with a piece at (100, 100) and player 1 with one at (300, 300),
+/// player 0 on turn.
+fn two_pieces() -> GameState {
+    let mut state = game(Rules::default());
+    state.place(0, 100.0, 100.0, 10.0).unwrap();
+    state.place(1, 300.0, 300.0, 10.0).unwrap();
+    state
+}
+
+#[test]
+fn placing_out_of_turn_is_refused() {
+    let mut state = game(Rules::default());
+    assert_eq!(state.place(1, 100.0, 100.0, 10.0), Err(Refusal::NotYourTurn));
+    assert!(state.pieces().is_empty());
+    assert_eq!(state.turn(), 0);
+}
+
+#[test]
+fn placing_on_another_piece_is_refused() {
+    let mut state = two_pieces();
+    assert_eq!(state.place(0, 315.0, 300.0, 10.0), Err(Refusal::Overlap));
+    // Touching is not overlapping.
+    assert_eq!(state.place(0, 320.0, 300.0, 10.0), Ok(()));
+}
+
+#[test]
+fn shooting_an_opponents_piece_is_refused() {
+    let mut state = two_pieces();
+    assert_eq!(state.shoot(0, 1, 1.0, 0.0, 10.0), Err(Refusal::NotYourPiece));
+    assert_eq!((state.pieces()[1].x, state.turn()), (300.0, 0));
+}
+
+#[test]
+fn shooting_a_missing_piece_is_refused() {
+    let mut state = two_pieces();
+    assert_eq!(state.shoot(0, 2, 1.0, 0.0, 10.0), Err(Refusal::BadIndex));
+    assert_eq!(state.shoot(0, usize::MAX, 1.0, 0.0, 10.0), Err(Refusal::BadIndex));
+}
+
+#[test]
+fn shooting_without_a_direction_is_refused() {
+    let mut state = two_pieces();
+    assert_eq!(state.shoot(0, 0, 0.0, 0.0, 10.0), Err(Refusal::NoDirection));
+    assert_eq!(state.shoot(0, 0, 1e-30, 0.0, 10.0), Err(Refusal::NoDirection));
+    assert_eq!(state.turn(), 0);
+}
+
+#[test]
+fn no_sequence_of_moves_leaves_pieces_overlapping() {
+    for seed in 0..200 {
+        let mut rng = Rng::new(seed);
+        let mut state = GameState::with_rules(seed, Rules { board_size: 200.0, max_radius: 30.0, ..Rules::default() });
+        for _ in 0..60 {
+            let player = state.turn();
+            let mut coord = || rng.next_f32() * 200.0;
+            let (x, y, r) = (coord(), coord(), coord() / 6.0);
+            let index = rng.below(state.pieces().len() as u64 + 1) as usize;
+            let (dx, dy, force) = (rng.next_f32() - 0.5, rng.next_f32() - 0.5, rng.next_f32() * 100.0);
+            // Refusals are expected; only what is accepted is checked.
+            let _ = if rng.below(3) == 0 { state.shoot(player, index, dx, dy, force) } else { state.place(player, x, y, r) };
+            assert_eq!(state.overlapping_pair(), None, "seed {seed}");
+            assert_eq!(state.check_invariants(), Ok(()), "seed {seed}");
+        }
+        assert!(state.pieces().len() > 5, "seed {seed} placed almost nothing");
+    }
+}
+
+#[test]
+fn seq_counts_accepted_moves_only() {
+    let mut state = game(Rules::default());
+    assert_eq!(state.seq(), 0);
+    state.place(0, 100.0, 100.0, 10.0).unwrap();
+    assert_eq!(state.seq(), 1);
+    assert!(state.place(0, 300.0, 300.0, 10.0).is_err());
+    assert!(state.place(1, 105.0, 100.0, 10.0).is_err());
+    assert_eq!(state.seq(), 1);
+    state.place(1, 300.0, 300.0, 10.0).unwrap();
+    state.shoot(0, 0, 1.0, 0.0, 10.0).unwrap();
+    assert_eq!(state.seq(), 3);
+    assert!(state.state_line().starts_with("STATE 3 "), "{}", state.state_line());
+}
+
+#[test]
+fn shooting_starts_once_everyone_has_placed_their_share() {
+    let phased = Rules { phase_mode: PhaseMode::PlacementThenShoot, pieces_per_player: 2, ..Rules::default() };
+    let mut state = game(phased);
+    for (owner, x) in [(0, 50.0), (1, 150.0), (0, 250.0)] {
+        state.place(owner, x, 100.0, 10.0).unwrap();
+        assert_eq!(state.phase(), Phase::Placement);
+    }
+    state.place(1, 350.0, 100.0, 10.0).unwrap();
+    assert_eq!(state.phase(), Phase::Shooting);
+    assert_eq!(state.place(0, 450.0, 100.0, 10.0), Err(Refusal::PlacementOver));
+    assert_eq!(state.pieces_owned(0), 2);
+}
+
+#[test]
+fn dealt_pieces_count_towards_the_placement_quota() {
+    let dealt = |starting_pieces| Rules {
+        phase_mode: PhaseMode::PlacementThenShoot,
+        pieces_per_player: 2,
+        starting_pieces,
+        ..Rules::default()
+    };
+    let mut state = game(dealt(1));
+    assert_eq!(state.phase(), Phase::Placement);
+    state.place(0, 20.0, 20.0, 10.0).unwrap();
+    state.place(1, 480.0, 480.0, 10.0).unwrap();
+    assert_eq!(state.phase(), Phase::Shooting);
+
+    // With the quota dealt out the first player is not left unable to move.
+    let state = game(dealt(2));
+    assert_eq!(state.phase(), Phase::Shooting);
+    assert!(state.has_legal_move(0));
+    assert_eq!(state.stalemate(), None);
+}
+
+/// A 40×40 board that only has room for four pieces of the minimum size.
+fn cramped(stalemate: StalemateRule) -> Rules {
+    Rules { board_size: 40.0, min_radius: 10.0, max_radius: 20.0, stalemate, ..Rules::default() }
+}
+
+#[test]
+fn a_crowded_board_leaves_a_player_without_pieces_stuck() {
+    let mut state = game(cramped(StalemateRule::Loss));
+    state.place(0, 20.0, 20.0, 20.0).unwrap();
+    assert!(!state.has_legal_move(1));
+    assert!(state.has_legal_move(0), "a piece to shoot is always a move");
+    assert_eq!(state.stalemate(), Some(GameResult::Win(0)));
+
+    let mut state = game(cramped(StalemateRule::Draw));
+    state.place(0, 20.0, 20.0, 20.0).unwrap();
+    assert_eq!(state.stalemate(), Some(GameResult::Draw));
+}
+
+#[test]
+fn one_gap_is_enough_to_place_in() {
+    // While placing, only room on the board counts as a move.
+    let phased = Rules { phase_mode: PhaseMode::PlacementThenShoot, ..cramped(StalemateRule::Loss) };
+    let mut state = game(phased);
+    state.place(0, 10.0, 10.0, 10.0).unwrap();
+    state.place(1, 30.0, 10.0, 10.0).unwrap();
+    state.place(0, 10.0, 30.0, 10.0).unwrap();
+    // Only the corner at (30, 30) is free, and exactly big enough.
+    assert!(state.has_legal_move(1));
+    assert_eq!(state.stalemate(), None);
+    state.place(1, 30.0, 30.0, 10.0).unwrap();
+    assert_eq!(state.phase(), Phase::Placement);
+    assert!(!state.has_legal_move(0));
+    assert_eq!(state.stalemate(), Some(GameResult::Win(1)));
+}
+
+#[test]
+fn a_packed_full_size_board_is_searched_for_its_one_gap() {
+    // Radius-10 pieces 20 apart leave gaps too small for a radius-5 piece,
+    // except where the one at (250, 250) is missing.
+    let packed = Rules {
+        min_radius: 5.0,
+        phase_mode: PhaseMode::PlacementThenShoot,
+        pieces_per_player: 1000,
+        ..Rules::default()
+    };
+    let mut state = game(packed);
+    let spots = (0..25).flat_map(|i| (0..25).map(move |j| (10.0 + 20.0 * i as f32, 10.0 + 20.0 * j as f32)));
+    for (x, y) in spots.filter(|&spot| spot != (250.0, 250.0)) {
+        state.place(state.turn(), x, y, 10.0).unwrap();
+    }
+    assert!(state.has_legal_move(state.turn()));
+    assert_eq!(state.stalemate(), None);
+
+    state.place(state.turn(), 250.0, 250.0, 10.0).unwrap();
+    assert!(!state.has_legal_move(state.turn()));
+    assert!(state.stalemate().is_some());
+}
+
+#[test]
+fn the_piece_budget_stops_one_player_while_the_other_places_on() {
+    let mut state = game(Rules { max_pieces_per_player: Some(2), ..Rules::default() });
+    state.place(0, 50.0, 50.0, 10.0).unwrap();
+    state.place(1, 450.0, 50.0, 10.0).unwrap();
+    state.place(0, 50.0, 150.0, 10.0).unwrap();
+    state.shoot(1, 1, 0.0, 1.0, 50.0).unwrap();
+    assert_eq!((state.placements_left(0), state.placements_left(1)), (Some(0), Some(1)));
+
+    assert_eq!(state.place(0, 50.0, 250.0, 10.0), Err(Refusal::PieceLimit));
+    assert_eq!((state.turn(), state.pieces_owned(0)), (0, 2));
+    state.shoot(0, 0, 1.0, 0.0, 50.0).unwrap();
+    state.place(1, 450.0, 250.0, 10.0).unwrap();
+    assert_eq!(state.placements_left(1), Some(0));
+}